@@ -0,0 +1,45 @@
+#import bevy_pbr::mesh_view_bindings
+
+// Diagonal hatches over the overlapping parts of the pieces. The lines are spaced
+// `spacing` pixels apart in world coordinates, so that they do not move with the pieces.
+struct HatchMaterial {
+    color: vec4<f32>,
+    spacing: f32,
+    direction: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> uni: HatchMaterial;
+
+// fraction of the spacing covered by a line
+let LINE_RATIO: f32 = 0.35;
+
+// // Converts a color from sRGB gamma to linear light gamma
+fn toLinear(sRGB: vec4<f32>) -> vec4<f32>
+{
+    let cutoff = vec4<f32>(sRGB < vec4<f32>(0.04045));
+    let higher = pow((sRGB + vec4<f32>(0.055))/vec4<f32>(1.055), vec4<f32>(2.4));
+    let lower = sRGB/vec4<f32>(12.92);
+    return mix(higher, lower, cutoff);
+}
+
+@fragment
+fn fragment(
+    @builtin(position) position: vec4<f32>,
+    #import bevy_sprite::mesh2d_vertex_output
+) -> @location(0) vec4<f32> {
+
+    var color = uni.color;
+
+    // distance across the lines, in pixels
+    let across = (world_position.x + uni.direction * world_position.y) * 0.70710678;
+    let spacing = max(uni.spacing, 2.0);
+    let distance = abs(fract(across / spacing) - 0.5) * spacing;
+
+    // anti-aliased lines: the alpha falls off over one pixel
+    let half_width = spacing * LINE_RATIO * 0.5;
+    let alpha = 1.0 - smoothstep(half_width - 0.5, half_width + 0.5, distance);
+
+    color.a = color.a * alpha;
+    return toLinear(color);
+}
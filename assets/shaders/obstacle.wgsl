@@ -0,0 +1,35 @@
+#import bevy_pbr::mesh_view_bindings
+
+struct ObstacleMaterial {
+    color: vec4<f32>, 
+};
+
+@group(1) @binding(0)
+var<uniform> uni: ObstacleMaterial;
+
+// // Converts a color from sRGB gamma to linear light gamma
+fn toLinear(sRGB: vec4<f32>) -> vec4<f32>
+{
+    let cutoff = vec4<f32>(sRGB < vec4<f32>(0.04045));
+    let higher = pow((sRGB + vec4<f32>(0.055))/vec4<f32>(1.055), vec4<f32>(2.4));
+    let lower = sRGB/vec4<f32>(12.92);
+    return mix(higher, lower, cutoff);
+}
+
+@fragment
+fn fragment(
+    @builtin(position) position: vec4<f32>,
+    #import bevy_sprite::mesh2d_vertex_output
+) -> @location(0) vec4<f32> {
+
+    // diagonal hatches, so that obstacles are not mistaken for pieces
+    let hatch_width = 12.0;
+    var color = uni.color;
+
+    if (position.x + position.y) % hatch_width < hatch_width / 3.0 {
+        let hatch_color = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        color = mix(color, hatch_color, 0.35);
+    }
+
+    return toLinear(color);
+}
\ No newline at end of file
@@ -1,19 +1,26 @@
 #import bevy_pbr::mesh_view_bindings
 
-// This uniform is not needed here, as color attributes are being sent.
-// But in the future, the uniform will be used to highlight selected 
-// fill meshes
+// The color of the piece, with its highlights and the pattern drawn over it. The uvs
+// are in pixels, in the coordinates of the piece before it was cut.
 struct PolygonMaterial {
     color: vec4<f32>, 
     show_com: f32,
     selected: f32,
     is_intersecting: f32,
+    intersecting_color: vec4<f32>,
+    selected_color: vec4<f32>,
+    // 0: flat, 1: felt, 2: paper, 3: wood, 4: texture
+    pattern: f32,
+    pattern_tile_size: f32,
 };
 
 @group(1) @binding(0)
 var<uniform> uni: PolygonMaterial;
 
-
+@group(1) @binding(1)
+var pattern_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var pattern_sampler: sampler;
 
 
 
@@ -26,6 +33,54 @@ fn toLinear(sRGB: vec4<f32>) -> vec4<f32>
     return mix(higher, lower, cutoff);
 }
 
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+// value noise, between 0 and 1
+fn noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    return mix(
+        mix(hash(i), hash(i + vec2<f32>(1.0, 0.0)), u.x),
+        mix(hash(i + vec2<f32>(0.0, 1.0)), hash(i + vec2<f32>(1.0, 1.0)), u.x),
+        u.y
+    );
+}
+
+fn fbm(p: vec2<f32>) -> f32 {
+    var value = 0.0;
+    var amplitude = 0.5;
+    var q = p;
+    for (var k = 0; k < 4; k = k + 1) {
+        value = value + amplitude * noise(q);
+        q = q * 2.0;
+        amplitude = amplitude * 0.5;
+    }
+    return value;
+}
+
+// brightness of the procedural patterns, around 1
+fn pattern_shade(pattern: f32, uv: vec2<f32>) -> f32 {
+    if pattern > 0.5 && pattern < 1.5 {
+        // felt: fine fuzz
+        return 0.88 + 0.24 * fbm(uv * 0.4);
+    }
+    if pattern > 1.5 && pattern < 2.5 {
+        // paper: large blotches and long fibers
+        let fibers = noise(uv * vec2<f32>(0.9, 0.15));
+        return 0.92 + 0.1 * fbm(uv * 0.02) + 0.06 * fibers;
+    }
+    if pattern > 2.5 && pattern < 3.5 {
+        // wood: wavy rings along the x axis, with a fine grain
+        let rings = (uv.y + fbm(uv * vec2<f32>(0.01, 0.04)) * 40.0) / 14.0;
+        let grain = noise(uv * vec2<f32>(0.05, 1.0));
+        return 0.85 + 0.12 * sin(rings * 6.2831) + 0.06 * grain;
+    }
+    return 1.0;
+}
+
 @fragment
 fn fragment(
     @builtin(position) position: vec4<f32>,
@@ -35,14 +90,20 @@ fn fragment(
     let plaid_width = 10.0;
     var color = uni.color;
 
+    // sampled outside of the branches, which need uniform control flow
+    let texel = textureSample(pattern_texture, pattern_sampler, fract(uv / uni.pattern_tile_size));
+    if uni.pattern > 3.5 {
+        color = vec4<f32>(color.rgb * texel.rgb, color.a * texel.a);
+    } else {
+        color = vec4<f32>(color.rgb * pattern_shade(uni.pattern, uv), color.a);
+    }
+
     if uni.is_intersecting > 0.5 {
-        let intersecting_color = vec4<f32>(1.0, 0.0, 0.0, 0.25);
-        color = mix(color, intersecting_color, 0.8);
+        color = mix(color, uni.intersecting_color, 0.8);
     }
 
     if uni.selected > 0.5 && position.x % plaid_width < plaid_width / 2.0 {
-        let selector_color = vec4<f32>(1.0, 1.0, 0.0, 0.5);
-        color = mix(color, selector_color, 0.2);
+        color = mix(color, uni.selected_color, 0.2);
     }
     
     if  uni.show_com > 0.5 {
@@ -52,4 +113,4 @@ fn fragment(
     }
 
     return toLinear(color);
-}
\ No newline at end of file
+}
@@ -0,0 +1,783 @@
+use bevy::input::gamepad::GamepadButtonType;
+use bevy::prelude::*;
+
+use crate::gamepad::{GamepadInput, GAMEPAD_BUTTONS};
+
+use serde::{Deserialize, Serialize};
+
+use std::io::{Read, Write};
+
+// the bindings are saved next to the executable, like the meshes in assets/meshes
+pub const BINDINGS_FILE: &str = "bindings.json";
+
+// Modifier keys must be in the exact same state as in the chord (held or not held),
+// unless the binding has `any_modifiers` set. Other held keys are only checked if they
+// are part of the chord.
+pub const MODIFIER_KEYS: [KeyCode; 3] = [KeyCode::LShift, KeyCode::LControl, KeyCode::Space];
+
+// Everything that can be bound to a chord. direct_action(..) turns these into an Action,
+// using the cursor position and the current state of the game (making a cut, etc.)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputAction {
+    Translate,
+    Rotate,
+    RotateLeft,
+    RotateRight,
+    RotateFast,
+    StartCut,
+    CancelCut,
+    Select,
+    SelectKeep,
+    RevertToInit,
+    DeleteSelected,
+    DeleteAll,
+    DeleteTarget,
+    ToggleGrid,
+    QuickSave,
+    SaveOneDialog,
+    LoadDialog,
+    LoadTarget,
+    QuickLoad,
+    TurnPolyIntoTarget,
+    StartMakingPolygon,
+    EndMakingPolygon,
+    EndSegment,
+    DeleteMakingPoly,
+    AddPoint,
+    MovePathPoint,
+    NextPiece,
+    PreviousPiece,
+    NudgeUp,
+    NudgeDown,
+    NudgeLeft,
+    NudgeRight,
+    FineStep,
+    RotateStepLeft,
+    RotateStepRight,
+    KeyboardCut,
+    ConfirmCut,
+    Flip,
+    ToggleSnapping,
+    ToggleMomentum,
+    ZoomIn,
+    ZoomOut,
+    Pan,
+    FitLevel,
+    ToggleFullscreen,
+}
+
+impl InputAction {
+    // the actions shown in the controls page of the game. The others are level-making tools
+    pub const PLAYER_ACTIONS: [InputAction; 29] = [
+        InputAction::Translate,
+        InputAction::Rotate,
+        InputAction::RotateLeft,
+        InputAction::RotateRight,
+        InputAction::RotateFast,
+        InputAction::StartCut,
+        InputAction::CancelCut,
+        InputAction::Select,
+        InputAction::SelectKeep,
+        InputAction::RevertToInit,
+        InputAction::NextPiece,
+        InputAction::PreviousPiece,
+        InputAction::NudgeUp,
+        InputAction::NudgeDown,
+        InputAction::NudgeLeft,
+        InputAction::NudgeRight,
+        InputAction::FineStep,
+        InputAction::RotateStepLeft,
+        InputAction::RotateStepRight,
+        InputAction::KeyboardCut,
+        InputAction::ConfirmCut,
+        InputAction::Flip,
+        InputAction::ToggleSnapping,
+        InputAction::ToggleMomentum,
+        InputAction::ZoomIn,
+        InputAction::ZoomOut,
+        InputAction::Pan,
+        InputAction::FitLevel,
+        InputAction::ToggleFullscreen,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            InputAction::Translate => "Move piece",
+            InputAction::Rotate => "Rotate piece",
+            InputAction::RotateLeft => "Rotate left",
+            InputAction::RotateRight => "Rotate right",
+            InputAction::RotateFast => "Rotate fast",
+            InputAction::StartCut => "Cut",
+            InputAction::CancelCut => "Cancel cut",
+            InputAction::Select => "Select",
+            InputAction::SelectKeep => "Add to selection",
+            InputAction::RevertToInit => "Restart level",
+            InputAction::DeleteSelected => "Delete selected",
+            InputAction::DeleteAll => "Delete all",
+            InputAction::DeleteTarget => "Delete target",
+            InputAction::ToggleGrid => "Toggle grid",
+            InputAction::QuickSave => "Quick save",
+            InputAction::SaveOneDialog => "Save one",
+            InputAction::LoadDialog => "Load",
+            InputAction::LoadTarget => "Load target",
+            InputAction::QuickLoad => "Quick load",
+            InputAction::TurnPolyIntoTarget => "Turn into target",
+            InputAction::StartMakingPolygon => "Start polygon",
+            InputAction::EndMakingPolygon => "End polygon",
+            InputAction::EndSegment => "End segment",
+            InputAction::DeleteMakingPoly => "Delete polygon",
+            InputAction::AddPoint => "Add point",
+            InputAction::MovePathPoint => "Move point",
+            InputAction::NextPiece => "Next piece",
+            InputAction::PreviousPiece => "Previous piece",
+            InputAction::NudgeUp => "Move up",
+            InputAction::NudgeDown => "Move down",
+            InputAction::NudgeLeft => "Move left",
+            InputAction::NudgeRight => "Move right",
+            InputAction::FineStep => "Fine steps (hold)",
+            InputAction::RotateStepLeft => "Step rotate left",
+            InputAction::RotateStepRight => "Step rotate right",
+            InputAction::KeyboardCut => "Place cut line",
+            InputAction::ConfirmCut => "Confirm cut line",
+            InputAction::Flip => "Flip piece",
+            InputAction::ToggleSnapping => "Toggle snapping",
+            InputAction::ToggleMomentum => "Toggle momentum",
+            InputAction::ZoomIn => "Zoom in",
+            InputAction::ZoomOut => "Zoom out",
+            InputAction::Pan => "Pan view (hold)",
+            InputAction::FitLevel => "Fit level to view",
+            InputAction::ToggleFullscreen => "Toggle fullscreen",
+        }
+    }
+}
+
+// keys that can be written in the bindings file, by their KeyCode name
+const NAMED_KEYS: [KeyCode; 75] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Escape,
+    KeyCode::Back,
+    KeyCode::Delete,
+    KeyCode::Return,
+    KeyCode::Space,
+    KeyCode::Tab,
+    KeyCode::Left,
+    KeyCode::Up,
+    KeyCode::Right,
+    KeyCode::Down,
+    KeyCode::LShift,
+    KeyCode::RShift,
+    KeyCode::LControl,
+    KeyCode::RControl,
+    KeyCode::LAlt,
+    KeyCode::RAlt,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Semicolon,
+    KeyCode::Slash,
+    KeyCode::Minus,
+    KeyCode::Equals,
+    KeyCode::LBracket,
+    KeyCode::RBracket,
+    KeyCode::Apostrophe,
+    KeyCode::Home,
+    KeyCode::End,
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub enum InputButton {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+    WheelUp,
+    WheelDown,
+}
+
+impl TryFrom<String> for InputButton {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        match name.as_str() {
+            "MouseLeft" => return Ok(InputButton::Mouse(MouseButton::Left)),
+            "MouseRight" => return Ok(InputButton::Mouse(MouseButton::Right)),
+            "MouseMiddle" => return Ok(InputButton::Mouse(MouseButton::Middle)),
+            "WheelUp" => return Ok(InputButton::WheelUp),
+            "WheelDown" => return Ok(InputButton::WheelDown),
+            _ => {}
+        }
+
+        if let Some(Ok(k)) = name.strip_prefix("Mouse").map(|k| k.parse::<u16>()) {
+            return Ok(InputButton::Mouse(MouseButton::Other(k)));
+        }
+
+        if let Some(gamepad_name) = name.strip_prefix("Gamepad") {
+            return GAMEPAD_BUTTONS
+                .iter()
+                .find(|button| format!("{:?}", button) == gamepad_name)
+                .map(|button| InputButton::Gamepad(*button))
+                .ok_or(format!("unknown gamepad button: {}", name));
+        }
+
+        NAMED_KEYS
+            .iter()
+            .find(|key| format!("{:?}", key) == name)
+            .map(|key| InputButton::Key(*key))
+            .ok_or(format!("unknown key or button: {}", name))
+    }
+}
+
+impl From<InputButton> for String {
+    fn from(button: InputButton) -> String {
+        match button {
+            InputButton::Key(key) => format!("{:?}", key),
+            InputButton::Mouse(MouseButton::Left) => "MouseLeft".to_string(),
+            InputButton::Mouse(MouseButton::Right) => "MouseRight".to_string(),
+            InputButton::Mouse(MouseButton::Middle) => "MouseMiddle".to_string(),
+            InputButton::Mouse(MouseButton::Other(k)) => format!("Mouse{}", k),
+            InputButton::Gamepad(button) => format!("Gamepad{:?}", button),
+            InputButton::WheelUp => "WheelUp".to_string(),
+            InputButton::WheelDown => "WheelDown".to_string(),
+        }
+    }
+}
+
+// Snapshot of the keyboard, the mouse buttons, the gamepad and the mouse wheel for the
+// current frame
+pub struct ButtonState<'a> {
+    pub keyboard: &'a Input<KeyCode>,
+    pub mouse: &'a Input<MouseButton>,
+    pub gamepad: &'a GamepadInput,
+    pub wheel_up: bool,
+    pub wheel_down: bool,
+}
+
+impl<'a> ButtonState<'a> {
+    pub fn pressed(&self, button: InputButton) -> bool {
+        match button {
+            InputButton::Key(key) => self.keyboard.pressed(key),
+            InputButton::Mouse(mouse_button) => self.mouse.pressed(mouse_button),
+            InputButton::Gamepad(button) => self.gamepad.pressed(button),
+            InputButton::WheelUp => self.wheel_up,
+            InputButton::WheelDown => self.wheel_down,
+        }
+    }
+
+    pub fn just_pressed(&self, button: InputButton) -> bool {
+        match button {
+            InputButton::Key(key) => self.keyboard.just_pressed(key),
+            InputButton::Mouse(mouse_button) => self.mouse.just_pressed(mouse_button),
+            InputButton::Gamepad(button) => self.gamepad.just_pressed(button),
+            InputButton::WheelUp => self.wheel_up,
+            InputButton::WheelDown => self.wheel_down,
+        }
+    }
+
+    pub fn just_released(&self, button: InputButton) -> bool {
+        match button {
+            InputButton::Key(key) => self.keyboard.just_released(key),
+            InputButton::Mouse(mouse_button) => self.mouse.just_released(mouse_button),
+            InputButton::Gamepad(button) => self.gamepad.just_released(button),
+            InputButton::WheelUp | InputButton::WheelDown => false,
+        }
+    }
+
+    // first key or button pressed this frame, used to rebind an action
+    pub fn first_just_pressed(&self) -> Option<InputButton> {
+        if let Some(key) = self.keyboard.get_just_pressed().next() {
+            return Some(InputButton::Key(*key));
+        }
+        if let Some(mouse_button) = self.mouse.get_just_pressed().next() {
+            return Some(InputButton::Mouse(*mouse_button));
+        }
+        if let Some(button) = self.gamepad.get_just_pressed().next() {
+            return Some(InputButton::Gamepad(*button));
+        }
+        if self.wheel_up {
+            return Some(InputButton::WheelUp);
+        }
+        if self.wheel_down {
+            return Some(InputButton::WheelDown);
+        }
+        None
+    }
+}
+
+//
+//
+// A chord is a trigger (pressed this frame) and a list of keys that are held
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub action: InputAction,
+    #[serde(default)]
+    pub held: Vec<InputButton>,
+    pub trigger: InputButton,
+    #[serde(default)]
+    pub any_modifiers: bool,
+}
+
+impl Binding {
+    pub fn new(action: InputAction, held: Vec<KeyCode>, trigger: InputButton) -> Self {
+        Self {
+            action,
+            held: held.into_iter().map(InputButton::Key).collect(),
+            trigger,
+            any_modifiers: false,
+        }
+    }
+
+    pub fn with_any_modifiers(mut self) -> Self {
+        self.any_modifiers = true;
+        self
+    }
+
+    fn modifiers_match(&self, state: &ButtonState) -> bool {
+        self.any_modifiers
+            || MODIFIER_KEYS.iter().all(|key| {
+                let button = InputButton::Key(*key);
+                let in_chord = self.held.contains(&button) || self.trigger == button;
+                state.pressed(button) == in_chord
+            })
+    }
+
+    pub fn just_pressed(&self, state: &ButtonState) -> bool {
+        state.just_pressed(self.trigger)
+            && self.held.iter().all(|button| state.pressed(*button))
+            && self.modifiers_match(state)
+    }
+
+    // the whole chord is held, whenever the trigger was pressed
+    pub fn pressed(&self, state: &ButtonState) -> bool {
+        state.pressed(self.trigger)
+            && self.held.iter().all(|button| state.pressed(*button))
+            && self.modifiers_match(state)
+    }
+
+    fn on_gamepad(&self) -> bool {
+        matches!(self.trigger, InputButton::Gamepad(_))
+    }
+
+    // e.g. "LControl + MouseLeft"
+    pub fn chord_label(&self) -> String {
+        let mut names: Vec<String> = self.held.iter().map(|b| String::from(*b)).collect();
+        names.push(String::from(self.trigger));
+        names.join(" + ")
+    }
+}
+
+//
+//
+// The binding table. The order of the table is the priority: the first binding that
+// matches in direct_action(..) wins, like the arms of a match.
+pub struct Bindings {
+    pub bindings: Vec<Binding>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use InputAction::*;
+        use InputButton::*;
+
+        let left = Mouse(MouseButton::Left);
+        let right = Mouse(MouseButton::Right);
+        let pad = |button: GamepadButtonType| InputButton::Gamepad(button);
+
+        Self {
+            bindings: vec![
+                Binding::new(
+                    DeleteTarget,
+                    vec![KeyCode::LShift, KeyCode::LControl, KeyCode::Space],
+                    Key(KeyCode::Delete),
+                ),
+                Binding::new(SelectKeep, vec![KeyCode::S, KeyCode::A], left),
+                Binding::new(SelectKeep, vec![KeyCode::LShift], left),
+                Binding::new(Select, vec![KeyCode::S], left),
+                Binding::new(RevertToInit, vec![], Key(KeyCode::Back)).with_any_modifiers(),
+                Binding::new(StartCut, vec![KeyCode::C], left),
+                Binding::new(StartCut, vec![KeyCode::LControl], left),
+                Binding::new(ToggleGrid, vec![], Key(KeyCode::G)).with_any_modifiers(),
+                Binding::new(RotateLeft, vec![], WheelUp),
+                Binding::new(RotateRight, vec![], WheelDown),
+                Binding::new(EndMakingPolygon, vec![], Key(KeyCode::Return)),
+                Binding::new(EndMakingPolygon, vec![], right),
+                Binding::new(EndMakingPolygon, vec![], Key(KeyCode::Space)),
+                Binding::new(MovePathPoint, vec![KeyCode::Q], left),
+                Binding::new(EndSegment, vec![], left),
+                Binding::new(QuickSave, vec![KeyCode::LControl], Key(KeyCode::S)),
+                Binding::new(
+                    SaveOneDialog,
+                    vec![KeyCode::LShift, KeyCode::LControl],
+                    Key(KeyCode::S),
+                ),
+                Binding::new(
+                    LoadDialog,
+                    vec![KeyCode::LShift, KeyCode::LControl],
+                    Key(KeyCode::L),
+                ),
+                Binding::new(
+                    LoadTarget,
+                    vec![KeyCode::LShift, KeyCode::LControl],
+                    Key(KeyCode::T),
+                ),
+                Binding::new(QuickLoad, vec![KeyCode::LControl], Key(KeyCode::L)),
+                Binding::new(TurnPolyIntoTarget, vec![KeyCode::E], Key(KeyCode::T)),
+                Binding::new(CancelCut, vec![], Key(KeyCode::Escape)),
+                Binding::new(DeleteMakingPoly, vec![], Key(KeyCode::Delete)),
+                Binding::new(DeleteMakingPoly, vec![], Key(KeyCode::Escape)),
+                // shift + left click adds to the selection, so starting a polygon also
+                // needs ctrl. An older bindings.json is migrated, see changed_defaults()
+                Binding::new(
+                    StartMakingPolygon,
+                    vec![KeyCode::LShift, KeyCode::LControl],
+                    left,
+                ),
+                Binding::new(AddPoint, vec![KeyCode::LShift], right),
+                Binding::new(DeleteAll, vec![KeyCode::A], Key(KeyCode::Delete)),
+                Binding::new(DeleteSelected, vec![], Key(KeyCode::Delete)).with_any_modifiers(),
+                Binding::new(RotateFast, vec![KeyCode::Space], Key(KeyCode::Space)),
+                Binding::new(Translate, vec![], left),
+                Binding::new(Rotate, vec![], right),
+                //
+                //
+                // keyboard-only play, see keyboard.rs
+                Binding::new(NextPiece, vec![], Key(KeyCode::Tab)),
+                Binding::new(PreviousPiece, vec![KeyCode::LShift], Key(KeyCode::Tab)),
+                Binding::new(NudgeUp, vec![], Key(KeyCode::Up)).with_any_modifiers(),
+                Binding::new(NudgeDown, vec![], Key(KeyCode::Down)).with_any_modifiers(),
+                Binding::new(NudgeLeft, vec![], Key(KeyCode::Left)).with_any_modifiers(),
+                Binding::new(NudgeRight, vec![], Key(KeyCode::Right)).with_any_modifiers(),
+                Binding::new(FineStep, vec![], Key(KeyCode::LShift)).with_any_modifiers(),
+                // not Q and E, which are held in the level maker chords above
+                Binding::new(RotateStepLeft, vec![], Key(KeyCode::Comma)),
+                Binding::new(RotateStepRight, vec![], Key(KeyCode::Period)),
+                Binding::new(KeyboardCut, vec![], Key(KeyCode::X)),
+                Binding::new(ConfirmCut, vec![], Key(KeyCode::Return)),
+                Binding::new(Flip, vec![], Key(KeyCode::F)),
+                Binding::new(ToggleSnapping, vec![], Key(KeyCode::N)),
+                Binding::new(ToggleMomentum, vec![], Key(KeyCode::I)),
+                //
+                //
+                // the view: the wheel alone rotates, see camera.rs
+                Binding::new(ZoomIn, vec![KeyCode::LControl], WheelUp),
+                Binding::new(ZoomOut, vec![KeyCode::LControl], WheelDown),
+                Binding::new(Pan, vec![], Mouse(MouseButton::Middle)).with_any_modifiers(),
+                Binding::new(FitLevel, vec![], Key(KeyCode::Home)),
+                Binding::new(ToggleFullscreen, vec![], Key(KeyCode::F11)).with_any_modifiers(),
+                //
+                //
+                // gamepad: the stick moves a virtual cursor, see gamepad.rs
+                Binding::new(StartCut, vec![], pad(GamepadButtonType::West)),
+                Binding::new(CancelCut, vec![], pad(GamepadButtonType::East)),
+                Binding::new(RevertToInit, vec![], pad(GamepadButtonType::Select)),
+                Binding::new(NextPiece, vec![], pad(GamepadButtonType::DPadRight)),
+                Binding::new(PreviousPiece, vec![], pad(GamepadButtonType::DPadLeft)),
+                Binding::new(Translate, vec![], pad(GamepadButtonType::South)),
+                Binding::new(Rotate, vec![], pad(GamepadButtonType::North)),
+                Binding::new(Flip, vec![], pad(GamepadButtonType::DPadUp)),
+            ],
+        }
+    }
+}
+
+impl Bindings {
+    pub fn just_pressed(&self, action: InputAction, state: &ButtonState) -> bool {
+        self.bindings
+            .iter()
+            .any(|binding| binding.action == action && binding.just_pressed(state))
+    }
+
+    pub fn pressed(&self, action: InputAction, state: &ButtonState) -> bool {
+        self.bindings
+            .iter()
+            .any(|binding| binding.action == action && binding.pressed(state))
+    }
+
+    // the trigger of one of the chords of the action was released, whatever the held keys
+    pub fn trigger_just_released(&self, action: InputAction, state: &ButtonState) -> bool {
+        self.bindings
+            .iter()
+            .any(|binding| binding.action == action && state.just_released(binding.trigger))
+    }
+
+    pub fn trigger_just_pressed(&self, action: InputAction, state: &ButtonState) -> bool {
+        self.bindings
+            .iter()
+            .any(|binding| binding.action == action && state.just_pressed(binding.trigger))
+    }
+
+    pub fn chord_labels(&self, action: InputAction) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.action == action)
+            .map(|binding| binding.chord_label())
+            .collect()
+    }
+
+    //
+    //
+    // Replaces the first chord of an action on the same device as the new trigger
+    // (keyboard and mouse, or gamepad). The other chords of the action on that device are
+    // removed, so that the old keys are free to be used for something else, and the chords
+    // on the other device are kept.
+    pub fn rebind(&mut self, action: InputAction, held: Vec<InputButton>, trigger: InputButton) {
+        let on_gamepad = matches!(trigger, InputButton::Gamepad(_));
+        let same_device = |b: &Binding| b.action == action && b.on_gamepad() == on_gamepad;
+
+        if let Some(idx) = self.bindings.iter().position(same_device) {
+            let any_modifiers = self.bindings[idx].any_modifiers;
+            self.bindings[idx] = Binding {
+                action,
+                held,
+                trigger,
+                any_modifiers,
+            };
+            let mut is_first = true;
+            self.bindings.retain(|b| {
+                if !same_device(b) {
+                    return true;
+                }
+                let keep = is_first;
+                is_first = false;
+                keep
+            });
+        } else {
+            self.bindings.push(Binding {
+                action,
+                held,
+                trigger,
+                any_modifiers: false,
+            });
+        }
+    }
+
+    // Default chords that were changed because they clashed with a newer chord, with their
+    // replacement. A file saved before the change still has the old chord.
+    fn changed_defaults() -> Vec<(Binding, Binding)> {
+        use InputAction::*;
+        use InputButton::*;
+
+        let left = Mouse(MouseButton::Left);
+        vec![
+            // shift + left click is SelectKeep
+            (
+                Binding::new(StartMakingPolygon, vec![KeyCode::LShift], left),
+                Binding::new(
+                    StartMakingPolygon,
+                    vec![KeyCode::LShift, KeyCode::LControl],
+                    left,
+                ),
+            ),
+            // Q and E are held in MovePathPoint and TurnPolyIntoTarget
+            (
+                Binding::new(RotateStepLeft, vec![], Key(KeyCode::Q)),
+                Binding::new(RotateStepLeft, vec![], Key(KeyCode::Comma)),
+            ),
+            (
+                Binding::new(RotateStepRight, vec![], Key(KeyCode::E)),
+                Binding::new(RotateStepRight, vec![], Key(KeyCode::Period)),
+            ),
+        ]
+    }
+
+    // The actions added since the file was saved keep their default chords, at the lowest
+    // priority. The actions of the file keep the chords of the file, except the old
+    // defaults of changed_defaults()
+    fn with_missing_defaults(mut bindings: Vec<Binding>) -> Self {
+        for (old, new) in Self::changed_defaults() {
+            for binding in bindings.iter_mut().filter(|b| **b == old) {
+                *binding = new.clone();
+            }
+        }
+
+        let saved: Vec<InputAction> = bindings.iter().map(|b| b.action).collect();
+        bindings.extend(
+            Self::default()
+                .bindings
+                .into_iter()
+                .filter(|b| !saved.contains(&b.action)),
+        );
+        Self { bindings }
+    }
+
+    pub fn load() -> Self {
+        let mut path = std::env::current_dir().unwrap_or_default();
+        path.push(BINDINGS_FILE);
+
+        let mut contents = String::new();
+        if let Ok(mut file) = std::fs::File::open(&path) {
+            if file.read_to_string(&mut contents).is_ok() {
+                match serde_json::from_str::<Vec<Binding>>(&contents) {
+                    Ok(bindings) => return Self::with_missing_defaults(bindings),
+                    Err(err) => warn!("could not read {:?}, using defaults: {}", path, err),
+                }
+            }
+        }
+
+        Self::default()
+    }
+
+    pub fn save(&self) {
+        let mut path = std::env::current_dir().unwrap_or_default();
+        path.push(BINDINGS_FILE);
+
+        let serialized = serde_json::to_string_pretty(&self.bindings).unwrap();
+        let result = std::fs::File::create(&path)
+            .and_then(|mut output| output.write_all(serialized.as_bytes()));
+        if let Err(err) = result {
+            warn!("could not save {:?}: {}", path, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_chords_round_trip_through_json() {
+        let bindings = Bindings::default().bindings;
+        let serialized = serde_json::to_string(&bindings).unwrap();
+        let deserialized: Vec<Binding> = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, bindings);
+    }
+
+    #[test]
+    fn saved_chords_are_kept_and_missing_actions_get_their_defaults() {
+        let saved = vec![Binding::new(
+            InputAction::ZoomIn,
+            vec![KeyCode::LControl],
+            InputButton::Key(KeyCode::Home),
+        )];
+        let bindings = Bindings::with_missing_defaults(saved.clone());
+        let defaults = Bindings::default();
+
+        assert_eq!(bindings.bindings[0], saved[0]);
+        assert_eq!(
+            bindings.chord_labels(InputAction::ZoomIn),
+            vec!["LControl + Home".to_string()]
+        );
+        for action in InputAction::PLAYER_ACTIONS {
+            if action != InputAction::ZoomIn {
+                assert_eq!(bindings.chord_labels(action), defaults.chord_labels(action));
+            }
+        }
+    }
+
+    #[test]
+    fn rebinding_a_keyboard_chord_keeps_the_gamepad_chord() {
+        let mut bindings = Bindings::default();
+        bindings.rebind(InputAction::StartCut, vec![], InputButton::Key(KeyCode::X));
+
+        assert_eq!(
+            bindings.chord_labels(InputAction::StartCut),
+            vec!["X".to_string(), "GamepadWest".to_string()]
+        );
+    }
+
+    #[test]
+    fn rebinding_a_gamepad_chord_keeps_the_keyboard_chords() {
+        let mut bindings = Bindings::default();
+        let keyboard_chords: Vec<String> = Bindings::default()
+            .chord_labels(InputAction::StartCut)
+            .into_iter()
+            .filter(|label| !label.starts_with("Gamepad"))
+            .collect();
+        bindings.rebind(
+            InputAction::StartCut,
+            vec![],
+            InputButton::Gamepad(GamepadButtonType::North),
+        );
+
+        let mut expected = keyboard_chords;
+        expected.push("GamepadNorth".to_string());
+        assert_eq!(bindings.chord_labels(InputAction::StartCut), expected);
+    }
+
+    #[test]
+    fn rotation_step_keys_are_not_held_in_other_chords() {
+        let bindings = Bindings::default().bindings;
+        for step in bindings.iter().filter(|b| {
+            b.action == InputAction::RotateStepLeft || b.action == InputAction::RotateStepRight
+        }) {
+            assert!(
+                bindings.iter().all(|b| !b.held.contains(&step.trigger)),
+                "{:?} is held in another chord",
+                step.trigger
+            );
+        }
+    }
+
+    #[test]
+    fn old_default_chords_are_migrated_on_load() {
+        let left = InputButton::Mouse(MouseButton::Left);
+        let saved = vec![
+            Binding::new(InputAction::SelectKeep, vec![KeyCode::LShift], left),
+            Binding::new(InputAction::StartMakingPolygon, vec![KeyCode::LShift], left),
+            Binding::new(
+                InputAction::RotateStepLeft,
+                vec![],
+                InputButton::Key(KeyCode::Q),
+            ),
+        ];
+        let bindings = Bindings::with_missing_defaults(saved);
+
+        assert_eq!(
+            bindings.chord_labels(InputAction::SelectKeep),
+            vec!["LShift + MouseLeft".to_string()]
+        );
+        assert_eq!(
+            bindings.chord_labels(InputAction::StartMakingPolygon),
+            vec!["LShift + LControl + MouseLeft".to_string()]
+        );
+        assert_eq!(
+            bindings.chord_labels(InputAction::RotateStepLeft),
+            vec!["Comma".to_string()]
+        );
+    }
+}
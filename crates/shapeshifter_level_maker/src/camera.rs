@@ -0,0 +1,322 @@
+use crate::bindings::*;
+use crate::gamepad::GamepadInput;
+use crate::input::Cursor;
+use crate::util::*;
+
+use bevy::{input::mouse::MouseWheel, prelude::*};
+
+use lyon::algorithms::aabb::fast_bounding_box;
+
+// change of the projection scale per notch of the wheel
+const ZOOM_STEP: f32 = 1.1;
+const MIN_ZOOM: f32 = 0.2;
+const MAX_ZOOM: f32 = 5.0;
+
+// room around the level when it is fitted to the view
+const FIT_MARGIN: f32 = 1.15;
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(zoom_camera)
+            .add_system(pan_camera)
+            .add_system(fit_camera_to_level)
+            .add_system(reset_camera_on_new_level);
+    }
+}
+
+// world units per pixel of the window, along x and y
+fn world_per_pixel(projection: &OrthographicProjection, window_size: Vec2) -> Vec2 {
+    Vec2::new(
+        (projection.right - projection.left) / window_size.x,
+        (projection.top - projection.bottom) / window_size.y,
+    ) * projection.scale
+}
+
+//
+//
+// Position in pixels (lower left is origin) to world position, through the projection and
+// the transform of the camera. The bounds of the projection follow the size of the window.
+pub fn screen_to_world(
+    pixels: Vec2,
+    window_size: Vec2,
+    cam_transform: &Transform,
+    projection: &OrthographicProjection,
+) -> Vec2 {
+    let uv = pixels / window_size;
+    let local = Vec2::new(
+        projection.left + uv.x * (projection.right - projection.left),
+        projection.bottom + uv.y * (projection.top - projection.bottom),
+    ) * projection.scale;
+
+    cam_transform
+        .compute_matrix()
+        .transform_point3(local.extend(0.0))
+        .truncate()
+}
+
+// the Cursor stays at the same place on the screen when the camera jumps
+fn move_cursor_with_camera(cursor: &mut Cursor, old: (Vec2, f32), new: (Vec2, f32)) {
+    let (old_position, old_scale) = old;
+    let (new_position, new_scale) = new;
+    let on_screen = (cursor.position - old_position) / old_scale;
+    cursor.position = new_position + on_screen * new_scale;
+    cursor.pos_relative_to_click = cursor.position - cursor.last_click_position;
+}
+
+//
+//
+// The wheel zooms in and out around the cursor while the zoom modifier is held. The
+// world point under the cursor does not move, so the Cursor is still correct.
+pub fn zoom_camera(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<GamepadInput>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    bindings: Res<Bindings>,
+    cursor: Res<Cursor>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+) {
+    let mut wheel_up = false;
+    let mut wheel_down = false;
+    if let Some(mouse_wheel) = mouse_wheel_events.iter().next() {
+        wheel_up = mouse_wheel.y > 0.5;
+        wheel_down = mouse_wheel.y < -0.5;
+    }
+
+    let state = ButtonState {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad: &gamepad_input,
+        wheel_up,
+        wheel_down,
+    };
+
+    let factor = if bindings.just_pressed(InputAction::ZoomIn, &state) {
+        1.0 / ZOOM_STEP
+    } else if bindings.just_pressed(InputAction::ZoomOut, &state) {
+        ZOOM_STEP
+    } else {
+        return;
+    };
+
+    for (mut transform, mut projection) in camera_query.iter_mut() {
+        let old_scale = projection.scale;
+        let new_scale = (old_scale * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        if new_scale == old_scale {
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+        let new_position = cursor.position - (cursor.position - position) * new_scale / old_scale;
+
+        projection.scale = new_scale;
+        transform.translation = new_position.extend(transform.translation.z);
+    }
+}
+
+//
+//
+// Dragging with the pan button moves the view. The world point under the cursor is
+// grabbed, so the Cursor does not move in the world while panning.
+pub fn pan_camera(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<GamepadInput>,
+    bindings: Res<Bindings>,
+    windows: Res<Windows>,
+    mut cursor: ResMut<Cursor>,
+    mut last_pixels: Local<Option<Vec2>>,
+    mut camera_query: Query<(&mut Transform, &OrthographicProjection), With<Camera2d>>,
+) {
+    let state = ButtonState {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad: &gamepad_input,
+        wheel_up: false,
+        wheel_down: false,
+    };
+
+    let window = if let Some(window) = windows.get_primary() {
+        window
+    } else {
+        return;
+    };
+
+    let pixels = match window.cursor_position() {
+        Some(pixels) if bindings.pressed(InputAction::Pan, &state) => pixels,
+        _ => {
+            *last_pixels = None;
+            return;
+        }
+    };
+
+    if let Some(last) = last_pixels.replace(pixels) {
+        let window_size = Vec2::new(window.width(), window.height());
+        for (mut transform, projection) in camera_query.iter_mut() {
+            let shift = (pixels - last) * world_per_pixel(projection, window_size);
+            transform.translation -= shift.extend(0.0);
+
+            // the cursor may have been moved with the previous camera this frame
+            cursor.position = screen_to_world(pixels, window_size, &transform, projection);
+            cursor.pos_relative_to_click = cursor.position - cursor.last_click_position;
+        }
+    }
+}
+
+//
+//
+// Centers the view on the target, the polygons and the obstacles, and zooms so that
+// they are all visible
+pub fn fit_camera_to_level(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<GamepadInput>,
+    bindings: Res<Bindings>,
+    mut cursor: ResMut<Cursor>,
+    polygon_query: Query<(&Transform, &MeshMeta), With<Polygon>>,
+    target_query: Query<(&Transform, &Target)>,
+    obstacle_query: Query<(&Transform, &Obstacle)>,
+    mut camera_query: Query<
+        (&mut Transform, &mut OrthographicProjection),
+        (
+            With<Camera2d>,
+            Without<Polygon>,
+            Without<Target>,
+            Without<Obstacle>,
+        ),
+    >,
+) {
+    let state = ButtonState {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad: &gamepad_input,
+        wheel_up: false,
+        wheel_down: false,
+    };
+    if !bindings.just_pressed(InputAction::FitLevel, &state) {
+        return;
+    }
+
+    let paths = polygon_query
+        .iter()
+        .map(|(transform, meta)| transform_path(&meta.path, transform).0)
+        .chain(
+            target_query
+                .iter()
+                .map(|(transform, target)| transform_path(&target.path, transform).0),
+        )
+        .chain(
+            obstacle_query
+                .iter()
+                .map(|(transform, obstacle)| transform_path(&obstacle.path, transform).0),
+        );
+
+    let mut min = Vec2::splat(f32::MAX);
+    let mut max = Vec2::splat(f32::MIN);
+    for path in paths {
+        let bb = fast_bounding_box(&path);
+        min = min.min(Vec2::new(bb.min.x, bb.min.y));
+        max = max.max(Vec2::new(bb.max.x, bb.max.y));
+    }
+    if min.x > max.x || min.y > max.y {
+        return;
+    }
+
+    for (mut transform, mut projection) in camera_query.iter_mut() {
+        let old = (transform.translation.truncate(), projection.scale);
+
+        // size of the view at scale 1
+        let view = Vec2::new(
+            projection.right - projection.left,
+            projection.top - projection.bottom,
+        );
+        let size = (max - min) * FIT_MARGIN;
+        let scale = (size.x / view.x)
+            .max(size.y / view.y)
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+        let center = (min + max) / 2.0;
+
+        projection.scale = scale;
+        transform.translation = center.extend(transform.translation.z);
+        move_cursor_with_camera(&mut cursor, old, (center, scale));
+    }
+}
+
+// the levels are laid out for the default view
+pub fn reset_camera_on_new_level(
+    mut spawn_level_event_reader: EventReader<SpawnLevel>,
+    mut cursor: ResMut<Cursor>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+) {
+    if spawn_level_event_reader.iter().next().is_none() {
+        return;
+    }
+
+    for (mut transform, mut projection) in camera_query.iter_mut() {
+        let old = (transform.translation.truncate(), projection.scale);
+        if old == (Vec2::ZERO, 1.0) {
+            continue;
+        }
+
+        projection.scale = 1.0;
+        transform.translation = Vec3::new(0.0, 0.0, transform.translation.z);
+        move_cursor_with_camera(&mut cursor, old, (Vec2::ZERO, 1.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the bounds that bevy gives to a 1280x720 window
+    fn projection(scale: f32) -> OrthographicProjection {
+        OrthographicProjection {
+            left: -640.0,
+            right: 640.0,
+            bottom: -360.0,
+            top: 360.0,
+            scale,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn screen_to_world_without_camera_transform() {
+        let window_size = Vec2::new(1280.0, 720.0);
+        let camera = Transform::default();
+        let projection = projection(1.0);
+
+        let world = |pixels| screen_to_world(pixels, window_size, &camera, &projection);
+        assert_eq!(world(Vec2::new(640.0, 360.0)), Vec2::ZERO);
+        assert_eq!(world(Vec2::ZERO), Vec2::new(-640.0, -360.0));
+        assert_eq!(world(window_size), Vec2::new(640.0, 360.0));
+    }
+
+    #[test]
+    fn screen_to_world_zoomed_out_and_panned() {
+        let window_size = Vec2::new(1280.0, 720.0);
+        let camera = Transform::from_xyz(100.0, 50.0, 999.0);
+        let projection = projection(2.0);
+
+        let world = |pixels| screen_to_world(pixels, window_size, &camera, &projection);
+        assert_eq!(world(Vec2::new(640.0, 360.0)), Vec2::new(100.0, 50.0));
+        assert_eq!(world(Vec2::ZERO), Vec2::new(100.0 - 1280.0, 50.0 - 720.0));
+    }
+
+    #[test]
+    fn cursor_stays_on_the_same_pixel_when_the_camera_jumps() {
+        let mut cursor = Cursor {
+            position: Vec2::new(110.0, 60.0),
+            ..Default::default()
+        };
+        move_cursor_with_camera(
+            &mut cursor,
+            (Vec2::new(100.0, 50.0), 1.0),
+            (Vec2::ZERO, 2.0),
+        );
+        assert_eq!(cursor.position, Vec2::new(20.0, 20.0));
+        assert_eq!(cursor.pos_relative_to_click, cursor.position);
+    }
+}
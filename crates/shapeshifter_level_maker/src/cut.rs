@@ -1,12 +1,15 @@
 use crate::input::Cursor;
 use crate::input::*;
 use crate::material::*;
+use crate::physics::CUT_PUSH_SPEED;
 // use crate::poly::make_polygon_mesh;
 // use crate::poly::Polygon;
 use crate::util::*;
 
 use bevy::{
+    math::Affine2,
     prelude::*,
+    render::render_resource::PrimitiveTopology,
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
     utils::Duration,
 };
@@ -49,6 +52,21 @@ impl PolyPoint {
     }
 }
 
+// pieces smaller than this are not worth a cut: the cut is cancelled
+pub const MIN_PIECE_AREA: f32 = 200.0;
+
+// result of a cut segment on a single polygon
+#[derive(Debug, Clone)]
+pub enum PolygonCut {
+    Missed,
+    OddIntersections(usize),
+    TooSmall,
+    Split(Vec<Vec<Point>>),
+}
+
+#[derive(Component)]
+pub struct CutPreviewOutline;
+
 pub struct CutTimer {
     pub timer: Timer,
     pub entities: Vec<Entity>,
@@ -68,12 +86,65 @@ pub struct CutPlugin;
 impl Plugin for CutPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(RemainingCuts { remaining: 1000 })
+            .init_resource::<CutPreview>()
             .init_resource::<CutTimer>()
             .add_system(start_cut_segment)
             .add_system(end_cut_segment)
             .add_system(making_cut_segment)
-            .add_system(move_after_cut)
-            .add_system(perform_cut);
+            .add_system(perform_cut)
+            .add_system(preview_cut)
+            .add_system(animate_cut_segments);
+    }
+}
+
+// the unit quad of a cut line, stretched along the segment
+pub fn cut_segment_transform(segment_meta: &SegmentMeta, globals: &Globals) -> Transform {
+    segment_meta.transform.with_scale(Vec3::new(
+        segment_meta.length,
+        globals.cutting_segment_thickness,
+        1.0,
+    ))
+}
+
+// a dashed cut line along the segment, for the cuts and their previews
+pub fn cut_segment_bundle(
+    segment: Segment,
+    meshes: &mut Assets<Mesh>,
+    cut_materials: &mut Assets<CutMesh2dMaterial>,
+    globals: &Globals,
+) -> MaterialMesh2dBundle<CutMesh2dMaterial> {
+    let segment_meta = get_segment_meta(segment);
+    MaterialMesh2dBundle {
+        mesh: Mesh2dHandle(meshes.add(Mesh::from(shape::Quad::new(Vec2::ONE)))),
+        material: cut_materials.add(CutMesh2dMaterial::new(globals)),
+        transform: cut_segment_transform(&segment_meta, globals),
+        ..Default::default()
+    }
+}
+
+// moves the dashes, and shows whether the cut being made would be rejected
+pub fn animate_cut_segments(
+    time: Res<Time>,
+    cut_preview: Res<CutPreview>,
+    globals: Res<Globals>,
+    mut cut_materials: ResMut<Assets<CutMesh2dMaterial>>,
+    query: Query<
+        (
+            &Transform,
+            &Handle<CutMesh2dMaterial>,
+            Option<&MakingCutSegment>,
+        ),
+        With<CutSegment>,
+    >,
+) {
+    for (transform, material_handle, making_segment) in query.iter() {
+        if let Some(material) = cut_materials.get_mut(material_handle) {
+            material.length = transform.scale.x;
+            material.thickness = globals.cutting_segment_thickness;
+            material.time = time.seconds_since_startup() as f32;
+            let invalid = making_segment.is_some() && cut_preview.invalid.is_some();
+            material.invalid = invalid as i32 as f32;
+        }
     }
 }
 
@@ -87,7 +158,7 @@ pub fn start_cut_segment(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     // query: Query<Entity, With<MakingCutSegment>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut cut_materials: ResMut<Assets<CutMesh2dMaterial>>,
     // mut start_segment_event_reader: EventReader<StartMakingCutSegment>,
     globals: Res<Globals>,
     cursor: Res<Cursor>,
@@ -105,20 +176,13 @@ pub fn start_cut_segment(
                 end: cursor.clone().into(),
             };
 
-            let segment_meta = get_segment_meta(segment);
-
-            let mesh = bevy::sprite::Mesh2dHandle(meshes.add(Mesh::from(shape::Quad::new(
-                Vec2::new(segment_meta.length, globals.cutting_segment_thickness),
-            ))));
-
-            let material = materials.add(globals.cutting_segment_color.into());
             commands
-                .spawn_bundle(MaterialMesh2dBundle {
-                    mesh,
-                    material,
-                    transform: segment_meta.transform,
-                    ..Default::default()
-                })
+                .spawn_bundle(cut_segment_bundle(
+                    segment,
+                    &mut meshes,
+                    &mut cut_materials,
+                    &globals,
+                ))
                 .insert(MakingCutSegment { start: *start })
                 .insert(CutSegment);
         }
@@ -127,36 +191,25 @@ pub fn start_cut_segment(
 
 // changes the position of the segment according to mouse position
 pub fn making_cut_segment(
-    mut query: Query<(&mut Transform, &Mesh2dHandle, &MakingCutSegment)>,
-    mut meshes: ResMut<Assets<Mesh>>,
+    mut query: Query<(&mut Transform, &MakingCutSegment)>,
     globals: Res<Globals>,
     cursor: Res<Cursor>,
 ) {
-    for (mut transform, mesh_handle, making_segment) in query.iter_mut() {
+    for (mut transform, making_segment) in query.iter_mut() {
         // info!("making polygon");
-        let mesh = meshes.get_mut(&mesh_handle.0).unwrap();
-
         let segment = Segment {
             start: Point::new(making_segment.start.x, making_segment.start.y),
             end: point(cursor.position.x, cursor.position.y),
         };
 
         let segment_meta = get_segment_meta(segment);
-
-        *mesh = Mesh::from(shape::Quad::new(Vec2::new(
-            segment_meta.length,
-            globals.cutting_segment_thickness,
-        )));
-
-        *transform = segment_meta.transform;
+        *transform = cut_segment_transform(&segment_meta, &globals);
     }
 }
 
 pub fn end_cut_segment(
     mut commands: Commands,
-    mut segment_query: Query<(Entity, &mut Transform, &Mesh2dHandle, &MakingCutSegment)>,
-
-    mut meshes: ResMut<Assets<Mesh>>,
+    mut segment_query: Query<(Entity, &mut Transform, &MakingCutSegment)>,
     globals: Res<Globals>,
     // cursor: Res<Cursor>,
     // mut end_segment_event_reader: EventReader<EndCutSegment>,
@@ -167,10 +220,9 @@ pub fn end_cut_segment(
 
     // for end_segment in end_segment_event_reader.iter() {
     if let Some(Action::EndCutSegment { end }) = action_event_reader.iter().next() {
-        for (entity, mut transform, mesh_handle, making_segment) in segment_query.iter_mut() {
+        for (entity, mut transform, making_segment) in segment_query.iter_mut() {
             // info!("end cut segment at pos: {:?}", current_position);
             // let current_position = point(cursor.position.x, cursor.position.y);
-            let mesh = meshes.get_mut(&mesh_handle.0).unwrap();
 
             let segment = Segment {
                 start: Point::new(making_segment.start.x, making_segment.start.y),
@@ -178,13 +230,7 @@ pub fn end_cut_segment(
             };
 
             let segment_meta = get_segment_meta(segment.clone());
-
-            *mesh = Mesh::from(shape::Quad::new(Vec2::new(
-                segment_meta.length,
-                globals.cutting_segment_thickness,
-            )));
-
-            *transform = segment_meta.transform;
+            *transform = cut_segment_transform(&segment_meta, &globals);
             commands.entity(entity).remove::<MakingCutSegment>();
             // commands.entity(entity).despawn_recursive();
             commands.entity(entity).insert(JustMadeCut { segment });
@@ -192,34 +238,6 @@ pub fn end_cut_segment(
     }
 }
 
-pub fn move_after_cut(
-    mut commands: Commands,
-    mut query: Query<(Entity, &mut Transform, &mut ForceMotion, &mut MeshMeta)>,
-    globals: Res<Globals>,
-    time: Res<Time>,
-) {
-    for (entity, mut transform, mut animation, mut mesh_meta) in query.iter_mut() {
-        //
-        // compute new velocity
-        animation.velocity = animation.velocity
-            - animation.velocity.normalize() * globals.friction * time.delta_seconds();
-
-        //
-        // compute new position
-        animation.position = animation.position + animation.velocity * time.delta_seconds();
-
-        transform.translation = animation.position.extend(transform.translation.z);
-
-        mesh_meta.previous_transform = transform.clone();
-
-        // *transform =
-
-        if animation.velocity.length() < globals.min_velocity {
-            commands.entity(entity).remove::<ForceMotion>();
-        }
-    }
-}
-
 pub fn perform_cut(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -228,219 +246,76 @@ pub fn perform_cut(
     mut fill_materials: ResMut<Assets<FillMesh2dMaterial>>,
     cut_query: Query<(Entity, &JustMadeCut)>,
     mut polygon_query: Query<
-        (Entity, &Handle<FillMesh2dMaterial>, &Transform, &MeshMeta),
+        (
+            Entity,
+            &Handle<FillMesh2dMaterial>,
+            &Transform,
+            &MeshMeta,
+            Option<&PatternFrame>,
+        ),
         With<Polygon>,
     >,
-    mut performed_cut_event_writer: EventWriter<PerformedCut>,
+    mut cut_applied_event_writer: EventWriter<CutApplied>,
+    mut cut_rejected_event_writer: EventWriter<CutRejected>,
     mut cut_timer: ResMut<CutTimer>,
+    globals: Res<Globals>,
 ) {
+    // with momentum, the pieces drift apart, see physics_step(..)
+    let push_speed = if globals.momentum {
+        CUT_PUSH_SPEED
+    } else {
+        0.05
+    };
+
     for (cut_entity, cut) in cut_query.iter() {
         commands.entity(cut_entity).despawn();
         let mut do_remove_cut_entity = true;
-        for (poly_entity, _material_handle, transform, mesh_meta) in polygon_query.iter_mut() {
+        // results of the polygons that were not split, to explain a rejected cut
+        let mut missed_results = Vec::new();
+        for (poly_entity, _material_handle, transform, mesh_meta, pattern_frame) in
+            polygon_query.iter_mut()
+        {
             //
             commands.entity(cut_entity).remove::<JustMadeCut>();
             //
             let direction = Vector::from(cut.segment.end - cut.segment.start);
-            // make a ray
-            let ray = Ray {
-                origin: cut.segment.start,
-                direction: direction,
-            };
 
             let (transformed_path, _) = transform_path(&mesh_meta.path, transform);
 
-            let maybe_hit = raycast_path(&ray, transformed_path.clone().iter(), 0.1);
-
-            // only compute the cut if the ray from the cut hits the polygon
-            if let None = maybe_hit {
-                continue;
-            }
-            let mut points: Vec<PolyPoint> = Vec::new();
-
-            for (k, segs) in transformed_path.iter().enumerate() {
-                if k == 0 {
-                    continue;
-                }
-
-                // println!("point: {:?}", mesh_meta.points[k - 1]);
-                // info!("seg: {:?}", segs);
-                let segment = Segment {
-                    start: segs.from(),
-                    end: segs.to(),
-                };
-
-                let intersection = segment.intersect(cut.segment.clone());
-
-                points.push(PolyPoint::Original(segs.from()));
-                if let Some(intersection) = intersection {
-                    points.push(PolyPoint::Intersect(intersection));
-                }
-            }
-
-            let mut only_intersects: Vec<(usize, &Point)> = points
-                .iter()
-                .enumerate()
-                .filter(|(_, x)| x.is_intersect())
-                .map(|(k, x)| {
-                    if let PolyPoint::Intersect(y) = x {
-                        (k, y)
-                    } else {
-                        panic!("should not happen")
-                    }
-                })
-                // .cloned()
-                .collect();
-
-            // println!("only_intersects: {:#?}", only_intersects);
-
-            // if the number of intersection is odd, the cut is invalid because,
-            // the polygon cannot be separated properly
-            let num_intersects = only_intersects.len();
-            if num_intersects % 2 == 1 || num_intersects < 2 {
-                println!("nope, number of intersects: {}", num_intersects);
-                // remove the cut entity
-
-                // // visual check for whether the intersects are positioned and sorted correctly
-                // show_intersects(
-                //     &mut commands,
-                //     &mut meshes,
-                //     &only_intersects,
-                //     &mut fill_materials,
-                // );
-
-                continue;
-            }
-
-            // detect along which axis the intersections vary most from each other,
-            // so that this axis can be used to sort the intersections
-            let delta = (*only_intersects[1].1 - *only_intersects[0].1).abs();
-
-            // sort intersects by distance along the cut segment
-            only_intersects.sort_by(|(_, a), (_, b)| {
-                if delta.x > delta.y {
-                    a.x.partial_cmp(&b.x).unwrap()
-                } else {
-                    a.y.partial_cmp(&b.y).unwrap()
-                }
-            });
-
-            // make pairs of intersects
-            let mut pairs: Vec<(usize, usize)> = Vec::new();
-            for (k, inter) in only_intersects.iter().enumerate() {
-                if k % 2 == 0 {
+            let pieces = match cut_polygon(&cut.segment, &transformed_path) {
+                PolygonCut::Split(pieces) => pieces,
+                other => {
+                    missed_results.push(other);
                     continue;
                 }
+            };
 
-                let prev = only_intersects[k - 1].0;
-                let curr = inter.0;
-
-                pairs.push((prev, curr));
-            }
-
-            // take only the indices of intersects
-            let intersects_inds: Vec<usize> = only_intersects.iter().map(|x| x.0).collect();
-
-            let num_points_in_cut_poly = points.len();
-
-            // let new_polygons: BinaryTree<Vec<PolyPoint>> = BinaryTree::new(points.clone());
-
-            // polygons to operate on at every loop iteration
-            let mut polys: Vec<Vec<usize>> = vec![(0..num_points_in_cut_poly).collect()];
-
-            // polygons that are known to be closed
-            let mut closed_polys: Vec<Vec<PolyPoint>> = Vec::new();
-
-            for (idx, pair) in pairs.iter().enumerate() {
-                // info!("pair idx: {:?}", idx);
-
-                let k0 = pair.0;
-                let k1 = pair.1;
-
-                let rest_of_intersects_inds = intersects_inds.clone().split_off((idx + 1) * 2);
-
-                let mut new_polys_to_explore: Vec<Vec<usize>> = vec![];
-                for poly in polys.clone() {
-                    if poly_contains_intersect(&poly, &vec![k0, k1]) {
-                        // info!("poly contains intersect : {:?}", &vec![k0, k1]);
-                        // check if poly contains the current intersects
-                        let (poly_a, poly_b) = split_poly_at(&poly, k0, k1);
-                        // let (pa, pb) = get_split_poly(&poly, k0, k1);
-
-                        // check if the new polys contains any of the remaining intersect indices
-                        // if it does, then it is not closed
-                        let do_poly_a = poly_contains_intersect(&poly_a, &rest_of_intersects_inds);
-                        let do_poly_b = poly_contains_intersect(&poly_b, &rest_of_intersects_inds);
-
-                        if do_poly_a {
-                            new_polys_to_explore.push(poly_a);
-                        } else {
-                            closed_polys.push(get_poly_points(&poly_a, &points));
-                        }
-                        if do_poly_b {
-                            new_polys_to_explore.push(poly_b);
-                        } else {
-                            closed_polys.push(get_poly_points(&poly_b, &points));
-                        }
-                    }
-                }
-
-                polys = new_polys_to_explore;
-            }
-
-            let mut area_test_passed = true;
             let mut new_entities = Vec::new();
 
+            // from the world to the pattern of the polygon that is cut
+            let world_to_pattern = pattern_frame.copied().unwrap_or_default().0
+                * transform_affine2(transform).inverse();
+
             //
             //
             // crate one path for every closed polygon
-            for poly in closed_polys {
-                //
-                //
-                // convert PolyPoint to Point for every element of poly
-                let poly_points: Vec<Point> = poly
-                    .iter()
-                    .map(|x| match x {
-                        PolyPoint::Original(p) => *p,
-                        PolyPoint::Intersect(i) => *i,
-                    })
-                    .collect();
-
-                //
-                //
-                //
-                // initialize the new path
-                let mut path = Path::builder();
-
-                let mut all_points = vec![];
-                for (k, point) in poly_points.iter().enumerate() {
-                    if k == 0 {
-                        path.begin(*point);
-                        all_points.push(Vec2::new(point.x, point.y));
-                    } else {
-                        path.line_to(*point);
-                        all_points.push(Vec2::new(point.x, point.y));
-                    }
-                }
-                path.close();
-                let built_path = path.build();
-
+            for poly_points in pieces {
+                let all_points: Vec<Vec2> =
+                    poly_points.iter().map(|p| Vec2::new(p.x, p.y)).collect();
+                let built_path = build_closed_path(&poly_points);
                 let area = approximate_signed_area(0.1, &built_path);
-                if area.abs() < 200.0 {
-                    area_test_passed = false;
-                    info!("area too small: {:?}", area);
-                    break;
-                }
 
-                let (mesh, center_of_mass) = make_polygon_mesh(&built_path, true);
+                let (mut mesh, center_of_mass) = make_polygon_mesh(&built_path, true);
+
+                // the pieces keep the pattern of the polygon where it was
+                let piece_frame =
+                    PatternFrame(world_to_pattern * Affine2::from_translation(center_of_mass));
+                set_pattern_uvs(&mut mesh, &piece_frame);
 
                 // Useless at the moment, but here for future use
-                let mat_handle = fill_materials.add(FillMesh2dMaterial {
-                    color: Color::TEAL.into(),
-                    show_com: 0.0, // show center of mass
-                    selected: 0.0,
-                    is_intersecting: 0.0,
-                });
+                let mat_handle = fill_materials.add(
+                    FillMesh2dMaterial::new(globals.cut_polygon, &globals).with_pattern(&globals),
+                );
 
                 let translation =
                     lyon::geom::Translation::new(-center_of_mass.x, -center_of_mass.y);
@@ -471,6 +346,7 @@ pub fn perform_cut(
                         ..Default::default()
                     })
                     .insert(Polygon { in_target: false })
+                    .insert(piece_frame)
                     .insert(MeshMeta {
                         id: rng.gen::<u64>(),
                         path: transformed_path.clone(),
@@ -487,8 +363,9 @@ pub fn perform_cut(
                     .insert(ForceMotion {
                         force: Vec2::new(0.0, 0.0),
                         area,
-                        velocity: cut_normal * 0.05,
+                        velocity: cut_normal * push_speed,
                         position: center_of_mass,
+                        thrown: false,
                     })
                     .id();
 
@@ -514,27 +391,342 @@ pub fn perform_cut(
             }
 
             // remove the polygon that was cut
-            if area_test_passed {
-                commands.entity(poly_entity).despawn();
-                do_remove_cut_entity = false;
+            commands.entity(poly_entity).despawn_recursive();
+            do_remove_cut_entity = false;
 
-                // return;
-            } else {
-                // remove all newly created polygons
-                for entity in new_entities.iter() {
-                    commands.entity(*entity).despawn();
-                }
-            }
+            cut_applied_event_writer.send(CutApplied {
+                cut: cut_entity,
+                source: poly_entity,
+                pieces: new_entities,
+            });
         }
         if do_remove_cut_entity {
             commands.entity(cut_entity).despawn();
+            if let Some(reason) = cut_validity(&missed_results) {
+                cut_rejected_event_writer.send(CutRejected { reason });
+            }
         } else {
-            performed_cut_event_writer.send(PerformedCut);
             remaining_cuts.remaining -= 1;
         }
     }
 }
 
+//
+//
+// Splits one polygon (already transformed to world coordinates) along a cut segment.
+// Used by perform_cut(..) and by preview_cut(..) whenever the cut line moves.
+pub fn cut_polygon(cut_segment: &Segment, transformed_path: &Path) -> PolygonCut {
+    let direction = Vector::from(cut_segment.end - cut_segment.start);
+    // make a ray
+    let ray = Ray {
+        origin: cut_segment.start,
+        direction: direction,
+    };
+
+    let maybe_hit = raycast_path(&ray, transformed_path.clone().iter(), 0.1);
+
+    // only compute the cut if the ray from the cut hits the polygon
+    if let None = maybe_hit {
+        return PolygonCut::Missed;
+    }
+    let mut points: Vec<PolyPoint> = Vec::new();
+
+    for (k, segs) in transformed_path.iter().enumerate() {
+        if k == 0 {
+            continue;
+        }
+
+        // println!("point: {:?}", mesh_meta.points[k - 1]);
+        // info!("seg: {:?}", segs);
+        let segment = Segment {
+            start: segs.from(),
+            end: segs.to(),
+        };
+
+        let intersection = segment.intersect(cut_segment.clone());
+
+        points.push(PolyPoint::Original(segs.from()));
+        if let Some(intersection) = intersection {
+            points.push(PolyPoint::Intersect(intersection));
+        }
+    }
+
+    let mut only_intersects: Vec<(usize, &Point)> = points
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| x.is_intersect())
+        .map(|(k, x)| {
+            if let PolyPoint::Intersect(y) = x {
+                (k, y)
+            } else {
+                panic!("should not happen")
+            }
+        })
+        // .cloned()
+        .collect();
+
+    // println!("only_intersects: {:#?}", only_intersects);
+
+    // if the number of intersection is odd, the cut is invalid because,
+    // the polygon cannot be separated properly
+    let num_intersects = only_intersects.len();
+    if num_intersects == 0 {
+        return PolygonCut::Missed;
+    }
+    if num_intersects % 2 == 1 {
+        return PolygonCut::OddIntersections(num_intersects);
+    }
+
+    // detect along which axis the intersections vary most from each other,
+    // so that this axis can be used to sort the intersections
+    let delta = (*only_intersects[1].1 - *only_intersects[0].1).abs();
+
+    // sort intersects by distance along the cut segment
+    only_intersects.sort_by(|(_, a), (_, b)| {
+        if delta.x > delta.y {
+            a.x.partial_cmp(&b.x).unwrap()
+        } else {
+            a.y.partial_cmp(&b.y).unwrap()
+        }
+    });
+
+    // make pairs of intersects
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+    for (k, inter) in only_intersects.iter().enumerate() {
+        if k % 2 == 0 {
+            continue;
+        }
+
+        let prev = only_intersects[k - 1].0;
+        let curr = inter.0;
+
+        pairs.push((prev, curr));
+    }
+
+    // take only the indices of intersects
+    let intersects_inds: Vec<usize> = only_intersects.iter().map(|x| x.0).collect();
+
+    let num_points_in_cut_poly = points.len();
+
+    // let new_polygons: BinaryTree<Vec<PolyPoint>> = BinaryTree::new(points.clone());
+
+    // polygons to operate on at every loop iteration
+    let mut polys: Vec<Vec<usize>> = vec![(0..num_points_in_cut_poly).collect()];
+
+    // polygons that are known to be closed
+    let mut closed_polys: Vec<Vec<PolyPoint>> = Vec::new();
+
+    for (idx, pair) in pairs.iter().enumerate() {
+        // info!("pair idx: {:?}", idx);
+
+        let k0 = pair.0;
+        let k1 = pair.1;
+
+        let rest_of_intersects_inds = intersects_inds.clone().split_off((idx + 1) * 2);
+
+        let mut new_polys_to_explore: Vec<Vec<usize>> = vec![];
+        for poly in polys.clone() {
+            if poly_contains_intersect(&poly, &vec![k0, k1]) {
+                // info!("poly contains intersect : {:?}", &vec![k0, k1]);
+                // check if poly contains the current intersects
+                let (poly_a, poly_b) = split_poly_at(&poly, k0, k1);
+                // let (pa, pb) = get_split_poly(&poly, k0, k1);
+
+                // check if the new polys contains any of the remaining intersect indices
+                // if it does, then it is not closed
+                let do_poly_a = poly_contains_intersect(&poly_a, &rest_of_intersects_inds);
+                let do_poly_b = poly_contains_intersect(&poly_b, &rest_of_intersects_inds);
+
+                if do_poly_a {
+                    new_polys_to_explore.push(poly_a);
+                } else {
+                    closed_polys.push(get_poly_points(&poly_a, &points));
+                }
+                if do_poly_b {
+                    new_polys_to_explore.push(poly_b);
+                } else {
+                    closed_polys.push(get_poly_points(&poly_b, &points));
+                }
+            }
+        }
+
+        polys = new_polys_to_explore;
+    }
+
+    //
+    //
+    // convert PolyPoint to Point for every element of every closed polygon
+    let mut pieces = Vec::new();
+    for poly in closed_polys {
+        let poly_points: Vec<Point> = poly
+            .iter()
+            .map(|x| match x {
+                PolyPoint::Original(p) => *p,
+                PolyPoint::Intersect(i) => *i,
+            })
+            .collect();
+
+        let area = approximate_signed_area(0.1, &build_closed_path(&poly_points));
+        if area.abs() < MIN_PIECE_AREA {
+            return PolygonCut::TooSmall;
+        }
+
+        pieces.push(poly_points);
+    }
+
+    PolygonCut::Split(pieces)
+}
+
+pub fn build_closed_path(points: &Vec<Point>) -> Path {
+    let mut path = Path::builder();
+    for (k, point) in points.iter().enumerate() {
+        if k == 0 {
+            path.begin(*point);
+        } else {
+            path.line_to(*point);
+        }
+    }
+    path.close();
+    path.build()
+}
+
+//
+//
+// A cut is valid if it splits at least one polygon. Otherwise the reason is given by
+// the polygons that the cut touched.
+pub fn cut_validity(results: &Vec<PolygonCut>) -> Option<InvalidCut> {
+    if results.iter().any(|r| matches!(r, PolygonCut::Split(_))) {
+        return None;
+    }
+
+    if let Some(count) = results.iter().find_map(|r| match r {
+        PolygonCut::OddIntersections(count) => Some(*count),
+        _ => None,
+    }) {
+        return Some(InvalidCut::OddIntersections { count });
+    }
+
+    if results.iter().all(|r| matches!(r, PolygonCut::Missed)) {
+        Some(InvalidCut::MissesEveryPiece)
+    } else {
+        Some(InvalidCut::CutsNothing)
+    }
+}
+
+//
+//
+// Outline of a closed polygon, as a line list
+fn make_outline_mesh(points: &Vec<Point>) -> Mesh {
+    let n = points.len();
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    for k in 0..n {
+        let a = points[k];
+        let b = points[(k + 1) % n];
+        positions.push([a.x, a.y, 0.0]);
+        positions.push([b.x, b.y, 0.0]);
+    }
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+    let uvs = vec![[0.0, 0.0]; positions.len()];
+
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh
+}
+
+//
+//
+// While a cut segment is being made, the pieces that would result from the cut are
+// outlined, and the reason is stored in CutPreview if the cut is invalid.
+pub fn preview_cut(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut cut_preview: ResMut<CutPreview>,
+    mut last_segment: Local<Option<(Point, Point)>>,
+    cursor: Res<Cursor>,
+    globals: Res<Globals>,
+    making_cut_query: Query<&MakingCutSegment>,
+    outline_query: Query<(Entity, &Mesh2dHandle, &Handle<ColorMaterial>), With<CutPreviewOutline>>,
+    polygon_query: Query<(&Transform, &MeshMeta), With<Polygon>>,
+    moved_polygon_query: Query<(), (With<Polygon>, Changed<Transform>)>,
+) {
+    let making_segment = if let Some(making_segment) = making_cut_query.iter().next() {
+        making_segment
+    } else {
+        for (entity, _, _) in outline_query.iter() {
+            commands.entity(entity).despawn();
+        }
+        *last_segment = None;
+        if cut_preview.invalid.is_some() {
+            cut_preview.invalid = None;
+        }
+        return;
+    };
+
+    let segment = Segment {
+        start: Point::new(making_segment.start.x, making_segment.start.y),
+        end: cursor.clone().into(),
+    };
+
+    // the outlines are only computed again when the cut line or a polygon moves
+    if *last_segment == Some((segment.start, segment.end)) && moved_polygon_query.is_empty() {
+        return;
+    }
+    *last_segment = Some((segment.start, segment.end));
+
+    let results: Vec<PolygonCut> = polygon_query
+        .iter()
+        .map(|(transform, mesh_meta)| {
+            let (transformed_path, _) = transform_path(&mesh_meta.path, transform);
+            cut_polygon(&segment, &transformed_path)
+        })
+        .collect();
+
+    let invalid = cut_validity(&results);
+    if cut_preview.invalid != invalid {
+        cut_preview.invalid = invalid;
+    }
+
+    let pieces = results.iter().flat_map(|result| match result {
+        PolygonCut::Split(pieces) => pieces.as_slice(),
+        _ => &[],
+    });
+
+    //
+    //
+    // the outline entities of the previous frame are reused in turn, their meshes and
+    // materials are replaced in place
+    let mut outlines = outline_query.iter();
+    for (color_index, piece) in pieces.enumerate() {
+        // outline colours of the pieces, used in turn
+        let color = globals.preview_colors[color_index % globals.preview_colors.len()];
+
+        if let Some((_, mesh_handle, material_handle)) = outlines.next() {
+            if let Some(mesh) = meshes.get_mut(&mesh_handle.0) {
+                *mesh = make_outline_mesh(piece);
+            }
+            if let Some(material) = materials.get_mut(material_handle) {
+                material.color = color;
+            }
+        } else {
+            commands
+                .spawn_bundle(MaterialMesh2dBundle {
+                    mesh: Mesh2dHandle(meshes.add(make_outline_mesh(piece))),
+                    material: materials.add(color.into()),
+                    transform: Transform::from_translation(Vec3::new(0.0, 0.0, 300.0)),
+                    ..Default::default()
+                })
+                .insert(CutPreviewOutline);
+        }
+    }
+
+    for (entity, _, _) in outlines {
+        commands.entity(entity).despawn();
+    }
+}
+
 // pub fn get_split_poly(l: usize, k0: usize, k1: usize) -> Vec<usize> {
 //     if k0 > k1 {
 //         let mut temp = (k0..l).collect::<Vec<usize>>();
@@ -0,0 +1,384 @@
+use bevy::input::gamepad::{
+    Gamepad, GamepadAxisType, GamepadButtonType, GamepadEvent, GamepadEventType,
+};
+use bevy::prelude::*;
+
+use crate::input::*;
+use crate::util::{Globals, LevelRules};
+
+use std::collections::HashSet;
+
+// analog buttons (the triggers) count as pressed above this value
+pub const GAMEPAD_BUTTON_THRESHOLD: f32 = 0.5;
+
+// stick values smaller than this are ignored
+pub const GAMEPAD_STICK_DEADZONE: f32 = 0.15;
+
+// speed of the virtual cursor at full tilt, in world units per second
+pub const GAMEPAD_CURSOR_SPEED: f32 = 600.0;
+
+// rotation speed with a trigger fully pressed, in radians per second
+pub const GAMEPAD_ROTATION_SPEED: f32 = 1.0;
+
+// every gamepad button that can be bound, also used for the names in bindings.json
+pub const GAMEPAD_BUTTONS: [GamepadButtonType; 19] = [
+    GamepadButtonType::South,
+    GamepadButtonType::East,
+    GamepadButtonType::North,
+    GamepadButtonType::West,
+    GamepadButtonType::C,
+    GamepadButtonType::Z,
+    GamepadButtonType::LeftTrigger,
+    GamepadButtonType::LeftTrigger2,
+    GamepadButtonType::RightTrigger,
+    GamepadButtonType::RightTrigger2,
+    GamepadButtonType::Select,
+    GamepadButtonType::Start,
+    GamepadButtonType::Mode,
+    GamepadButtonType::LeftThumb,
+    GamepadButtonType::RightThumb,
+    GamepadButtonType::DPadUp,
+    GamepadButtonType::DPadDown,
+    GamepadButtonType::DPadLeft,
+    GamepadButtonType::DPadRight,
+];
+
+//
+//
+// State of the active gamepad, built from the GamepadEvents only. Feeding synthetic
+// GamepadEvents into an app is therefore enough to drive the whole gamepad scheme.
+#[derive(Default)]
+pub struct GamepadInput {
+    pub gamepad: Option<Gamepad>,
+    pub left_stick: Vec2,
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    pressed: HashSet<GamepadButtonType>,
+    just_pressed: HashSet<GamepadButtonType>,
+    just_released: HashSet<GamepadButtonType>,
+}
+
+impl GamepadInput {
+    pub fn pressed(&self, button: GamepadButtonType) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn just_pressed(&self, button: GamepadButtonType) -> bool {
+        self.just_pressed.contains(&button)
+    }
+
+    pub fn just_released(&self, button: GamepadButtonType) -> bool {
+        self.just_released.contains(&button)
+    }
+
+    pub fn get_pressed(&self) -> impl Iterator<Item = &GamepadButtonType> {
+        self.pressed.iter()
+    }
+
+    pub fn get_just_pressed(&self) -> impl Iterator<Item = &GamepadButtonType> {
+        self.just_pressed.iter()
+    }
+
+    fn set_button(&mut self, button: GamepadButtonType, value: f32) {
+        if value > GAMEPAD_BUTTON_THRESHOLD {
+            if self.pressed.insert(button) {
+                self.just_pressed.insert(button);
+            }
+        } else if self.pressed.remove(&button) {
+            self.just_released.insert(button);
+        }
+    }
+}
+
+// Tag component for the sprite of the virtual cursor
+#[derive(Component)]
+pub struct GamepadCursorSprite;
+
+//
+//
+// Only the first connected gamepad is used. Its state is reset when it is disconnected.
+pub fn record_gamepad_events_system(
+    mut gamepad_events: EventReader<GamepadEvent>,
+    mut gamepad_input: ResMut<GamepadInput>,
+) {
+    gamepad_input.just_pressed.clear();
+    gamepad_input.just_released.clear();
+
+    for event in gamepad_events.iter() {
+        let GamepadEvent {
+            gamepad,
+            event_type,
+        } = event;
+
+        // events may arrive before the Connected event
+        if gamepad_input.gamepad.is_none() && !matches!(event_type, GamepadEventType::Disconnected)
+        {
+            info!("gamepad {:?} connected", gamepad);
+            gamepad_input.gamepad = Some(*gamepad);
+        }
+
+        if gamepad_input.gamepad != Some(*gamepad) {
+            continue;
+        }
+
+        match event_type {
+            GamepadEventType::Connected => {}
+            GamepadEventType::Disconnected => {
+                info!("gamepad {:?} disconnected", gamepad);
+                *gamepad_input = GamepadInput::default();
+            }
+
+            GamepadEventType::ButtonChanged(button, value) => {
+                match button {
+                    GamepadButtonType::LeftTrigger2 => gamepad_input.left_trigger = *value,
+                    GamepadButtonType::RightTrigger2 => gamepad_input.right_trigger = *value,
+                    _ => {}
+                }
+                gamepad_input.set_button(*button, *value);
+            }
+            GamepadEventType::AxisChanged(GamepadAxisType::LeftStickX, value) => {
+                gamepad_input.left_stick.x = *value;
+            }
+            GamepadEventType::AxisChanged(GamepadAxisType::LeftStickY, value) => {
+                gamepad_input.left_stick.y = *value;
+            }
+            GamepadEventType::AxisChanged(_, _) => {}
+        }
+    }
+}
+
+//
+//
+// The left stick moves the Cursor like the mouse would. The translation and rotation of
+// a grabbed polygon in transform_poly(..) follow the Cursor, so they work unchanged.
+pub fn gamepad_cursor_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    gamepad_input: Res<GamepadInput>,
+    mut cursor: ResMut<Cursor>,
+    mut sprite_query: Query<(Entity, &mut Transform), With<GamepadCursorSprite>>,
+) {
+    if gamepad_input.gamepad.is_none() {
+        for (entity, _) in sprite_query.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    let stick = gamepad_input.left_stick;
+    if stick.length() > GAMEPAD_STICK_DEADZONE {
+        cursor.position += stick * GAMEPAD_CURSOR_SPEED * time.delta_seconds();
+        cursor.pos_relative_to_click = cursor.position - cursor.last_click_position;
+    }
+
+    //
+    //
+    // the OS cursor does not follow the virtual one, so it gets its own sprite
+    if let Some((_, mut transform)) = sprite_query.iter_mut().next() {
+        transform.translation = cursor.position.extend(transform.translation.z);
+    } else {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::WHITE,
+                    custom_size: Some(Vec2::splat(8.0)),
+                    ..default()
+                },
+                transform: Transform::from_translation(cursor.position.extend(500.0)),
+                ..default()
+            })
+            .insert(GamepadCursorSprite);
+    }
+}
+
+//
+//
+// Holding a trigger or a bumper rotates the polygon under the virtual cursor. The
+// triggers are analog, so the rotation speed follows how far they are pressed.
+// The rotation steps are counted in `pending_steps` and sent at most once per frame, so
+// that the speed does not depend on the frame rate. With a snapping rule, the steps are
+// whole snap angles and come at a slower pace. The first step is immediate.
+pub fn gamepad_action(
+    time: Res<Time>,
+    globals: Res<Globals>,
+    level_rules: Res<LevelRules>,
+    gamepad_input: Res<GamepadInput>,
+    cursor: Res<Cursor>,
+    mut pending_steps: Local<Option<f32>>,
+    mut action_event: EventWriter<Action>,
+) {
+    if gamepad_input.gamepad.is_none() || !level_rules.can_rotate() {
+        *pending_steps = None;
+        return;
+    }
+
+    let mut dir = gamepad_input.left_trigger - gamepad_input.right_trigger;
+    if gamepad_input.pressed(GamepadButtonType::LeftTrigger) {
+        dir += 1.0;
+    }
+    if gamepad_input.pressed(GamepadButtonType::RightTrigger) {
+        dir -= 1.0;
+    }
+
+    if dir.abs() <= GAMEPAD_STICK_DEADZONE {
+        *pending_steps = None;
+        return;
+    }
+
+    let steps_per_second =
+        GAMEPAD_ROTATION_SPEED / level_rules.rotation_step(globals.min_turn_angle);
+    let pending = match pending_steps.as_mut() {
+        Some(pending) => {
+            *pending += dir * steps_per_second * time.delta_seconds();
+            pending
+        }
+        None => pending_steps.insert(dir.signum()),
+    };
+
+    let steps = pending.trunc();
+    if steps != 0.0 {
+        *pending -= steps;
+        action_event.send(Action::RotateAt {
+            pos: cursor.position,
+            dir: steps,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::RotationRule;
+
+    use bevy::ecs::event::Events;
+    use std::time::{Duration, Instant};
+
+    fn app(level_rules: LevelRules) -> App {
+        let mut app = App::new();
+        app.add_event::<GamepadEvent>()
+            .add_event::<Action>()
+            .insert_resource(Time::default())
+            .insert_resource(Globals::default())
+            .insert_resource(level_rules)
+            .insert_resource(GamepadInput::default())
+            .insert_resource(Cursor::default())
+            .add_system_to_stage(CoreStage::PreUpdate, record_gamepad_events_system)
+            .add_system(gamepad_action);
+        app
+    }
+
+    fn send(app: &mut App, event_type: GamepadEventType) {
+        app.world
+            .resource_mut::<Events<GamepadEvent>>()
+            .send(GamepadEvent {
+                gamepad: Gamepad { id: 0 },
+                event_type,
+            });
+    }
+
+    // runs `frames` frames of `frame_time` seconds, and returns the rotation steps sent
+    fn run(app: &mut App, start: Instant, frames: u32, frame_time: f32) -> Vec<f32> {
+        let mut steps = Vec::new();
+        for frame in 1..=frames {
+            let now = start + Duration::from_secs_f32(frame as f32 * frame_time);
+            app.world.resource_mut::<Time>().update_with_instant(now);
+            app.update();
+
+            let events = app.world.resource::<Events<Action>>();
+            for action in events.get_reader().iter(events) {
+                if let Action::RotateAt { dir, .. } = action {
+                    steps.push(*dir);
+                }
+            }
+            app.world.resource_mut::<Events<Action>>().clear();
+        }
+        steps
+    }
+
+    fn hold_right_trigger(level_rules: LevelRules, frames: u32, frame_time: f32) -> Vec<f32> {
+        let mut app = app(level_rules);
+        let start = Instant::now();
+        app.world.resource_mut::<Time>().update_with_instant(start);
+        send(&mut app, GamepadEventType::Connected);
+        send(
+            &mut app,
+            GamepadEventType::ButtonChanged(GamepadButtonType::RightTrigger2, 1.0),
+        );
+        run(&mut app, start, frames, frame_time)
+    }
+
+    #[test]
+    fn rotation_speed_does_not_depend_on_the_frame_rate() {
+        let steps_per_second = GAMEPAD_ROTATION_SPEED / Globals::default().min_turn_angle;
+        for fps in [30, 60, 144] {
+            let frame_time = 1.0 / fps as f32;
+            let steps: f32 = hold_right_trigger(LevelRules::default(), fps, frame_time)
+                .iter()
+                .sum();
+
+            // the first step is sent on the first frame, then the trigger is held for the
+            // other frames
+            let expected = -(1.0 + steps_per_second * (fps - 1) as f32 * frame_time);
+            assert!((steps - expected).abs() < 1.0, "{} vs {}", steps, expected);
+        }
+    }
+
+    #[test]
+    fn snapped_rotation_is_not_sent_every_frame() {
+        let level_rules = LevelRules {
+            rotation: RotationRule::Snap(std::f32::consts::FRAC_PI_2),
+            ..Default::default()
+        };
+        let steps = hold_right_trigger(level_rules, 120, 1.0 / 60.0);
+
+        // the first step, then one more after a quarter turn at full speed (1.6 s)
+        assert_eq!(steps, vec![-1.0, -1.0]);
+    }
+
+    #[test]
+    fn released_trigger_stops_the_rotation() {
+        let mut app = app(LevelRules::default());
+        let start = Instant::now();
+        app.world.resource_mut::<Time>().update_with_instant(start);
+        send(&mut app, GamepadEventType::Connected);
+        send(
+            &mut app,
+            GamepadEventType::ButtonChanged(GamepadButtonType::LeftTrigger2, 1.0),
+        );
+        assert!(!run(&mut app, start, 10, 1.0 / 60.0).is_empty());
+
+        send(
+            &mut app,
+            GamepadEventType::ButtonChanged(GamepadButtonType::LeftTrigger2, 0.0),
+        );
+        let later = start + Duration::from_secs_f32(10.0 / 60.0);
+        assert!(run(&mut app, later, 10, 1.0 / 60.0).is_empty());
+    }
+
+    #[test]
+    fn gamepad_chord_still_cuts_after_a_keyboard_rebind() {
+        use crate::bindings::{Bindings, ButtonState, InputAction, InputButton};
+
+        let mut bindings = Bindings::default();
+        bindings.rebind(InputAction::StartCut, vec![], InputButton::Key(KeyCode::X));
+
+        let mut app = app(LevelRules::default());
+        send(&mut app, GamepadEventType::Connected);
+        send(
+            &mut app,
+            GamepadEventType::ButtonChanged(GamepadButtonType::West, 1.0),
+        );
+        app.update();
+
+        let keyboard = Input::<KeyCode>::default();
+        let mouse = Input::<MouseButton>::default();
+        let state = ButtonState {
+            keyboard: &keyboard,
+            mouse: &mouse,
+            gamepad: app.world.resource::<GamepadInput>(),
+            wheel_up: false,
+            wheel_down: false,
+        };
+        assert!(bindings.just_pressed(InputAction::StartCut, &state));
+    }
+}
@@ -3,9 +3,13 @@ use bevy::{
     prelude::*,
 };
 
+use crate::bindings::*;
+use crate::camera::screen_to_world;
 use crate::cut::*;
+use crate::gamepad::GamepadInput;
 // use crate::load::QuickLoad;
 use crate::poly::MakingPolygon;
+use crate::touch::*;
 // use crate::save::SaveMeshEvent;
 use crate::util::{Globals, MovingPathPoint, TurnPolyIntoTarget};
 // use crate::util::Globals;
@@ -40,6 +44,11 @@ pub enum Action {
     LoadTarget,
     MovePathPoint,
     DeleteTarget,
+    CyclePoly { dir: i32 },
+    FlipAt { pos: Vec2 },
+    ToggleSnapping,
+    ToggleMomentum,
+    ReleasePoly, // end of a touch drag or twist
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -48,6 +57,8 @@ pub struct Cursor {
     pub pos_relative_to_click: Vec2,
     pub last_click_position: Vec2,
     pub last_right_click_position: Vec2,
+    // angle of a two-finger twist, used instead of the vertical mouse distance to rotate
+    pub touch_twist: Option<f32>,
 }
 
 impl Default for Cursor {
@@ -57,6 +68,7 @@ impl Default for Cursor {
             pos_relative_to_click: Vec2::ZERO,
             last_click_position: Vec2::ZERO,
             last_right_click_position: Vec2::ZERO,
+            touch_twist: None,
         }
     }
 }
@@ -83,39 +95,98 @@ impl Into<Point> for Cursor {
 pub fn record_mouse_events_system(
     mut cursor_moved_events: EventReader<CursorMoved>,
     mouse_button_input: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_input: Res<GamepadInput>,
+    touches: Res<Touches>,
+    time: Res<Time>,
+    mut touch_gestures: ResMut<TouchGestures>,
+    mut action_event: EventWriter<Action>,
     mut cursor_res: ResMut<Cursor>,
     mut windows: ResMut<Windows>,
-    cam_transform_query: Query<&Transform, With<OrthographicProjection>>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+    bindings: Res<Bindings>,
 ) {
+    let window_size = if let Some(window) = windows.get_primary_mut() {
+        Vec2::new(window.width(), window.height())
+    } else {
+        return;
+    };
+
     for event in cursor_moved_events.iter() {
         let cursor_in_pixels = event.position; // lower left is origin
-        let window_size = Vec2::new(
-            windows.get_primary_mut().unwrap().width(),
-            windows.get_primary_mut().unwrap().height(),
-        );
-
-        let screen_position = cursor_in_pixels - window_size / 2.0;
-
-        if let Some(cam_transform) = cam_transform_query.iter().next() {
-            // this variable currently has no effect
-            let scale = 1.0;
 
-            let cursor_vec4: Vec4 = cam_transform.compute_matrix()
-                * screen_position.extend(0.0).extend(1.0 / (scale))
-                * scale;
-
-            let cursor_pos = Vec2::new(cursor_vec4.x, cursor_vec4.y);
-            cursor_res.position = cursor_pos;
+        if let Some((cam_transform, projection)) = camera_query.iter().next() {
+            cursor_res.position =
+                screen_to_world(cursor_in_pixels, window_size, cam_transform, projection);
             cursor_res.pos_relative_to_click = cursor_res.position - cursor_res.last_click_position;
         }
     }
 
-    if mouse_button_input.just_pressed(MouseButton::Left) {
+    //
+    //
+    // touches: the gestures move the Cursor and send the same actions as the mouse
+    if let Some((cam_transform, projection)) = camera_query.iter().next() {
+        let touch_points: Vec<TouchPoint> = touches
+            .iter()
+            .map(|touch| TouchPoint {
+                id: touch.id(),
+                position: screen_to_world(touch.position(), window_size, cam_transform, projection),
+            })
+            .collect();
+
+        let now = time.seconds_since_startup() as f32;
+        for gesture in touch_gestures.update(&touch_points, now) {
+            match gesture {
+                TouchGesture::Move { pos } => {
+                    cursor_res.position = pos;
+                    cursor_res.pos_relative_to_click =
+                        cursor_res.position - cursor_res.last_click_position;
+                }
+                TouchGesture::DragStart { pos } => {
+                    cursor_res.last_click_position = pos;
+                    action_event.send(Action::MaybeTranslatePoly);
+                }
+                TouchGesture::TwistStart { center } => {
+                    cursor_res.position = center;
+                    cursor_res.last_right_click_position = center;
+                    cursor_res.touch_twist = Some(0.0);
+                    action_event.send(Action::MaybeRotatePoly);
+                }
+                TouchGesture::Twist { angle } => cursor_res.touch_twist = Some(angle),
+                TouchGesture::DragEnd => action_event.send(Action::ReleasePoly),
+                TouchGesture::TwistEnd => {
+                    cursor_res.touch_twist = None;
+                    action_event.send(Action::ReleasePoly);
+                }
+                TouchGesture::CutStart { pos } => {
+                    action_event.send(Action::StartMakingCutSegment { start: pos })
+                }
+                TouchGesture::CutEnd { pos } => {
+                    action_event.send(Action::EndCutSegment { end: pos })
+                }
+            }
+        }
+    }
+
+    let state = ButtonState {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad: &gamepad_input,
+        wheel_up: false,
+        wheel_down: false,
+    };
+
+    // the translation and rotation may be bound to other buttons than the mouse buttons
+    if mouse_button_input.just_pressed(MouseButton::Left)
+        || bindings.trigger_just_pressed(InputAction::Translate, &state)
+    {
         cursor_res.last_click_position = cursor_res.position;
         cursor_res.pos_relative_to_click = Vec2::ZERO;
     }
 
-    if mouse_button_input.just_pressed(MouseButton::Right) {
+    if mouse_button_input.just_pressed(MouseButton::Right)
+        || bindings.trigger_just_pressed(InputAction::Rotate, &state)
+    {
         cursor_res.last_right_click_position = cursor_res.position;
     }
 }
@@ -127,27 +198,14 @@ pub fn direct_action(
     making_cut_query: Query<(Entity, &MakingCutSegment)>,
     keyboard_input: Res<Input<KeyCode>>,
     mouse_button_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<GamepadInput>,
     mut mouse_wheel_events: EventReader<MouseWheel>,
-    // mut quickload_event_writer: EventWriter<QuickLoad>,
-
-    // mut start_polygon: EventWriter<StartMakingPolygon>,
-    // mut start_segment: EventWriter<EndSegment>,
-    // mut start_cut_segment: EventWriter<StartMakingCutSegment>,
-    // mut end_polygon: EventWriter<EndMakingPolygon>,
-    // mut delete_event: EventWriter<DeleteEvent>,
     mut action_event: EventWriter<Action>,
     mut turn_poly_into_target_event_writer: EventWriter<TurnPolyIntoTarget>,
-    // mut quicksave_event_writer: EventWriter<SaveMeshEvent>,
-    // mut end_cut_segment: EventWriter<EndCutSegment>,
     cursor: Res<Cursor>,
     globals: ResMut<Globals>,
+    bindings: Res<Bindings>,
 ) {
-    // let mouse_pressed = mouse_button_input.pressed(MouseButton::Left);
-
-    let mouse_just_pressed = mouse_button_input.just_pressed(MouseButton::Left);
-    let mouse_right_just_pressed = mouse_button_input.just_pressed(MouseButton::Right);
-    let mouse_just_released = mouse_button_input.just_released(MouseButton::Left);
-
     let mut mouse_wheel_up = false;
     let mut mouse_wheel_down = false;
     if let Some(mouse_wheel) = mouse_wheel_events.iter().next() {
@@ -159,6 +217,14 @@ pub fn direct_action(
         }
     }
 
+    let state = ButtonState {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad: &gamepad_input,
+        wheel_up: mouse_wheel_up,
+        wheel_down: mouse_wheel_down,
+    };
+
     let mut pos = cursor.position;
     if globals.snap_to_grid {
         pos = (pos.clone() / globals.grid_size).round() * globals.grid_size;
@@ -167,181 +233,147 @@ pub fn direct_action(
     let making_cut = making_cut_query.iter().next().is_some();
     let making_poly = making_poly_query.iter().next().is_some();
 
-    // only used for pattern matching
-    let pressing_a = keyboard_input.pressed(KeyCode::A);
-    let pressing_q = keyboard_input.pressed(KeyCode::Q);
-    let pressing_c = keyboard_input.pressed(KeyCode::C);
-    let pressing_s = keyboard_input.pressed(KeyCode::S);
-    let pressing_e = keyboard_input.pressed(KeyCode::E);
-
-    let pressed_g = keyboard_input.just_pressed(KeyCode::G);
-    let _pressed_h = keyboard_input.just_pressed(KeyCode::H);
-    let pressed_s = keyboard_input.just_pressed(KeyCode::S);
-    let pressed_l = keyboard_input.just_pressed(KeyCode::L);
-    let _pressed_z = keyboard_input.just_pressed(KeyCode::Z);
-    let pressed_t = keyboard_input.just_pressed(KeyCode::T);
-    let pressed_delete = keyboard_input.just_pressed(KeyCode::Delete);
-    let pressed_enter = keyboard_input.just_pressed(KeyCode::Return);
-    let pressed_escape = keyboard_input.just_pressed(KeyCode::Escape);
-    let pressed_space = keyboard_input.just_pressed(KeyCode::Space);
-    let pressed_back = keyboard_input.just_pressed(KeyCode::Back);
+    let pressed = |action: InputAction| bindings.just_pressed(action, &state);
 
-    // match keys / mouse buttons / mouse wheel combination and send event to corresponding action
-    match (
-        keyboard_input.pressed(KeyCode::LShift),
-        keyboard_input.pressed(KeyCode::LControl),
-        keyboard_input.pressed(KeyCode::Space),
-    ) {
-        (true, true, true) if pressed_delete => action_event.send(Action::DeleteTarget),
-        //
-        //
-        //select
-        (false, false, false)
-            if mouse_just_pressed && !making_poly && !making_cut && pressing_s =>
-        {
-            info!("select");
-            action_event.send(Action::SelectPoly {
-                pos: pos,
-                keep_selected: pressing_a,
-            });
-        }
-        //
-        //
-        // cut on mouse release
-        (_, _, _) if mouse_just_released && making_cut => {
-            action_event.send(Action::EndCutSegment { end: pos });
-        }
-        //
-        //
-        // revert to initial state
-        (_, _, _) if pressed_back => {
-            action_event.send(Action::RevertToInit);
-        }
-
-        // Start a cut
-        // cannot start a cut segment if one is already being made
-        (false, false, false)
-            if pressing_c && mouse_just_pressed && making_cut_query.iter().count() == 0 =>
-        {
-            action_event.send(Action::StartMakingCutSegment { start: pos });
-        }
-
-        // Start a cut as well!
-        // cannot start a cut segment if one is already being made
-        (false, true, false) if mouse_just_pressed && making_cut_query.iter().count() == 0 => {
-            action_event.send(Action::StartMakingCutSegment { start: pos });
-        }
-
-        (_, _, _) if pressed_g => action_event.send(Action::ToggleGrid),
-
-        (false, false, false) if mouse_wheel_up => {
-            action_event.send(Action::RotateAt { pos: pos, dir: 1.0 })
-        }
-        (false, false, false) if mouse_wheel_down => action_event.send(Action::RotateAt {
+    // match the chords of the binding table and send the corresponding action.
+    // The order of the branches is the priority of the actions
+    if pressed(InputAction::DeleteTarget) {
+        action_event.send(Action::DeleteTarget);
+    }
+    //
+    //
+    //select
+    else if pressed(InputAction::SelectKeep) && !making_poly && !making_cut {
+        info!("select");
+        action_event.send(Action::SelectPoly {
+            pos: pos,
+            keep_selected: true,
+        });
+    } else if pressed(InputAction::Select) && !making_poly && !making_cut {
+        info!("select");
+        action_event.send(Action::SelectPoly {
+            pos: pos,
+            keep_selected: false,
+        });
+    }
+    //
+    //
+    // cut on release of the button that started the cut
+    else if making_cut && bindings.trigger_just_released(InputAction::StartCut, &state) {
+        action_event.send(Action::EndCutSegment { end: pos });
+    }
+    //
+    //
+    // revert to initial state
+    else if pressed(InputAction::RevertToInit) {
+        action_event.send(Action::RevertToInit);
+    }
+    // Start a cut
+    // cannot start a cut segment if one is already being made
+    else if pressed(InputAction::StartCut) && !making_cut {
+        action_event.send(Action::StartMakingCutSegment { start: pos });
+    } else if pressed(InputAction::NextPiece) {
+        action_event.send(Action::CyclePoly { dir: 1 });
+    } else if pressed(InputAction::PreviousPiece) {
+        action_event.send(Action::CyclePoly { dir: -1 });
+    } else if pressed(InputAction::Flip) {
+        action_event.send(Action::FlipAt { pos });
+    } else if pressed(InputAction::ToggleSnapping) {
+        action_event.send(Action::ToggleSnapping);
+    } else if pressed(InputAction::ToggleMomentum) {
+        action_event.send(Action::ToggleMomentum);
+    } else if pressed(InputAction::ToggleGrid) {
+        action_event.send(Action::ToggleGrid);
+    } else if pressed(InputAction::RotateLeft) {
+        action_event.send(Action::RotateAt { pos: pos, dir: 1.0 });
+    } else if pressed(InputAction::RotateRight) {
+        action_event.send(Action::RotateAt {
             pos: pos,
             dir: -1.0,
-        }),
-
-        //
-        //
-        //
-
-        //
-        //
-        //
-        ///////////////////////////////// start of Part of level making  /////////////////////////////
-        //
-        //
-        // ends the current polygon being made
-        (false, false, _)
-            if (pressed_enter || mouse_right_just_pressed || pressed_space) && making_poly =>
-        {
-            action_event.send(Action::EndMakingPolygon);
-        }
-
-        (false, false, false) if mouse_just_pressed && pressing_q => {
-            action_event.send(Action::MovePathPoint);
-        }
-        //
-        //
-        // a click ends the current segment
-        (false, false, false) if mouse_just_pressed && making_poly => {
-            action_event.send(Action::EndSegment {
-                pos: cursor.clone().into(),
-            });
-        }
-
-        (false, true, false) if pressed_s => action_event.send(Action::QuickSave),
-        (true, true, false) if pressed_s => action_event.send(Action::SaveOneDialog),
-        (true, true, false) if pressed_l => action_event.send(Action::LoadDialog),
-        (true, true, false) if pressed_t => action_event.send(Action::LoadTarget),
-        (false, true, false) if pressed_l => {
-            action_event.send(Action::QuickLoad { maybe_name: None })
-        }
-        (false, false, false) if pressing_e && pressed_t => {
-            turn_poly_into_target_event_writer.send(TurnPolyIntoTarget {});
-            // action_event.send(Action::QuickLoadTarget { maybe_name: None })
-        }
-
-        //
-        //
-        //
-        (false, false, false) if pressed_escape && making_cut => {
-            // delete cut segment
-            let (entity, _) = making_cut_query.single();
-            commands.entity(entity).despawn();
-        }
-
-        //
-        //
-        //
-        (false, false, false) if making_poly && (pressed_delete || pressed_escape) => {
-            action_event.send(Action::DeleteMakingPoly);
-        }
-
-        // cannot start a polygon if one is already being made
-        (true, false, false) if (mouse_just_pressed && making_poly_query.iter().count() == 0) => {
-            action_event.send(Action::StartMakingPolygon {
-                pos: cursor.clone().into(),
-            })
-        }
-
-        // add point
-        (true, false, false) if mouse_right_just_pressed && !making_poly && !making_cut => {
-            action_event.send(Action::AddPointAt { pos: pos });
-        }
-
-        (false, false, false) if pressed_delete && pressing_a => {
-            action_event.send(Action::DeleteAll)
-        }
-
-        (_, _, _) if pressed_delete => action_event.send(Action::DeleteSelected),
-
-        (false, false, true) if pressed_space => action_event.send(Action::RotateAt {
+        });
+    }
+    //
+    //
+    //
+    ///////////////////////////////// start of Part of level making  /////////////////////////////
+    //
+    //
+    // ends the current polygon being made
+    else if pressed(InputAction::EndMakingPolygon) && making_poly {
+        action_event.send(Action::EndMakingPolygon);
+    } else if pressed(InputAction::MovePathPoint) {
+        action_event.send(Action::MovePathPoint);
+    }
+    //
+    //
+    // a click ends the current segment
+    else if pressed(InputAction::EndSegment) && making_poly {
+        action_event.send(Action::EndSegment {
+            pos: cursor.clone().into(),
+        });
+    } else if pressed(InputAction::QuickSave) {
+        action_event.send(Action::QuickSave);
+    } else if pressed(InputAction::SaveOneDialog) {
+        action_event.send(Action::SaveOneDialog);
+    } else if pressed(InputAction::LoadDialog) {
+        action_event.send(Action::LoadDialog);
+    } else if pressed(InputAction::LoadTarget) {
+        action_event.send(Action::LoadTarget);
+    } else if pressed(InputAction::QuickLoad) {
+        action_event.send(Action::QuickLoad { maybe_name: None });
+    } else if pressed(InputAction::TurnPolyIntoTarget) {
+        turn_poly_into_target_event_writer.send(TurnPolyIntoTarget {});
+    }
+    //
+    //
+    //
+    else if pressed(InputAction::CancelCut) && making_cut {
+        // delete cut segment
+        let (entity, _) = making_cut_query.single();
+        commands.entity(entity).despawn();
+    }
+    //
+    //
+    //
+    else if pressed(InputAction::DeleteMakingPoly) && making_poly {
+        action_event.send(Action::DeleteMakingPoly);
+    }
+    // cannot start a polygon if one is already being made
+    else if pressed(InputAction::StartMakingPolygon) && !making_poly {
+        action_event.send(Action::StartMakingPolygon {
+            pos: cursor.clone().into(),
+        })
+    }
+    // add point
+    else if pressed(InputAction::AddPoint) && !making_poly && !making_cut {
+        action_event.send(Action::AddPointAt { pos: pos });
+    } else if pressed(InputAction::DeleteAll) {
+        action_event.send(Action::DeleteAll)
+    } else if pressed(InputAction::DeleteSelected) {
+        action_event.send(Action::DeleteSelected);
+    } else if pressed(InputAction::RotateFast) {
+        action_event.send(Action::RotateAt {
             pos,
             dir: -10.0, // use dir to multiply the rotation angle
-        }),
-
-        ///////////////////////////////// end of Part of level making  /////////////////////////////
-
-        //
-        //
-        // low_priority (but still important)
-        //
-        //
-        //
-        // translation (q is for moving points in level-making)
-        (false, false, false) if mouse_just_pressed && !pressing_q && !making_cut => {
-            action_event.send(Action::MaybeTranslatePoly)
-        }
-        //
-        //
-        //
-        // rotation
-        (false, false, false) if mouse_right_just_pressed && !pressing_q => {
-            action_event.send(Action::MaybeRotatePoly)
-        }
-        _ => {}
+        });
+    }
+    ///////////////////////////////// end of Part of level making  /////////////////////////////
+    //
+    //
+    // low_priority (but still important)
+    //
+    //
+    //
+    // translation
+    else if pressed(InputAction::Translate) && !making_cut {
+        action_event.send(Action::MaybeTranslatePoly)
+    }
+    //
+    //
+    //
+    // rotation
+    else if pressed(InputAction::Rotate) {
+        action_event.send(Action::MaybeRotatePoly)
     }
 }
 
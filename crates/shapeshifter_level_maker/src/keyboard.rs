@@ -0,0 +1,234 @@
+use bevy::prelude::*;
+
+use crate::bindings::*;
+use crate::cut::*;
+use crate::gamepad::GamepadInput;
+use crate::input::Cursor;
+use crate::material::CutMesh2dMaterial;
+use crate::util::*;
+
+use lyon::tessellation::math::Point;
+
+// translation of one arrow key press, without and with the FineStep key held
+pub const KEYBOARD_COARSE_STEP: f32 = 20.0;
+pub const KEYBOARD_FINE_STEP: f32 = 1.0;
+
+// the keyboard cut line is long enough to cross any polygon on the screen
+pub const KEYBOARD_CUT_HALF_LENGTH: f32 = 1000.0;
+
+// holding a step key repeats it after the delay, in seconds
+pub const KEY_REPEAT_DELAY: f32 = 0.35;
+pub const KEY_REPEAT_INTERVAL: f32 = 0.04;
+
+const STEP_ACTIONS: [InputAction; 6] = [
+    InputAction::NudgeUp,
+    InputAction::NudgeDown,
+    InputAction::NudgeLeft,
+    InputAction::NudgeRight,
+    InputAction::RotateStepLeft,
+    InputAction::RotateStepRight,
+];
+
+//
+//
+// Cut line placed from the keyboard. It is moved and rotated with the same keys as the
+// polygons, and becomes a JustMadeCut when confirmed, exactly like a mouse cut.
+#[derive(Component)]
+pub struct KeyboardCutLine {
+    pub center: Vec2,
+    pub angle: f32,
+}
+
+impl KeyboardCutLine {
+    pub fn segment(&self) -> Segment {
+        let half = Vec2::new(self.angle.cos(), self.angle.sin()) * KEYBOARD_CUT_HALF_LENGTH;
+        let start = self.center - half;
+        let end = self.center + half;
+        Segment {
+            start: Point::new(start.x, start.y),
+            end: Point::new(end.x, end.y),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct KeyRepeat {
+    held_for: f32,
+    since_last: f32,
+}
+
+//
+//
+// Keyboard-only play. The Selected polygons (see cycle_selected_poly(..)) are moved with
+// the arrows and rotated by one rotation step of the LevelRules, or the keyboard cut line
+// if there is one.
+pub fn keyboard_action(
+    mut commands: Commands,
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<GamepadInput>,
+    bindings: Res<Bindings>,
+    globals: Res<Globals>,
+    cursor: Res<Cursor>,
+    remaining_cuts: Res<RemainingCuts>,
+    level_rules: Res<LevelRules>,
+    mut key_repeat: ResMut<KeyRepeat>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut cut_materials: ResMut<Assets<CutMesh2dMaterial>>,
+    mut poly_query: Query<(Entity, &mut Transform, &MeshMeta), (With<Polygon>, With<Selected>)>,
+    mut cut_line_query: Query<(Entity, &mut KeyboardCutLine, &mut Transform), Without<Polygon>>,
+    mut collision_test_writer: EventWriter<TestCollisionEvent>,
+) {
+    let state = ButtonState {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad: &gamepad_input,
+        wheel_up: false,
+        wheel_down: false,
+    };
+
+    //
+    //
+    // key repeat, shared by all the step keys
+    let mut repeat_now = false;
+    if STEP_ACTIONS
+        .iter()
+        .any(|action| bindings.pressed(*action, &state))
+    {
+        key_repeat.held_for += time.delta_seconds();
+        key_repeat.since_last += time.delta_seconds();
+        if key_repeat.held_for > KEY_REPEAT_DELAY && key_repeat.since_last >= KEY_REPEAT_INTERVAL {
+            key_repeat.since_last = 0.0;
+            repeat_now = true;
+        }
+    } else {
+        *key_repeat = KeyRepeat::default();
+    }
+
+    let step = |action: InputAction| {
+        bindings.just_pressed(action, &state) || (repeat_now && bindings.pressed(action, &state))
+    };
+
+    let step_size = if bindings.pressed(InputAction::FineStep, &state) {
+        KEYBOARD_FINE_STEP
+    } else {
+        KEYBOARD_COARSE_STEP
+    };
+
+    let mut translation = Vec2::ZERO;
+    if step(InputAction::NudgeUp) {
+        translation.y += step_size;
+    }
+    if step(InputAction::NudgeDown) {
+        translation.y -= step_size;
+    }
+    if step(InputAction::NudgeLeft) {
+        translation.x -= step_size;
+    }
+    if step(InputAction::NudgeRight) {
+        translation.x += step_size;
+    }
+
+    let mut rotation_dir = 0.0;
+    if step(InputAction::RotateStepLeft) {
+        rotation_dir += 1.0;
+    }
+    if step(InputAction::RotateStepRight) {
+        rotation_dir -= 1.0;
+    }
+
+    //
+    //
+    // adjust, confirm or cancel the cut line
+    if let Some((entity, mut cut_line, mut transform)) = cut_line_query.iter_mut().next() {
+        if bindings.just_pressed(InputAction::CancelCut, &state) {
+            commands.entity(entity).despawn();
+            return;
+        }
+
+        cut_line.center += translation;
+        // the level rules do not apply to the cut line
+        cut_line.angle += rotation_dir * globals.min_turn_angle;
+        *transform = cut_segment_transform(&get_segment_meta(cut_line.segment()), &globals);
+
+        if bindings.just_pressed(InputAction::ConfirmCut, &state) {
+            if remaining_cuts.remaining > 0 {
+                commands
+                    .entity(entity)
+                    .remove::<KeyboardCutLine>()
+                    .insert(JustMadeCut {
+                        segment: cut_line.segment(),
+                    });
+            } else {
+                commands.entity(entity).despawn();
+            }
+        }
+        return;
+    }
+
+    //
+    //
+    // start a cut line through the selected polygon, or through the cursor
+    if bindings.just_pressed(InputAction::KeyboardCut, &state) {
+        let center = if let Some((_, transform, _)) = poly_query.iter().next() {
+            transform.translation.truncate()
+        } else {
+            cursor.position
+        };
+
+        let cut_line = KeyboardCutLine { center, angle: 0.0 };
+
+        commands
+            .spawn_bundle(cut_segment_bundle(
+                cut_line.segment(),
+                &mut meshes,
+                &mut cut_materials,
+                &globals,
+            ))
+            .insert(cut_line)
+            .insert(CutSegment);
+        return;
+    }
+
+    // several Selected polygons turn together around their combined center of mass
+    let members: Vec<(Vec2, f32)> = poly_query
+        .iter()
+        .map(|(_, transform, mesh_meta)| (transform.translation.truncate(), mesh_meta.area()))
+        .collect();
+    let center = group_center(&members);
+
+    // the first polygon decides the rotation step, so that the group stays rigid
+    let delta_angle = match poly_query.iter().next() {
+        Some((_, transform, _)) if rotation_dir != 0.0 && level_rules.can_rotate() => {
+            let (axis, angle) = transform.rotation.to_axis_angle();
+            level_rules.rotate(axis.z * angle, rotation_dir, globals.min_turn_angle)
+                - axis.z * angle
+        }
+        _ => 0.0,
+    };
+
+    for (_, mut transform, _) in poly_query.iter_mut() {
+        transform.translation += translation.extend(0.0);
+        if delta_angle != 0.0 {
+            let (axis, angle) = transform.rotation.to_axis_angle();
+            transform.rotation = Quat::from_rotation_z(axis.z * angle + delta_angle);
+            transform.translation = rotate_around(
+                transform.translation.truncate(),
+                center + translation,
+                delta_angle,
+            )
+            .extend(transform.translation.z);
+        }
+    }
+
+    // like a mouse release, the collisions are checked when a step key is released
+    if STEP_ACTIONS
+        .iter()
+        .any(|action| bindings.trigger_just_released(*action, &state))
+    {
+        for (entity, _, _) in poly_query.iter() {
+            collision_test_writer.send(TestCollisionEvent(entity));
+        }
+    }
+}
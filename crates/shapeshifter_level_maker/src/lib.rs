@@ -1,23 +1,45 @@
 // TODO: delete this example
 
+mod camera;
 mod cut;
+mod outline;
 mod poly;
 mod target;
 mod view;
 
+pub mod bindings;
+pub mod gamepad;
 pub mod input;
+pub mod keyboard;
 pub mod load_poly_wasm;
 pub mod material;
+pub mod overlap;
+pub mod physics;
+pub mod snap;
+pub mod stats;
+pub mod theme;
+pub mod touch;
 pub mod util;
+pub mod validate;
 
 ///// Delete when building for wasm
 
+use bindings::*;
+use camera::*;
 use cut::*;
+use gamepad::*;
 use input::*;
+use keyboard::*;
 use load_poly_wasm::*;
 use material::*;
+use outline::*;
+use overlap::*;
+use physics::*;
 use poly::*;
+use snap::*;
 use target::*;
+use theme::*;
+use touch::*;
 use util::*;
 use view::*;
 
@@ -54,7 +76,11 @@ fn add_save(_app: &mut App) {
 
 impl Plugin for ShapeshifterLevelMakerPlugin {
     fn build(&self, mut app: &mut App) {
-        //
+        // the Globals start with the colors of the saved theme
+        let themes = Themes::load();
+        let mut globals = Globals::default();
+        globals.set_theme(themes.current());
+
         app.add_event::<StartMakingSegment>()
             .add_event::<Action>()
             .add_event::<TestCollisionEvent>()
@@ -62,13 +88,15 @@ impl Plugin for ShapeshifterLevelMakerPlugin {
             .add_event::<SpawnPoly>()
             .add_event::<SpawnTarget>()
             .add_event::<SpawnLevel>()
-            .add_event::<HasWonLevelEvent>()
-            .add_event::<PerformedCut>()
+            .add_event::<LevelWon>()
+            .add_event::<CutApplied>()
+            .add_event::<CutRejected>()
             .add_event::<TurnPolyIntoTarget>()
             .add_event::<SpawnTargetKeepTarget>()
             .add_event::<SpawnPolyKeepPoly>()
             .add_event::<PolyIsInsideTarget>()
             .add_event::<CheckPolyInsideTarget>()
+            .add_event::<SnapPoly>()
             //
             //
             //
@@ -85,32 +113,57 @@ impl Plugin for ShapeshifterLevelMakerPlugin {
             //
             //
             //
-            .insert_resource(Globals::default())
+            .insert_resource(globals)
+            .insert_resource(themes)
             .insert_resource(Cursor::default())
             .insert_resource(LoadedPolygonsRaw::default())
             .insert_resource(CurrentLevel::default())
+            .insert_resource(LevelRules::default())
+            .insert_resource(FlipCount::default())
+            .insert_resource(ExcessArea::default())
+            .insert_resource(Bindings::load())
+            .insert_resource(GamepadInput::default())
+            .insert_resource(KeyRepeat::default())
+            .insert_resource(TouchGestures::default())
             //
             .add_plugin(bevy_easings::EasingsPlugin)
             .add_plugin(FillMesh2dPlugin)
-            // .add_plugin(FeltPlugin)
             .add_plugin(TargetMesh2dPlugin)
+            .add_plugin(ObstacleMesh2dPlugin)
             .add_plugin(CutMesh2dPlugin)
+            .add_plugin(OutlineMesh2dPlugin)
+            .add_plugin(HatchMesh2dPlugin)
             //
             // .add_plugin(SavePlugin)
             // // // // // // .add_plugin(WorldInspectorPlugin::new())
             .add_plugin(CutPlugin)
             .add_plugin(PolyMakerPlugin)
             .add_plugin(TargetPlugin)
+            .add_plugin(OutlinePlugin)
+            .add_plugin(OverlapPlugin)
+            .add_plugin(CameraPlugin)
             .add_startup_system(load_all_polygons)
             .add_system(setup_mesh)
             .add_system(spawn_poly)
             .add_system(spawn_target)
+            .add_system(spawn_obstacles)
             .add_system(turn_poly_into_target)
+            .add_system(set_current_level)
+            .add_system(spawn_level_pieces)
+            .add_system(record_gamepad_events_system.exclusive_system().at_start())
             .add_system(record_mouse_events_system.exclusive_system().at_start())
+            .add_system(gamepad_cursor_system)
+            .add_system(gamepad_action)
+            .add_system(keyboard_action)
             .add_system(direct_action)
             .add_system(glow_poly)
             // .add_system(rotate_poly)
             .add_system(rotate_once)
+            .add_system(flip_poly)
+            .add_system(snap_poly)
+            .add_system(physics_step)
+            .add_system(toggle_momentum)
+            .add_system(toggle_snapping)
             .add_system(delete_poly)
             // .add_system(toggle_grid)
             .add_system(test_collisions)
@@ -119,6 +172,7 @@ impl Plugin for ShapeshifterLevelMakerPlugin {
             // .add_system(hover_path_point)
             .add_system(direct_release_action)
             .add_system(check_cut_timer)
+            .add_system(apply_theme)
             // delete me please
             // .add_system(debug_input)
             .add_system(transform_poly.exclusive_system().at_end());
@@ -151,6 +205,50 @@ pub fn debug_input(mut action_event_reader: EventReader<Action>) {
     }
 }
 
+//
+//
+// keeps the CurrentLevel and its rules in sync with the last spawned level
+pub fn set_current_level(
+    mut spawn_level_event_reader: EventReader<SpawnLevel>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut level_rules: ResMut<LevelRules>,
+    mut flip_count: ResMut<FlipCount>,
+) {
+    if let Some(level) = spawn_level_event_reader.iter().last() {
+        current_level.polygon = level.polygon.clone();
+        current_level.target = level.target.clone();
+        current_level.polygon_multiplier = 1.0;
+        current_level.target_multiplier = level.target_multiplier;
+        current_level.pieces = level.pieces.clone();
+
+        *level_rules = level.rules;
+        flip_count.flips = 0;
+    }
+}
+
+pub fn send_preset_pieces(
+    pieces: &Vec<PresetPiece>,
+    spawn_polykeep_event_writer: &mut EventWriter<SpawnPolyKeepPoly>,
+) {
+    for piece in pieces.iter() {
+        spawn_polykeep_event_writer.send(SpawnPolyKeepPoly {
+            polygon: piece.polygon.clone(),
+            polygon_multiplier: 1.0,
+            maybe_transform: Some(piece.transform),
+        });
+    }
+}
+
+// spawns the pieces of a tangram level, spawn_poly(..) removes the previous polygons
+pub fn spawn_level_pieces(
+    mut spawn_level_event_reader: EventReader<SpawnLevel>,
+    mut spawn_polykeep_event_writer: EventWriter<SpawnPolyKeepPoly>,
+) {
+    for level in spawn_level_event_reader.iter() {
+        send_preset_pieces(&level.pieces, &mut spawn_polykeep_event_writer);
+    }
+}
+
 pub fn revert_to_init(
     mut commands: Commands,
     query: Query<Entity, Or<(With<Polygon>, With<CutSegment>)>>,
@@ -158,15 +256,20 @@ pub fn revert_to_init(
     current_level: Res<CurrentLevel>,
     mut spawn_poly_event_writer: EventWriter<SpawnPoly>,
     mut spawn_target_event_writer: EventWriter<SpawnTarget>,
+    mut spawn_polykeep_event_writer: EventWriter<SpawnPolyKeepPoly>,
 ) {
     if let Some(Action::RevertToInit) = action_event_reader.iter().next() {
         for entity in query.iter() {
             commands.entity(entity).despawn_recursive();
         }
-        spawn_poly_event_writer.send(SpawnPoly {
-            polygon: current_level.polygon.clone(),
-            polygon_multiplier: current_level.polygon_multiplier,
-        });
+        if current_level.pieces.is_empty() {
+            spawn_poly_event_writer.send(SpawnPoly {
+                polygon: current_level.polygon.clone(),
+                polygon_multiplier: current_level.polygon_multiplier,
+            });
+        } else {
+            send_preset_pieces(&current_level.pieces, &mut spawn_polykeep_event_writer);
+        }
         spawn_target_event_writer.send(SpawnTarget {
             target: current_level.target.clone(),
             target_multiplier: current_level.target_multiplier,
@@ -203,25 +306,34 @@ pub fn test_collisions(
             &Transform,
             &mut MeshMeta,
             &Handle<FillMesh2dMaterial>,
+            Option<&Grouped>,
         ),
         With<Polygon>,
     >,
     mut fill_mesh_assets: ResMut<Assets<FillMesh2dMaterial>>,
     target_query: Query<(&Transform, &Target)>,
+    obstacle_query: Query<(&Transform, &Obstacle)>,
     mut collision_test_event: EventReader<TestCollisionEvent>,
     mut check_win_condition_event: EventWriter<TestWinEvent>,
     mut check_poly_inside_writer_event: EventWriter<CheckPolyInsideTarget>,
+    globals: Res<Globals>,
 ) {
+    let tolerance = globals.touch_tolerance();
+
     //
     //
     //
-    if let Some(TestCollisionEvent(entity)) = collision_test_event.iter().next() {
-        //
-        //
+    // a group sends one event per polygon, all polygons are tested once
+    let mut tested = false;
+    for TestCollisionEvent(entity) in collision_test_event.iter() {
         //
         //
         // check whether the polygon is inside the target
         check_poly_inside_writer_event.send(CheckPolyInsideTarget { entity: *entity });
+        tested = true;
+    }
+
+    if tested {
         //
         //
         //
@@ -229,8 +341,9 @@ pub fn test_collisions(
         //
         let mut colliding_entities: Vec<Entity> = Vec::new();
         let mut iter = query.iter_combinations_mut();
-        while let Some([(entity1, transform1, meta1, _), (entity2, transform2, meta2, _)]) =
-            iter.fetch_next()
+        while let Some(
+            [(entity1, transform1, meta1, _, grouped1), (entity2, transform2, meta2, _, grouped2)],
+        ) = iter.fetch_next()
         {
             //
             // do not test collision with self
@@ -238,10 +351,20 @@ pub fn test_collisions(
                 continue;
             }
 
+            //
+            // the polygons of a group moved rigidly, they overlap as much as when grabbed
+            if let (Some(grouped1), Some(_)) = (grouped1, grouped2) {
+                if grouped1.overlapping.contains(&entity2) {
+                    colliding_entities.push(entity2);
+                    colliding_entities.push(entity1);
+                }
+                continue;
+            }
+
             //
             //
             if meta1.bounding_box_collide(&meta2.path, &transform1, &transform2) {
-                if meta1.precise_intersect_test(&meta2.path, &transform1, &transform2) {
+                if meta1.precise_intersect_test(&meta2.path, &transform1, &transform2, tolerance) {
                     // do_go_back_to_previous_pos = true;
                     //
                     //
@@ -254,16 +377,34 @@ pub fn test_collisions(
         //
         //
         // test the target zone
-        for (entity1, transform1, meta1, _) in query.iter() {
+        for (entity1, transform1, meta1, _, _) in query.iter() {
             if let Some((transform, target)) = target_query.iter().next() {
-                if meta1.precise_intersect_test(&target.path, &transform1, &transform) {
+                if meta1.precise_intersect_test(&target.path, &transform1, &transform, tolerance) {
+                    colliding_entities.push(entity1);
+                }
+            }
+        }
+
+        //
+        //
+        // test the obstacles, which a polygon must not overlap at all
+        for (entity1, transform1, meta1, _, _) in query.iter() {
+            for (transform, obstacle) in obstacle_query.iter() {
+                if meta1.bounding_box_collide(&obstacle.path, &transform1, &transform)
+                    && meta1.precise_overlap_test(
+                        &obstacle.path,
+                        &transform1,
+                        &transform,
+                        tolerance,
+                    )
+                {
                     colliding_entities.push(entity1);
                 }
             }
         }
 
         // for entity in colliding_entities {
-        for (entity, _, mut meta, mat_handle) in query.iter_mut() {
+        for (entity, _, mut meta, mat_handle, _) in query.iter_mut() {
             let fill_mat = fill_mesh_assets.get_mut(mat_handle).unwrap();
             if colliding_entities.contains(&entity) {
                 meta.is_intersecting = true;
@@ -205,12 +205,8 @@ pub fn quick_load(
 
         let points = shift_to_center_of_mass(&loaded_mesh_params.points);
 
-        let mat_handle = fill_materials.add(FillMesh2dMaterial {
-            color: globals.polygon_color.into(),
-            show_com: 0.0,
-            selected: 0.0,
-            is_intersecting: 0.0,
-        });
+        let mat_handle = fill_materials
+            .add(FillMesh2dMaterial::new(globals.polygon_color, &globals).with_pattern(&globals));
 
         // let mut path: NoAttributes<BuilderImpl> = Path::builder();
 
@@ -267,12 +263,8 @@ pub fn quick_load(
             })
             .id();
 
-        let ghost_mat_handle = fill_materials.add(FillMesh2dMaterial {
-            color: globals.ghost_color.into(),
-            show_com: 0.0,
-            selected: 0.0,
-            is_intersecting: 0.0,
-        });
+        let ghost_mat_handle =
+            fill_materials.add(FillMesh2dMaterial::new(globals.ghost_color, &globals));
 
         let mut ghost_transform = transform;
         ghost_transform.translation.z = -10.0;
@@ -391,12 +383,8 @@ pub fn quick_load_all_mesh(
                 globals.cut_polygon
             };
 
-            let mat_handle = fill_materials.add(FillMesh2dMaterial {
-                color: poly_color.into(),
-                show_com: 0.0,
-                selected: 0.0,
-                is_intersecting: 0.0,
-            });
+            let mat_handle = fill_materials
+                .add(FillMesh2dMaterial::new(poly_color, &globals).with_pattern(&globals));
 
             //
             //
@@ -457,12 +445,8 @@ pub fn quick_load_all_mesh(
                 })
                 .id();
 
-            let ghost_mat_handle = fill_materials.add(FillMesh2dMaterial {
-                color: globals.ghost_color.into(),
-                show_com: 0.0,
-                selected: 0.0,
-                is_intersecting: 0.0,
-            });
+            let ghost_mat_handle =
+                fill_materials.add(FillMesh2dMaterial::new(globals.ghost_color, &globals));
 
             let mut ghost_transform = transform;
             ghost_transform.translation.z = -10.0;
@@ -513,12 +497,8 @@ pub fn load_mesh(
 
             let poly_color = globals.polygon_color;
 
-            let mat_handle = fill_materials.add(FillMesh2dMaterial {
-                color: poly_color.into(),
-                show_com: 0.0,
-                selected: 0.0,
-                is_intersecting: 0.0,
-            });
+            let mat_handle = fill_materials
+                .add(FillMesh2dMaterial::new(poly_color, &globals).with_pattern(&globals));
 
             //
             //
@@ -581,12 +561,8 @@ pub fn load_mesh(
                 })
                 .id();
 
-            let ghost_mat_handle = fill_materials.add(FillMesh2dMaterial {
-                color: globals.ghost_color.into(),
-                show_com: 0.0,
-                selected: 0.0,
-                is_intersecting: 0.0,
-            });
+            let ghost_mat_handle =
+                fill_materials.add(FillMesh2dMaterial::new(globals.ghost_color, &globals));
 
             let mut ghost_transform = transform;
             ghost_transform.translation.z = -10.0;
@@ -1,4 +1,5 @@
 use crate::util::*;
+use crate::validate::validate_and_repair_polygon;
 use bevy::prelude::*;
 use std::collections::HashMap;
 
@@ -344,5 +345,35 @@ pub fn load_all_polygons(mut loaded_polygons: ResMut<LoadedPolygonsRaw>) {
         serde_json::from_str(&include_str!("polygons/crab1.pts")).unwrap(),
     );
 
+    // tangram pieces, see SpawnLevel::tangram(..)
+    polygon_map.insert(
+        "tangram_large_triangle".to_string(),
+        serde_json::from_str(&include_str!("polygons/tangram_large_triangle.pts")).unwrap(),
+    );
+    polygon_map.insert(
+        "tangram_medium_triangle".to_string(),
+        serde_json::from_str(&include_str!("polygons/tangram_medium_triangle.pts")).unwrap(),
+    );
+    polygon_map.insert(
+        "tangram_small_triangle".to_string(),
+        serde_json::from_str(&include_str!("polygons/tangram_small_triangle.pts")).unwrap(),
+    );
+    polygon_map.insert(
+        "tangram_square".to_string(),
+        serde_json::from_str(&include_str!("polygons/tangram_square.pts")).unwrap(),
+    );
+    polygon_map.insert(
+        "tangram_parallelogram".to_string(),
+        serde_json::from_str(&include_str!("polygons/tangram_parallelogram.pts")).unwrap(),
+    );
+    polygon_map.insert(
+        "tangram_full_square".to_string(),
+        serde_json::from_str(&include_str!("polygons/tangram_full_square.pts")).unwrap(),
+    );
+
+    for (name, save_mesh_meta) in polygon_map.iter_mut() {
+        validate_and_repair_polygon(name, save_mesh_meta);
+    }
+
     loaded_polygons.polygons = polygon_map;
 }
@@ -5,36 +5,47 @@ use bevy::{
     sprite::{Material2d, Material2dPlugin},
 };
 
-// #[derive(AsBindGroup, TypeUuid, Debug, Clone, Component, Default)]
-// #[uuid = "f690fdae-d598-1133-5566-97e2a3f95a9a"]
-// pub struct FeltMaterial {
-//     #[uniform(0)]
-//     pub color: Vec4,
-//     #[uniform(0)]
-//     pub show_com: f32,
-//     #[uniform(0)]
-//     pub selected: f32,
-//     #[uniform(0)]
-//     pub is_intersecting: f32,
-//     //
-//     #[texture(1)]
-//     #[sampler(2)]
-//     pub felt: Handle<Image>,
-// }
-
-// pub struct FeltPlugin;
-
-// impl Plugin for FeltPlugin {
-//     fn build(&self, app: &mut App) {
-//         app.add_plugin(Material2dPlugin::<FeltMaterial>::default());
-//     }
-// }
-
-// impl Material2d for FeltMaterial {
-//     fn fragment_shader() -> ShaderRef {
-//         "shaders/felt.wgsl".into()
-//     }
-// }
+use crate::util::Globals;
+
+use serde::{Deserialize, Serialize};
+
+//
+//
+// The pieces are filled with a flat color or with a pattern drawn over their color. The
+// pattern is sampled with the uvs of the mesh, which are the coordinates of the piece
+// before it was cut, see PatternFrame.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PiecePattern {
+    Flat,
+    Felt,
+    Paper,
+    Wood,
+    // the texture of the theme, tiled every PATTERN_TILE_SIZE
+    Texture,
+}
+
+impl Default for PiecePattern {
+    fn default() -> Self {
+        PiecePattern::Flat
+    }
+}
+
+impl PiecePattern {
+    // as read by polygon.wgsl
+    pub fn code(&self) -> f32 {
+        match self {
+            PiecePattern::Flat => 0.0,
+            PiecePattern::Felt => 1.0,
+            PiecePattern::Paper => 2.0,
+            PiecePattern::Wood => 3.0,
+            PiecePattern::Texture => 4.0,
+        }
+    }
+}
+
+// size of a tile of the texture pattern, in pixels
+pub const PATTERN_TILE_SIZE: f32 = 256.0;
 
 #[derive(AsBindGroup, TypeUuid, Debug, Clone, Component, Default)]
 #[uuid = "f690fdae-d598-45ab-1684-97e2a3f95a9a"]
@@ -47,6 +58,49 @@ pub struct FillMesh2dMaterial {
     pub selected: f32,
     #[uniform(0)]
     pub is_intersecting: f32,
+    #[uniform(0)]
+    pub intersecting_color: Vec4,
+    #[uniform(0)]
+    pub selected_color: Vec4,
+    #[uniform(0)]
+    pub pattern: f32,
+    #[uniform(0)]
+    pub pattern_tile_size: f32,
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Option<Handle<Image>>,
+}
+
+impl FillMesh2dMaterial {
+    // the highlights of the polygon follow the theme, see apply_theme(..)
+    pub fn new(color: Color, globals: &Globals) -> Self {
+        Self {
+            color: color.into(),
+            show_com: 0.0,
+            selected: 0.0,
+            is_intersecting: 0.0,
+            intersecting_color: globals.intersecting_color.into(),
+            selected_color: globals.selected_color.into(),
+            pattern: PiecePattern::Flat.code(),
+            pattern_tile_size: PATTERN_TILE_SIZE,
+            texture: None,
+        }
+    }
+
+    // the pieces that can be moved by the player are drawn with the pattern of the theme
+    pub fn with_pattern(mut self, globals: &Globals) -> Self {
+        self.set_pattern(globals.piece_pattern, globals.piece_texture.clone());
+        self
+    }
+
+    pub fn set_pattern(&mut self, pattern: PiecePattern, texture: Option<Handle<Image>>) {
+        // without a texture, the pieces stay flat
+        self.pattern = match (pattern, &texture) {
+            (PiecePattern::Texture, None) => PiecePattern::Flat.code(),
+            _ => pattern.code(),
+        };
+        self.texture = texture;
+    }
 }
 
 pub struct FillMesh2dPlugin;
@@ -63,18 +117,51 @@ impl Material2d for FillMesh2dMaterial {
     }
 }
 
+//
+//
+// The cut lines are dashed, and the dashes run from the start to the end of the line.
+// The mesh is a unit quad scaled to the length and thickness of the line, see
+// cut_segment_transform(..).
 #[derive(AsBindGroup, TypeUuid, Debug, Clone, Component, Default)]
 #[uuid = "f690fdae-d512-45ab-1663-9678a3f95a9a"]
 pub struct CutMesh2dMaterial {
     #[uniform(0)]
     pub color: Vec4,
+    // used instead of `color` while the cut would be rejected
+    #[uniform(0)]
+    pub invalid_color: Vec4,
+    #[uniform(0)]
+    pub length: f32,
+    #[uniform(0)]
+    pub thickness: f32,
+    #[uniform(0)]
+    pub dash_length: f32,
+    // seconds, moves the dashes
+    #[uniform(0)]
+    pub time: f32,
+    #[uniform(0)]
+    pub invalid: f32,
+}
+
+impl CutMesh2dMaterial {
+    pub fn new(globals: &Globals) -> Self {
+        Self {
+            color: globals.cutting_segment_color.into(),
+            invalid_color: globals.invalid_cut_color.into(),
+            length: 0.0,
+            thickness: globals.cutting_segment_thickness,
+            dash_length: globals.cut_dash_length,
+            time: 0.0,
+            invalid: 0.0,
+        }
+    }
 }
 
 pub struct CutMesh2dPlugin;
 
 impl Plugin for CutMesh2dPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(Material2dPlugin::<FillMesh2dMaterial>::default());
+        app.add_plugin(Material2dPlugin::<CutMesh2dMaterial>::default());
     }
 }
 
@@ -84,6 +171,120 @@ impl Material2d for CutMesh2dMaterial {
     }
 }
 
+//
+//
+// The outline of a polygon or a target, see make_outline_stroke_mesh(..). The stroke is
+// tessellated `geometry_width` wide and drawn `width` wide, so that the width can change
+// without tessellating again.
+#[derive(AsBindGroup, TypeUuid, Debug, Clone, Component, Default)]
+#[uuid = "f690fdae-d512-4c21-8a3e-9678a3f95c27"]
+pub struct OutlineMesh2dMaterial {
+    #[uniform(0)]
+    pub color: Vec4,
+    #[uniform(0)]
+    pub width: f32,
+    #[uniform(0)]
+    pub geometry_width: f32,
+    #[uniform(0)]
+    pub dashed: f32,
+    #[uniform(0)]
+    pub dash_length: f32,
+    // seconds, moves the dashes
+    #[uniform(0)]
+    pub time: f32,
+}
+
+impl OutlineMesh2dMaterial {
+    pub fn new(globals: &Globals, geometry_width: f32) -> Self {
+        Self {
+            color: globals.outline_color.into(),
+            width: globals.outline_width,
+            geometry_width,
+            dashed: 0.0,
+            dash_length: globals.cut_dash_length * 0.5,
+            time: 0.0,
+        }
+    }
+}
+
+pub struct OutlineMesh2dPlugin;
+
+impl Plugin for OutlineMesh2dPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(Material2dPlugin::<OutlineMesh2dMaterial>::default());
+    }
+}
+
+impl Material2d for OutlineMesh2dMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/outline.wgsl".into()
+    }
+}
+
+//
+//
+// Diagonal hatches over the parts of the pieces that are outside the target or over other
+// pieces, see overlap.rs. The hatches are in world coordinates, `direction` is 1 or -1
+// so that the two kinds of overlays cross each other.
+#[derive(AsBindGroup, TypeUuid, Debug, Clone, Component, Default)]
+#[uuid = "f690fdae-d512-4c21-8a3e-9678a3f95d36"]
+pub struct HatchMesh2dMaterial {
+    #[uniform(0)]
+    pub color: Vec4,
+    #[uniform(0)]
+    pub spacing: f32,
+    #[uniform(0)]
+    pub direction: f32,
+}
+
+impl HatchMesh2dMaterial {
+    pub fn new(color: Color, direction: f32, globals: &Globals) -> Self {
+        Self {
+            color: color.into(),
+            spacing: globals.hatch_spacing,
+            direction,
+        }
+    }
+}
+
+pub struct HatchMesh2dPlugin;
+
+impl Plugin for HatchMesh2dPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(Material2dPlugin::<HatchMesh2dMaterial>::default());
+    }
+}
+
+impl Material2d for HatchMesh2dMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/hatch.wgsl".into()
+    }
+}
+
+//
+//
+//
+#[derive(AsBindGroup, TypeUuid, Debug, Clone, Component, Default)]
+#[uuid = "f690fdae-d512-4c21-8a3e-9678a3f95b14"]
+pub struct ObstacleMesh2dMaterial {
+    #[uniform(0)]
+    pub color: Vec4,
+}
+
+pub struct ObstacleMesh2dPlugin;
+
+impl Plugin for ObstacleMesh2dPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(Material2dPlugin::<ObstacleMesh2dMaterial>::default());
+    }
+}
+
+impl Material2d for ObstacleMesh2dMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/obstacle.wgsl".into()
+    }
+}
+
 //
 //
 //
@@ -0,0 +1,181 @@
+use crate::material::*;
+use crate::util::*;
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use lyon::tessellation::path::Path;
+use lyon::tessellation::{
+    BuffersBuilder, LineJoin, Side, StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers,
+};
+
+// above the fill of the polygon, below the polygons that are above it
+const OUTLINE_Z: f32 = 0.001;
+
+// the intersecting polygons get a thicker outline
+const INTERSECTING_WIDTH_MULTIPLIER: f32 = 1.5;
+
+pub struct OutlinePlugin;
+
+impl Plugin for OutlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_outlines).add_system(style_outlines);
+    }
+}
+
+// the outline of a Polygon or a Target, spawned as its child to follow its Transform
+#[derive(Component)]
+pub struct Outline;
+
+//
+//
+// Tessellates the stroke of the path. The uvs are the distance along the path and the
+// side of the stroke (-1 or 1), for the dashes and the anti-aliasing in outline.wgsl.
+pub fn make_outline_stroke_mesh(path: &Path, width: f32) -> Mesh {
+    let mut buffers: VertexBuffers<([f32; 3], [f32; 2]), u32> = VertexBuffers::new();
+
+    let mut tessellator = StrokeTessellator::new();
+    let result = tessellator.tessellate_path(
+        path,
+        &StrokeOptions::default()
+            .with_line_width(width)
+            .with_line_join(LineJoin::Round),
+        &mut BuffersBuilder::new(&mut buffers, |vertex: StrokeVertex| {
+            let position = vertex.position();
+            let side = match vertex.side() {
+                Side::Negative => -1.0,
+                Side::Positive => 1.0,
+            };
+            ([position.x, position.y, 0.0], [vertex.advancement(), side])
+        }),
+    );
+    if let Err(err) = result {
+        warn!("could not tessellate an outline: {:?}", err);
+    }
+
+    let positions: Vec<[f32; 3]> = buffers.vertices.iter().map(|v| v.0).collect();
+    let uvs: Vec<[f32; 2]> = buffers.vertices.iter().map(|v| v.1).collect();
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(buffers.indices)));
+    mesh
+}
+
+//
+//
+// (Re)makes the outline when a polygon gets a new mesh, e.g. when it is spawned, cut or
+// flipped, and when a polygon is turned into a target
+pub fn spawn_outlines(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut outline_materials: ResMut<Assets<OutlineMesh2dMaterial>>,
+    globals: Res<Globals>,
+    query: Query<
+        (
+            Entity,
+            Option<&MeshMeta>,
+            Option<&Target>,
+            Option<&Children>,
+        ),
+        (
+            Or<(With<Polygon>, With<Target>)>,
+            Or<(Changed<Mesh2dHandle>, Added<Target>)>,
+        ),
+    >,
+    outline_query: Query<Entity, With<Outline>>,
+) {
+    for (entity, mesh_meta, target, children) in query.iter() {
+        let path = match (target, mesh_meta) {
+            (Some(target), _) => &target.path,
+            (None, Some(mesh_meta)) => &mesh_meta.path,
+            _ => continue,
+        };
+
+        if let Some(children) = children {
+            for child in children.iter() {
+                if outline_query.get(*child).is_ok() {
+                    commands.entity(*child).despawn_recursive();
+                }
+            }
+        }
+
+        // room for the thicker outline of the intersecting polygons
+        let geometry_width = globals.outline_width * INTERSECTING_WIDTH_MULTIPLIER + 2.0;
+        let mesh = make_outline_stroke_mesh(path, geometry_width);
+
+        let outline = commands
+            .spawn_bundle(MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(mesh)),
+                material: outline_materials
+                    .add(OutlineMesh2dMaterial::new(&globals, geometry_width)),
+                transform: Transform::from_translation(Vec3::new(0.0, 0.0, OUTLINE_Z)),
+                ..Default::default()
+            })
+            .insert(Outline)
+            .id();
+        commands.entity(entity).add_child(outline);
+    }
+}
+
+//
+//
+// Outlines are plain for the targets and the polygons at rest, dashed for the selected
+// polygons and thicker for the intersecting ones
+pub fn style_outlines(
+    time: Res<Time>,
+    globals: Res<Globals>,
+    mut outline_materials: ResMut<Assets<OutlineMesh2dMaterial>>,
+    outline_query: Query<(&Parent, &Handle<OutlineMesh2dMaterial>), With<Outline>>,
+    parent_query: Query<(Option<&MeshMeta>, Option<&Selected>, Option<&Target>)>,
+) {
+    for (parent, material_handle) in outline_query.iter() {
+        let (mesh_meta, selected, target) = if let Ok(parent) = parent_query.get(parent.get()) {
+            parent
+        } else {
+            continue;
+        };
+
+        let is_polygon = target.is_none();
+        let intersecting = is_polygon && mesh_meta.map_or(false, |m| m.is_intersecting);
+        let selected = is_polygon && selected.is_some();
+
+        let (color, width) = if intersecting {
+            (
+                globals.intersecting_outline_color,
+                globals.outline_width * INTERSECTING_WIDTH_MULTIPLIER,
+            )
+        } else if selected {
+            (globals.selected_outline_color, globals.outline_width)
+        } else {
+            (globals.outline_color, globals.outline_width)
+        };
+        let color: Vec4 = color.into();
+        let dashed = selected as i32 as f32;
+
+        // the material is only touched when needed, which would upload it again
+        let needs_update = match outline_materials.get(material_handle) {
+            Some(material) => {
+                material.color != color
+                    || material.width != width
+                    || material.dashed != dashed
+                    || dashed > 0.5
+            }
+            None => false,
+        };
+        if needs_update {
+            if let Some(material) = outline_materials.get_mut(material_handle) {
+                material.color = color;
+                material.width = width;
+                material.dashed = dashed;
+                material.time = time.seconds_since_startup() as f32;
+            }
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use crate::material::*;
+use crate::util::*;
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use geo::{Area, BooleanOps, EuclideanLength, LineString, MultiPolygon};
+use lyon::tessellation::math::point;
+use lyon::tessellation::path::{Path, PathEvent};
+use lyon::tessellation::{BuffersBuilder, FillOptions, FillTessellator, FillVertex, VertexBuffers};
+
+// above the polygons, whose z is between 1 and 2, below the selection box
+const OVERLAY_Z: f32 = 50.0;
+
+// the boolean operations leave slivers along the edges that touch, even when the
+// touch tolerance is zero
+const MIN_OVERLAY_WIDTH: f64 = 0.5;
+
+pub struct OverlapPlugin;
+
+impl Plugin for OverlapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(update_overlays)
+            .add_system(hide_overlays_while_moving);
+    }
+}
+
+// hatched area over the pieces, in world coordinates
+#[derive(Component)]
+pub enum Overlay {
+    // parts of the pieces outside the target
+    Excess,
+    // parts of the pieces over other pieces
+    Overlap,
+}
+
+// The first contour of the path, in the coordinates of the path. Degenerate contours
+// (repeated points, no area, NaN) are skipped, as the boolean operations may panic on them
+fn path_to_polygon(path: &Path) -> Option<geo::Polygon<f64>> {
+    let mut coords: Vec<(f64, f64)> = Vec::new();
+    for event in path.iter() {
+        match event {
+            PathEvent::Begin { at } => coords.push((at.x as f64, at.y as f64)),
+            PathEvent::Line { to, .. } => coords.push((to.x as f64, to.y as f64)),
+            PathEvent::End { .. } => break,
+            _ => {}
+        }
+    }
+    coords.dedup();
+
+    if coords.len() < 3 || coords.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+        return None;
+    }
+    let polygon = geo::Polygon::new(LineString::from(coords), vec![]);
+    if polygon.unsigned_area() < MIN_OVERLAY_WIDTH * MIN_OVERLAY_WIDTH {
+        return None;
+    }
+    Some(polygon)
+}
+
+// The parts of a boolean operation, or none if it panicked. On wasm, where panics abort,
+// only the checks of path_to_polygon(..) protect the game
+fn boolean_op_parts(op: impl FnOnce() -> MultiPolygon<f64>) -> Vec<geo::Polygon<f64>> {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(op)) {
+        Ok(MultiPolygon(parts)) => parts,
+        Err(_) => {
+            warn!("a boolean operation failed, its overlay is skipped");
+            Vec::new()
+        }
+    }
+}
+
+// thinner than the tolerance on average, e.g. two edges that touch
+fn is_sliver(polygon: &geo::Polygon<f64>, tolerance: f64) -> bool {
+    let width = tolerance.max(MIN_OVERLAY_WIDTH);
+    polygon.unsigned_area() <= polygon.exterior().euclidean_length() * width * 0.5
+}
+
+fn ring_to_path(builder: &mut lyon::path::path::Builder, ring: &LineString<f64>) {
+    for (k, coord) in ring.coords().enumerate() {
+        let p = point(coord.x as f32, coord.y as f32);
+        if k == 0 {
+            builder.begin(p);
+        } else {
+            builder.line_to(p);
+        }
+    }
+    builder.close();
+}
+
+//
+//
+// One mesh for all the polygons. They are tessellated one by one, so that the polygons
+// that overlap each other are not cancelled out by the even-odd fill of their holes.
+pub fn make_overlay_mesh(polygons: &[geo::Polygon<f64>]) -> Mesh {
+    let mut buffers: VertexBuffers<[f32; 3], u32> = VertexBuffers::new();
+
+    let mut tessellator = FillTessellator::new();
+    for polygon in polygons.iter() {
+        let mut builder = Path::builder();
+        ring_to_path(&mut builder, polygon.exterior());
+        for interior in polygon.interiors() {
+            ring_to_path(&mut builder, interior);
+        }
+        let path = builder.build();
+
+        let result = tessellator.tessellate_path(
+            &path,
+            &FillOptions::default(),
+            &mut BuffersBuilder::new(&mut buffers, |vertex: FillVertex| {
+                let position = vertex.position();
+                [position.x, position.y, 0.0]
+            }),
+        );
+        if let Err(err) = result {
+            warn!("could not tessellate an overlay: {:?}", err);
+        }
+    }
+
+    let normals = vec![[0.0, 0.0, 1.0]; buffers.vertices.len()];
+    let uvs = vec![[0.0, 0.0]; buffers.vertices.len()];
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, buffers.vertices);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(buffers.indices)));
+    mesh
+}
+
+fn spawn_overlay(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    hatch_materials: &mut Assets<HatchMesh2dMaterial>,
+    globals: &Globals,
+    polygons: &[geo::Polygon<f64>],
+    overlay: Overlay,
+) {
+    if polygons.is_empty() {
+        return;
+    }
+
+    // the two kinds of hatches cross each other where they meet
+    let material = match overlay {
+        Overlay::Excess => HatchMesh2dMaterial::new(globals.excess_color, 1.0, globals),
+        Overlay::Overlap => HatchMesh2dMaterial::new(globals.overlap_color, -1.0, globals),
+    };
+
+    commands
+        .spawn_bundle(MaterialMesh2dBundle {
+            mesh: Mesh2dHandle(meshes.add(make_overlay_mesh(polygons))),
+            material: hatch_materials.add(material),
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, OVERLAY_Z)),
+            ..Default::default()
+        })
+        .insert(overlay);
+}
+
+//
+//
+// Once the polygons are at rest, the parts of the pieces outside the target and the
+// parts where pieces overlap are computed with boolean operations and hatched.
+pub fn update_overlays(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut hatch_materials: ResMut<Assets<HatchMesh2dMaterial>>,
+    mut excess_area: ResMut<ExcessArea>,
+    mut collision_test_event: EventReader<TestCollisionEvent>,
+    removed_polygons: RemovedComponents<Polygon>,
+    added_targets: Query<(), Added<Target>>,
+    polygon_query: Query<(&Transform, &MeshMeta), With<Polygon>>,
+    target_query: Query<(&Transform, &Target)>,
+    overlay_query: Query<Entity, With<Overlay>>,
+    globals: Res<Globals>,
+) {
+    let tested = collision_test_event.iter().count() > 0;
+    if !tested && removed_polygons.iter().next().is_none() && added_targets.is_empty() {
+        return;
+    }
+
+    for entity in overlay_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let tolerance = globals.touch_tolerance() as f64;
+
+    let pieces: Vec<geo::Polygon<f64>> = polygon_query
+        .iter()
+        .filter_map(|(transform, meta)| path_to_polygon(&transform_path(&meta.path, transform).0))
+        .collect();
+
+    //
+    //
+    // the pieces minus the target
+    let mut excess: Vec<geo::Polygon<f64>> = Vec::new();
+    if let Some((transform, target)) = target_query.iter().next() {
+        if let Some(target) = path_to_polygon(&transform_path(&target.path, transform).0) {
+            for piece in pieces.iter() {
+                let parts = boolean_op_parts(|| piece.difference(&target));
+                excess.extend(parts.into_iter().filter(|p| !is_sliver(p, tolerance)));
+            }
+        }
+    }
+
+    //
+    //
+    // the pieces intersected two by two
+    let mut overlaps: Vec<geo::Polygon<f64>> = Vec::new();
+    for (k, piece1) in pieces.iter().enumerate() {
+        for piece2 in pieces.iter().skip(k + 1) {
+            let parts = boolean_op_parts(|| piece1.intersection(piece2));
+            overlaps.extend(parts.into_iter().filter(|p| !is_sliver(p, tolerance)));
+        }
+    }
+
+    let area = excess.iter().map(|p| p.unsigned_area()).sum::<f64>() as f32;
+    if excess_area.area != area {
+        excess_area.area = area;
+    }
+
+    spawn_overlay(
+        &mut commands,
+        &mut meshes,
+        &mut hatch_materials,
+        &globals,
+        &excess,
+        Overlay::Excess,
+    );
+    spawn_overlay(
+        &mut commands,
+        &mut meshes,
+        &mut hatch_materials,
+        &globals,
+        &overlaps,
+        Overlay::Overlap,
+    );
+}
+
+// the overlays are in world coordinates and do not follow the polygons being moved
+pub fn hide_overlays_while_moving(
+    moving_query: Query<(), Or<(With<Translating>, With<Rotating>, With<ForceMotion>)>>,
+    mut overlay_query: Query<&mut Visibility, With<Overlay>>,
+) {
+    let is_visible = moving_query.is_empty();
+    for mut visibility in overlay_query.iter_mut() {
+        if visibility.is_visible != is_visible {
+            visibility.is_visible = is_visible;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(points: &[(f32, f32)]) -> Path {
+        let points: Vec<Vec2> = points.iter().map(|(x, y)| Vec2::new(*x, *y)).collect();
+        build_path_from_points(&points, 1.0)
+    }
+
+    #[test]
+    fn degenerate_contours_are_skipped() {
+        assert!(path_to_polygon(&path(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)])).is_some());
+        // repeated points
+        assert!(path_to_polygon(&path(&[(0.0, 0.0), (10.0, 0.0), (10.0, 0.0)])).is_none());
+        // no area
+        assert!(path_to_polygon(&path(&[(0.0, 0.0), (5.0, 0.0), (10.0, 0.0)])).is_none());
+    }
+
+    #[test]
+    fn overlap_of_two_squares() {
+        let square1 = path_to_polygon(&path(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]));
+        let square2 = path_to_polygon(&path(&[(5.0, 5.0), (15.0, 5.0), (15.0, 15.0), (5.0, 15.0)]));
+        let (square1, square2) = (square1.unwrap(), square2.unwrap());
+
+        let parts = boolean_op_parts(|| square1.intersection(&square2));
+        let area: f64 = parts.iter().map(|p| p.unsigned_area()).sum();
+        assert!((area - 25.0).abs() < 1e-6, "{}", area);
+    }
+
+    #[test]
+    fn panicking_boolean_op_gives_no_parts() {
+        assert!(boolean_op_parts(|| panic!("degenerate")).is_empty());
+    }
+}
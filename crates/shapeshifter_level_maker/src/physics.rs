@@ -0,0 +1,267 @@
+use bevy::prelude::*;
+
+use crate::input::Action;
+use crate::snap::SnapPoly;
+use crate::util::*;
+
+// initial speed of the pieces of a cut, away from the cut line
+pub const CUT_PUSH_SPEED: f32 = 120.0;
+
+// acceleration of a cut piece away from a polygon it overlaps
+pub const OVERLAP_PUSH: f32 = 2000.0;
+
+// a thrown polygon never goes faster than this, in world units per second
+pub const MAX_THROW_SPEED: f32 = 1200.0;
+
+// weight of the last frame in the drag velocity
+pub const DRAG_VELOCITY_SMOOTHING: f32 = 0.5;
+
+// acceleration of a cut piece away from the polygons it overlaps
+fn overlap_push<'a>(
+    mesh_meta: &MeshMeta,
+    transform: &Transform,
+    others: impl Iterator<Item = (&'a Transform, &'a MeshMeta)>,
+    tolerance: f32,
+) -> Vec2 {
+    let mut push = Vec2::ZERO;
+    for (other_transform, other) in others {
+        if mesh_meta.bounding_box_collide(&other.path, transform, other_transform)
+            && mesh_meta.precise_intersect_test(&other.path, transform, other_transform, tolerance)
+        {
+            let away = (transform.translation - other_transform.translation)
+                .truncate()
+                .normalize_or_zero();
+            push += away * OVERLAP_PUSH;
+        }
+    }
+    push
+}
+
+fn collides(
+    mesh_meta: &MeshMeta,
+    transform: &Transform,
+    still_query: &Query<(&Transform, &MeshMeta), (With<Polygon>, Without<ForceMotion>)>,
+    obstacle_query: &Query<(&Transform, &Obstacle), Without<Polygon>>,
+    tolerance: f32,
+) -> bool {
+    still_query.iter().any(|(other_transform, other)| {
+        mesh_meta.bounding_box_collide(&other.path, transform, other_transform)
+            && mesh_meta.precise_intersect_test(&other.path, transform, other_transform, tolerance)
+    }) || obstacle_query.iter().any(|(other_transform, obstacle)| {
+        mesh_meta.bounding_box_collide(&obstacle.path, transform, other_transform)
+            && mesh_meta.precise_overlap_test(&obstacle.path, transform, other_transform, tolerance)
+    })
+}
+
+//
+//
+// Moves the polygons that have a ForceMotion: thrown polygons (see transform_poly(..))
+// and the pieces of a cut. They slow down with Globals::friction.
+//
+// A thrown polygon stops at its last free position when it would hit another polygon or
+// an obstacle. The pieces of a cut are instead pushed away from the polygons they overlap,
+// including the other pieces that are still moving.
+pub fn physics_step(
+    mut commands: Commands,
+    mut moving_query: Query<
+        (Entity, &mut Transform, &mut ForceMotion, &mut MeshMeta),
+        With<Polygon>,
+    >,
+    still_query: Query<(&Transform, &MeshMeta), (With<Polygon>, Without<ForceMotion>)>,
+    obstacle_query: Query<(&Transform, &Obstacle), Without<Polygon>>,
+    globals: Res<Globals>,
+    time: Res<Time>,
+    mut snap_writer: EventWriter<SnapPoly>,
+    mut collision_test_writer: EventWriter<TestCollisionEvent>,
+) {
+    let dt = time.delta_seconds();
+
+    //
+    //
+    // overlapping pieces after a cut push each other apart. The pushes are computed from
+    // the positions before the step, so that both pieces of a pair are pushed
+    let pushes: Vec<(Entity, Vec2)> = moving_query
+        .iter()
+        .filter(|(_, _, motion, _)| !motion.thrown)
+        .map(|(entity, transform, _, mesh_meta)| {
+            let moving_others = moving_query
+                .iter()
+                .filter(|(other_entity, _, _, _)| *other_entity != entity)
+                .map(|(_, other_transform, _, other)| (other_transform, other));
+            let push = overlap_push(
+                mesh_meta,
+                transform,
+                still_query.iter().chain(moving_others),
+                globals.collision_tolerance,
+            );
+            (entity, push)
+        })
+        .collect();
+
+    for (entity, mut transform, mut motion, mut mesh_meta) in moving_query.iter_mut() {
+        if let Some((_, push)) = pushes.iter().find(|(pushed, _)| *pushed == entity) {
+            motion.velocity += *push * dt;
+        }
+
+        //
+        // compute new velocity
+        let speed = motion.velocity.length();
+        let mut stop = speed < globals.min_velocity;
+        if !stop {
+            let deceleration = (globals.friction * dt).min(speed);
+            motion.velocity = motion.velocity - motion.velocity / speed * deceleration;
+        }
+
+        //
+        // compute new position
+        let mut new_transform = transform.clone();
+        let new_position = motion.position + motion.velocity * dt;
+        new_transform.translation = new_position.extend(transform.translation.z);
+
+        if !stop
+            && motion.thrown
+            && collides(
+                &mesh_meta,
+                &new_transform,
+                &still_query,
+                &obstacle_query,
+                globals.collision_tolerance,
+            )
+        {
+            stop = true;
+        } else {
+            motion.position = new_position;
+            *transform = new_transform;
+            mesh_meta.previous_transform = transform.clone();
+        }
+
+        if stop || motion.velocity.length() < globals.min_velocity {
+            commands.entity(entity).remove::<ForceMotion>();
+            if motion.thrown && globals.magnetic_snapping {
+                snap_writer.send(SnapPoly(entity));
+            } else {
+                collision_test_writer.send(TestCollisionEvent(entity));
+            }
+        }
+    }
+}
+
+pub fn toggle_momentum(mut action_event_reader: EventReader<Action>, mut globals: ResMut<Globals>) {
+    if let Some(Action::ToggleMomentum) = action_event_reader.iter().next() {
+        globals.momentum = !globals.momentum;
+        info!("momentum: {}", globals.momentum);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::{Duration, Instant};
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_event::<SnapPoly>()
+            .add_event::<TestCollisionEvent>()
+            .insert_resource(Time::default())
+            .insert_resource(Globals::default())
+            .add_system(physics_step);
+        app
+    }
+
+    // a 100x100 square, pushed like the pieces of a cut when `moving`
+    fn spawn_square(app: &mut App, position: Vec2, moving: bool) -> Entity {
+        let points = vec![
+            Vec2::new(-50.0, -50.0),
+            Vec2::new(50.0, -50.0),
+            Vec2::new(50.0, 50.0),
+            Vec2::new(-50.0, 50.0),
+        ];
+        let transform = Transform::from_translation(position.extend(0.0));
+        let mut square = app.world.spawn();
+        square
+            .insert(transform)
+            .insert(Polygon { in_target: false })
+            .insert(MeshMeta {
+                id: 0,
+                path: build_path_from_points(&points, 1.0),
+                points,
+                previous_transform: transform,
+                is_intersecting: false,
+                name: "".to_string(),
+            });
+        if moving {
+            square.insert(ForceMotion {
+                force: Vec2::ZERO,
+                area: 10000.0,
+                velocity: Vec2::ZERO,
+                position,
+                thrown: false,
+            });
+        }
+        square.id()
+    }
+
+    fn run(app: &mut App, frames: u32) {
+        let start = Instant::now();
+        app.world.resource_mut::<Time>().update_with_instant(start);
+        for frame in 1..=frames {
+            let now = start + Duration::from_secs_f32(frame as f32 / 60.0);
+            app.world.resource_mut::<Time>().update_with_instant(now);
+            app.update();
+        }
+    }
+
+    fn position(app: &App, entity: Entity) -> Vec2 {
+        app.world
+            .get::<Transform>(entity)
+            .unwrap()
+            .translation
+            .truncate()
+    }
+
+    fn overlapping(app: &App, a: Entity, b: Entity) -> bool {
+        let (a_meta, b_meta) = (
+            app.world.get::<MeshMeta>(a).unwrap(),
+            app.world.get::<MeshMeta>(b).unwrap(),
+        );
+        let (a_transform, b_transform) = (
+            app.world.get::<Transform>(a).unwrap(),
+            app.world.get::<Transform>(b).unwrap(),
+        );
+        a_meta.precise_intersect_test(
+            &b_meta.path,
+            a_transform,
+            b_transform,
+            Globals::default().collision_tolerance,
+        )
+    }
+
+    // the squares are shifted vertically, so that their edges cross instead of being aligned
+    #[test]
+    fn the_pieces_of_a_cut_push_each_other_apart() {
+        let mut app = app();
+        let left = spawn_square(&mut app, Vec2::new(-20.0, 0.0), true);
+        let right = spawn_square(&mut app, Vec2::new(20.0, 20.0), true);
+        assert!(overlapping(&app, left, right));
+        run(&mut app, 120);
+
+        assert!(position(&app, left).x < -20.0);
+        assert!(position(&app, right).x > 20.0);
+        assert!(!overlapping(&app, left, right));
+        assert!(app.world.get::<ForceMotion>(left).is_none());
+        assert!(app.world.get::<ForceMotion>(right).is_none());
+    }
+
+    #[test]
+    fn a_piece_is_pushed_out_of_a_still_polygon() {
+        let mut app = app();
+        let still = spawn_square(&mut app, Vec2::ZERO, false);
+        let piece = spawn_square(&mut app, Vec2::new(40.0, 20.0), true);
+        run(&mut app, 120);
+
+        assert_eq!(position(&app, still), Vec2::ZERO);
+        assert!(position(&app, piece).x > 40.0);
+        assert!(!overlapping(&app, still, piece));
+    }
+}
@@ -1,4 +1,6 @@
+use crate::bindings::*;
 use crate::cut::CutSegment;
+use crate::gamepad::GamepadInput;
 use crate::input::Action;
 use crate::input::Cursor;
 use crate::material::*;
@@ -29,7 +31,9 @@ impl Plugin for PolyMakerPlugin {
             // .add_system(add_point_to_poly)
             .add_system(delete_poly)
             .add_system(delete_all)
-            .add_system(select_poly);
+            .add_system(select_poly)
+            .add_system(box_select)
+            .add_system(cycle_selected_poly);
         // .add_system(start_poly_segment);
     }
 }
@@ -53,31 +57,180 @@ pub struct MakingPolygon {
     pub all_points: Vec<Vec2>,
 }
 
+//
+//
+// Selects the polygon under the cursor. With keep_selected, the polygon is added to the
+// selection, or removed from it if it was already selected. Clicking next to the polygons
+// starts a SelectionBox instead, see box_select(..)
 pub fn select_poly(
     mut commands: Commands,
     mut fill_mesh_materials: ResMut<Assets<FillMesh2dMaterial>>,
-    mut query: Query<(Entity, &MeshMeta, &Transform, &Handle<FillMesh2dMaterial>), With<Polygon>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<
+        (
+            Entity,
+            &MeshMeta,
+            &Transform,
+            &Handle<FillMesh2dMaterial>,
+            Option<&Selected>,
+        ),
+        With<Polygon>,
+    >,
     mut action_event_reader: EventReader<Action>,
+    globals: Res<Globals>,
 ) {
     if let Some(Action::SelectPoly { pos, keep_selected }) = action_event_reader.iter().next() {
         if !keep_selected {
-            for (entity, _, _, mat_handle) in query.iter_mut() {
-                commands.entity(entity).remove::<Selected>();
+            for (entity, _, _, mat_handle, _) in query.iter_mut() {
+                commands
+                    .entity(entity)
+                    .remove::<Selected>()
+                    .remove::<Grouped>();
                 let mat = fill_mesh_materials.get_mut(mat_handle).unwrap();
                 mat.selected = 0.0;
             }
         }
 
-        for (entity, mesh_meta, transform, mat_handle) in query.iter_mut() {
+        let mut hit = false;
+        for (entity, mesh_meta, transform, mat_handle, selected) in query.iter_mut() {
             //
             let mat = fill_mesh_materials.get_mut(mat_handle).unwrap();
 
             if mesh_meta.hit_test(&Point::new(pos.x, pos.y), &transform).0 {
-                commands.entity(entity).insert(Selected);
-                mat.selected = 1.0;
+                if *keep_selected && selected.is_some() {
+                    commands
+                        .entity(entity)
+                        .remove::<Selected>()
+                        .remove::<Grouped>();
+                    mat.selected = 0.0;
+                } else {
+                    commands.entity(entity).insert(Selected);
+                    mat.selected = 1.0;
+                }
+                hit = true;
                 break;
             }
         }
+
+        if !hit {
+            commands
+                .spawn_bundle(MaterialMesh2dBundle {
+                    mesh: Mesh2dHandle(meshes.add(Mesh::from(shape::Quad::new(Vec2::ONE)))),
+                    material: color_materials.add(globals.selection_box_color.into()),
+                    transform: Transform::from_translation(pos.extend(SELECTION_BOX_Z))
+                        .with_scale(Vec3::ZERO),
+                    ..Default::default()
+                })
+                .insert(SelectionBox { start: *pos });
+        }
+    }
+}
+
+//
+//
+// Stretches the SelectionBox up to the cursor. Upon release of the button bound to Select
+// or SelectKeep, the polygons whose center of mass is inside the box are added to the
+// selection.
+pub fn box_select(
+    mut commands: Commands,
+    mut fill_mesh_materials: ResMut<Assets<FillMesh2dMaterial>>,
+    mut box_query: Query<(Entity, &SelectionBox, &mut Transform), Without<Polygon>>,
+    poly_query: Query<(Entity, &Transform, &Handle<FillMesh2dMaterial>), With<Polygon>>,
+    cursor: Res<Cursor>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<GamepadInput>,
+    bindings: Res<Bindings>,
+) {
+    let state = ButtonState {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad: &gamepad_input,
+        wheel_up: false,
+        wheel_down: false,
+    };
+    let released = bindings.trigger_just_released(InputAction::Select, &state)
+        || bindings.trigger_just_released(InputAction::SelectKeep, &state);
+
+    for (entity, selection_box, mut transform) in box_query.iter_mut() {
+        let min = selection_box.start.min(cursor.position);
+        let max = selection_box.start.max(cursor.position);
+
+        transform.translation = ((min + max) / 2.0).extend(SELECTION_BOX_Z);
+        transform.scale = (max - min).extend(1.0);
+
+        if released {
+            for (poly_entity, poly_transform, mat_handle) in poly_query.iter() {
+                let center = poly_transform.translation.truncate();
+                if center.cmpge(min).all() && center.cmple(max).all() {
+                    commands.entity(poly_entity).insert(Selected);
+                    let mat = fill_mesh_materials.get_mut(mat_handle).unwrap();
+                    mat.selected = 1.0;
+                }
+            }
+
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+//
+//
+// Selects the next (dir = 1) or previous (dir = -1) polygon and moves the Cursor onto it,
+// so that a gamepad or the keyboard can grab it without pointing at it. The polygons
+// are ordered from left to right.
+pub fn cycle_selected_poly(
+    mut commands: Commands,
+    mut fill_mesh_materials: ResMut<Assets<FillMesh2dMaterial>>,
+    query: Query<
+        (
+            Entity,
+            &Transform,
+            &Handle<FillMesh2dMaterial>,
+            Option<&Selected>,
+        ),
+        With<Polygon>,
+    >,
+    mut action_event_reader: EventReader<Action>,
+    mut cursor: ResMut<Cursor>,
+) {
+    if let Some(Action::CyclePoly { dir }) = action_event_reader.iter().next() {
+        let mut polygons: Vec<_> = query.iter().collect();
+        if polygons.is_empty() {
+            return;
+        }
+        polygons.sort_by(|a, b| {
+            a.1.translation
+                .x
+                .partial_cmp(&b.1.translation.x)
+                .unwrap()
+                .then(a.0.cmp(&b.0))
+        });
+
+        let num = polygons.len() as i32;
+        let next_index = if let Some(idx) = polygons.iter().position(|p| p.3.is_some()) {
+            (idx as i32 + dir).rem_euclid(num)
+        } else if *dir > 0 {
+            0
+        } else {
+            num - 1
+        };
+
+        for (k, (entity, transform, mat_handle, _)) in polygons.iter().enumerate() {
+            let mat = fill_mesh_materials.get_mut(mat_handle).unwrap();
+            if k as i32 == next_index {
+                commands.entity(*entity).insert(Selected);
+                mat.selected = 1.0;
+                cursor.position = transform.translation.truncate();
+            } else {
+                commands
+                    .entity(*entity)
+                    .remove::<Selected>()
+                    .remove::<Grouped>();
+                mat.selected = 0.0;
+            }
+        }
     }
 }
 
@@ -154,7 +154,7 @@ pub fn quick_save(
         save_path.push("dummy.point");
         //
         //
-        println!("saving to {:?}", save_path);
+        info!("saving to {:?}", save_path);
         //
         //
         // Save all individual meshes and meta data for the meshes in the same folder
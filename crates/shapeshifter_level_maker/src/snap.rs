@@ -0,0 +1,303 @@
+use bevy::prelude::*;
+
+use crate::input::Action;
+use crate::util::*;
+
+use lyon::tessellation::path::Path;
+
+// edges that are less than this angle apart (in radians) are turned to be exactly parallel
+pub const SNAP_MAX_ANGLE: f32 = 0.09;
+
+// event sent when a polygon is released, see transform_poly(..)
+pub struct SnapPoly(pub Entity);
+
+// the corners of a polygon path
+pub fn path_vertices(path: &Path) -> Vec<Vec2> {
+    path.iter()
+        .filter_map(|seg| match seg {
+            lyon::path::Event::Begin { at } => Some(Vec2::new(at.x, at.y)),
+            lyon::path::Event::Line { from: _, to } => Some(Vec2::new(to.x, to.y)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn edges(vertices: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    let n = vertices.len();
+    (0..n).map(move |k| (vertices[k], vertices[(k + 1) % n]))
+}
+
+// angle that turns `dir` parallel to `other_dir`, in ]-pi/2, pi/2]. Touching polygons
+// have opposite edge directions, so both orientations count as parallel
+fn parallel_angle(dir: Vec2, other_dir: Vec2) -> f32 {
+    let mut angle = dir.perp_dot(other_dir).atan2(dir.dot(other_dir));
+    if angle > std::f32::consts::FRAC_PI_2 {
+        angle -= std::f32::consts::PI;
+    } else if angle <= -std::f32::consts::FRAC_PI_2 {
+        angle += std::f32::consts::PI;
+    }
+    angle
+}
+
+//
+//
+// Finds the small rotation (around `center`) and translation that bring the edges of the
+// `moving` polygon flush against a nearby edge of one of the `others`, and one of its
+// vertices on a nearby vertex. Returns None when nothing is within `snap_distance`.
+//
+// Only the closest edge is aligned. The vertex snap then slides the polygon along that
+// edge, so that it stays flush.
+pub fn magnetic_snap(
+    moving: &[Vec2],
+    center: Vec2,
+    others: &[Vec<Vec2>],
+    snap_distance: f32,
+    allow_rotation: bool,
+) -> Option<(f32, Vec2)> {
+    //
+    //
+    // closest pair of almost parallel edges
+    // (distance, angle, index of the moving edge, start and direction of the other edge)
+    let mut best_edge: Option<(f32, f32, usize, Vec2, Vec2)> = None;
+    for (k, (a, b)) in edges(moving).enumerate() {
+        let dir = (b - a).normalize_or_zero();
+        let mid = (a + b) / 2.0;
+        for other in others.iter() {
+            for (c, d) in edges(other) {
+                let other_len = (d - c).length();
+                if other_len == 0.0 || dir == Vec2::ZERO {
+                    continue;
+                }
+                let other_dir = (d - c) / other_len;
+
+                let angle = parallel_angle(dir, other_dir);
+                if angle.abs() > SNAP_MAX_ANGLE || (!allow_rotation && angle.abs() > 0.001) {
+                    continue;
+                }
+
+                let distance = (mid - c).perp_dot(other_dir).abs();
+                if distance > snap_distance {
+                    continue;
+                }
+
+                // the edges must face each other
+                let proj_a = (a - c).dot(other_dir);
+                let proj_b = (b - c).dot(other_dir);
+                if proj_a.max(proj_b) < 0.0 || proj_a.min(proj_b) > other_len {
+                    continue;
+                }
+
+                if best_edge.map_or(true, |(best, _, _, _, _)| distance < best) {
+                    best_edge = Some((distance, angle, k, c, other_dir));
+                }
+            }
+        }
+    }
+
+    let mut rotation = 0.0;
+    let mut translation = Vec2::ZERO;
+    let mut slide_dir = None;
+
+    if let Some((_, angle, k, c, other_dir)) = best_edge {
+        if allow_rotation {
+            rotation = angle;
+        }
+
+        //
+        //
+        // after the rotation, push the edge onto the other one
+        let a = rotate_around(moving[k], center, rotation);
+        let b = rotate_around(moving[(k + 1) % moving.len()], center, rotation);
+
+        let normal = other_dir.perp();
+        let signed_distance = ((a + b) / 2.0 - c).dot(normal);
+        translation = -normal * signed_distance;
+        slide_dir = Some(other_dir);
+    }
+
+    //
+    //
+    // closest pair of vertices
+    let placed: Vec<Vec2> = moving
+        .iter()
+        .map(|v| rotate_around(*v, center, rotation) + translation)
+        .collect();
+
+    let mut best_vertex: Option<Vec2> = None;
+    for v in placed.iter() {
+        for other in others.iter() {
+            for w in other.iter() {
+                let offset = *w - *v;
+                if offset.length() < snap_distance
+                    && best_vertex.map_or(true, |best| offset.length() < best.length())
+                {
+                    best_vertex = Some(offset);
+                }
+            }
+        }
+    }
+
+    if let Some(offset) = best_vertex {
+        translation += match slide_dir {
+            Some(dir) => dir * offset.dot(dir),
+            None => offset,
+        };
+    }
+
+    if best_edge.is_none() && best_vertex.is_none() {
+        None
+    } else {
+        Some((rotation, translation))
+    }
+}
+
+//
+//
+// Snaps a released polygon against the other polygons, the target and the obstacles, then
+// checks it for collisions. A Grouped polygon is snapped with the rest of its group, as
+// one rigid body turning around the combined center of mass.
+pub fn snap_poly(
+    mut query: Query<(Entity, &mut Transform, &MeshMeta, Option<&Grouped>), With<Polygon>>,
+    target_query: Query<(&Transform, &Target), Without<Polygon>>,
+    obstacle_query: Query<(&Transform, &Obstacle), Without<Polygon>>,
+    mut snap_event_reader: EventReader<SnapPoly>,
+    globals: Res<Globals>,
+    level_rules: Res<LevelRules>,
+    mut collision_test_writer: EventWriter<TestCollisionEvent>,
+) {
+    for SnapPoly(entity) in snap_event_reader.iter() {
+        let maybe_group = match query.get(*entity) {
+            Ok((_, _, _, Some(grouped))) => Some(grouped.group),
+            _ => None,
+        };
+        let group: Vec<Entity> = if let Some(group) = maybe_group {
+            query
+                .iter()
+                .filter(|(_, _, _, grouped)| grouped.map_or(false, |g| g.group == group))
+                .map(|(entity, _, _, _)| entity)
+                .collect()
+        } else {
+            vec![*entity]
+        };
+
+        let mut others = Vec::new();
+        let mut members = Vec::new();
+        let mut moving = Vec::new();
+        for (other_entity, transform, mesh_meta, _) in query.iter() {
+            let vertices = path_vertices(&transform_path(&mesh_meta.path, transform).0);
+            if group.contains(&other_entity) {
+                members.push((transform.translation.truncate(), mesh_meta.area()));
+                moving.push(vertices);
+            } else {
+                others.push(vertices);
+            }
+        }
+        for (transform, target) in target_query.iter() {
+            others.push(path_vertices(&transform_path(&target.path, transform).0));
+        }
+        for (transform, obstacle) in obstacle_query.iter() {
+            others.push(path_vertices(&transform_path(&obstacle.path, transform).0));
+        }
+
+        //
+        //
+        // the member of the group that needs the smallest move decides the snap
+        let center = group_center(&members);
+        let best_snap = moving
+            .iter()
+            .filter_map(|vertices| {
+                magnetic_snap(
+                    vertices,
+                    center,
+                    &others,
+                    globals.snap_distance,
+                    level_rules.rotation == RotationRule::Free,
+                )
+            })
+            .min_by(|a, b| a.1.length().partial_cmp(&b.1.length()).unwrap());
+
+        for member in group.iter() {
+            if let Ok((_, mut transform, _, _)) = query.get_mut(*member) {
+                if let Some((rotation, translation)) = best_snap {
+                    let (axis, angle) = transform.rotation.to_axis_angle();
+                    transform.rotation = Quat::from_rotation_z(axis.z * angle + rotation);
+                    transform.translation =
+                        (rotate_around(transform.translation.truncate(), center, rotation)
+                            + translation)
+                            .extend(transform.translation.z);
+                }
+            }
+
+            collision_test_writer.send(TestCollisionEvent(*member));
+        }
+    }
+}
+
+pub fn toggle_snapping(mut action_event_reader: EventReader<Action>, mut globals: ResMut<Globals>) {
+    if let Some(Action::ToggleSnapping) = action_event_reader.iter().next() {
+        globals.magnetic_snapping = !globals.magnetic_snapping;
+        info!("magnetic snapping: {}", globals.magnetic_snapping);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f32, y: f32) -> Vec<Vec2> {
+        vec![
+            Vec2::new(x, y),
+            Vec2::new(x + 10.0, y),
+            Vec2::new(x + 10.0, y + 10.0),
+            Vec2::new(x, y + 10.0),
+        ]
+    }
+
+    fn rotated_square(angle: f32) -> Vec<Vec2> {
+        square(0.0, 0.0)
+            .iter()
+            .map(|v| rotate_around(*v, Vec2::splat(5.0), angle))
+            .collect()
+    }
+
+    #[test]
+    fn nothing_nearby() {
+        let others = vec![square(30.0, 0.0)];
+        assert_eq!(
+            magnetic_snap(&square(0.0, 0.0), Vec2::splat(5.0), &others, 5.0, true),
+            None
+        );
+    }
+
+    // the gap is closed, then the square slides along the edge onto the vertices
+    #[test]
+    fn edge_then_vertex_snap() {
+        let others = vec![square(13.0, 2.0)];
+        let (rotation, translation) =
+            magnetic_snap(&square(0.0, 0.0), Vec2::splat(5.0), &others, 5.0, true).unwrap();
+        assert_eq!(rotation, 0.0);
+        assert!(
+            (translation - Vec2::new(3.0, 2.0)).length() < 1e-4,
+            "{}",
+            translation
+        );
+    }
+
+    #[test]
+    fn tilted_edge_is_turned_parallel() {
+        let others = vec![square(11.0, 0.0)];
+        let (rotation, _) =
+            magnetic_snap(&rotated_square(0.05), Vec2::splat(5.0), &others, 5.0, true).unwrap();
+        assert!((rotation + 0.05).abs() < 1e-4, "{}", rotation);
+    }
+
+    #[test]
+    fn tilted_edge_is_not_turned_without_rotation() {
+        let others = vec![square(11.0, 0.0)];
+        if let Some((rotation, _)) =
+            magnetic_snap(&rotated_square(0.05), Vec2::splat(5.0), &others, 5.0, false)
+        {
+            assert_eq!(rotation, 0.0);
+        }
+    }
+}
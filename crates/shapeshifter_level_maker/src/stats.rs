@@ -0,0 +1,145 @@
+use crate::validate::signed_area;
+
+use bevy::prelude::*;
+
+// every polygon of the library is normalized to this area
+pub const NORMALIZED_AREA: f32 = 50000.0;
+
+#[derive(Debug, Clone)]
+pub struct ShapeStats {
+    pub name: String,
+    pub area: f32,
+    pub width: f32,
+    pub height: f32,
+    pub perimeter: f32,
+    pub is_convex: bool,
+}
+
+impl ShapeStats {
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width / self.height.max(f32::EPSILON)
+    }
+
+    // 1.0 for a disk, smaller for long or jagged shapes
+    pub fn compactness(&self) -> f32 {
+        4.0 * std::f32::consts::PI * self.area / (self.perimeter * self.perimeter)
+    }
+}
+
+pub fn compute_shape_stats(name: &str, points: &Vec<Vec2>) -> ShapeStats {
+    let n = points.len();
+
+    let mut min = Vec2::splat(f32::MAX);
+    let mut max = Vec2::splat(f32::MIN);
+    let mut perimeter = 0.0;
+    for k in 0..n {
+        min = min.min(points[k]);
+        max = max.max(points[k]);
+        perimeter += (points[(k + 1) % n] - points[k]).length();
+    }
+
+    //
+    //
+    // a polygon is convex if all its turns go in the same direction
+    let mut has_left_turn = false;
+    let mut has_right_turn = false;
+    for k in 0..n {
+        let a = points[k];
+        let b = points[(k + 1) % n];
+        let c = points[(k + 2) % n];
+        let cross = (b - a).perp_dot(c - b);
+        if cross > 0.0 {
+            has_left_turn = true;
+        } else if cross < 0.0 {
+            has_right_turn = true;
+        }
+    }
+
+    ShapeStats {
+        name: name.to_string(),
+        area: signed_area(points).abs(),
+        width: max.x - min.x,
+        height: max.y - min.y,
+        perimeter,
+        is_convex: !(has_left_turn && has_right_turn),
+    }
+}
+
+//
+//
+// Moves the points to their center of mass and scales them such that the area of
+// the polygon becomes `area`. Same operation as the "m" key of the import_pts example.
+pub fn normalize_points(points: &Vec<Vec2>, area: f32) -> Vec<Vec2> {
+    let area_sqrt = signed_area(points).abs().sqrt();
+
+    let mut center_of_mass = Vec2::ZERO;
+    for pt in points.iter() {
+        center_of_mass += *pt;
+    }
+    center_of_mass /= points.len() as f32;
+
+    points
+        .iter()
+        .map(|pt| (*pt - center_of_mass) / area_sqrt * area.sqrt())
+        .collect()
+}
+
+//
+//
+// The target path is built with build_path_from_points(points, target_multiplier), so its
+// area grows with the square of the multiplier. This is the smallest multiplier for which
+// the target has at least the area of the polygon (spawned with a multiplier of 1.0).
+pub fn min_target_multiplier(polygon: &ShapeStats, target: &ShapeStats) -> f32 {
+    (polygon.area / target.area).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle(width: f32, height: f32) -> Vec<Vec2> {
+        vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(width, 0.0),
+            Vec2::new(width, height),
+            Vec2::new(0.0, height),
+        ]
+    }
+
+    #[test]
+    fn rectangle_stats() {
+        let stats = compute_shape_stats("rectangle", &rectangle(200.0, 100.0));
+        assert_eq!(stats.area, 20000.0);
+        assert_eq!(stats.width, 200.0);
+        assert_eq!(stats.height, 100.0);
+        assert_eq!(stats.perimeter, 600.0);
+        assert!(stats.is_convex);
+        assert_eq!(stats.aspect_ratio(), 2.0);
+    }
+
+    #[test]
+    fn notched_shape_is_not_convex() {
+        let mut points = rectangle(200.0, 100.0);
+        points.insert(3, Vec2::new(100.0, 50.0));
+        assert!(!compute_shape_stats("notched", &points).is_convex);
+    }
+
+    #[test]
+    fn normalized_points_have_the_given_area_around_the_origin() {
+        let points = normalize_points(&rectangle(200.0, 100.0), NORMALIZED_AREA);
+        let stats = compute_shape_stats("rectangle", &points);
+        assert!((stats.area - NORMALIZED_AREA).abs() < 1.0);
+        assert!((stats.aspect_ratio() - 2.0).abs() < 1e-4);
+
+        let center = points.iter().fold(Vec2::ZERO, |sum, pt| sum + *pt) / points.len() as f32;
+        assert!(center.length() < 1e-3);
+    }
+
+    #[test]
+    fn target_multiplier_scales_with_the_side() {
+        let polygon = compute_shape_stats("polygon", &rectangle(200.0, 200.0));
+        let target = compute_shape_stats("target", &rectangle(100.0, 100.0));
+        assert_eq!(min_target_multiplier(&polygon, &target), 2.0);
+        assert_eq!(min_target_multiplier(&target, &polygon), 0.5);
+    }
+}
@@ -124,7 +124,10 @@ pub fn check_win_condition(
     query: Query<(&Transform, &MeshMeta), With<Polygon>>,
     target_query: Query<(&Target, &Transform)>,
     mut check_win_condition_event: EventReader<TestWinEvent>,
-    mut has_won_event_writer: EventWriter<HasWonLevelEvent>,
+    mut level_won_event_writer: EventWriter<LevelWon>,
+    current_level: Res<CurrentLevel>,
+    remaining_cuts: Res<RemainingCuts>,
+    flip_count: Res<FlipCount>,
 ) {
     for _ in check_win_condition_event.iter() {
         //
@@ -157,8 +160,15 @@ pub fn check_win_condition(
                 }
             }
             if has_won {
-                println!("You got the level! moving on to the next one");
-                has_won_event_writer.send(HasWonLevelEvent {});
+                level_won_event_writer.send(LevelWon {
+                    stats: LevelStats {
+                        polygon: current_level.polygon.clone(),
+                        target: current_level.target.clone(),
+                        pieces: query.iter().count(),
+                        remaining_cuts: remaining_cuts.remaining,
+                        flips: flip_count.flips,
+                    },
+                });
             }
         }
     }
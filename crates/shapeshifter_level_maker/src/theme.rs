@@ -0,0 +1,396 @@
+use crate::material::{
+    CutMesh2dMaterial, FillMesh2dMaterial, HatchMesh2dMaterial, ObstacleMesh2dMaterial,
+    PiecePattern, TargetMesh2dMaterial,
+};
+use crate::util::Globals;
+
+use bevy::prelude::*;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use std::io::{Read, Write};
+
+// themes shipped with the game
+const DEFAULT_THEMES: &str = include_str!("themes.json");
+
+// a themes.json in the current directory replaces the shipped themes
+pub const THEMES_FILE: &str = "themes.json";
+
+// name of the chosen theme, saved in the current directory like the bindings
+pub const THEME_CHOICE_FILE: &str = "theme.json";
+
+fn parse_hex<E: serde::de::Error>(hex: &str) -> Result<Color, E> {
+    Color::hex(hex.trim_start_matches('#'))
+        .map_err(|err| E::custom(format!("invalid color {}: {:?}", hex, err)))
+}
+
+fn to_hex(color: &Color) -> String {
+    let [r, g, b, a] = color
+        .as_rgba_f32()
+        .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    if a == 255 {
+        format!("{:02X}{:02X}{:02X}", r, g, b)
+    } else {
+        format!("{:02X}{:02X}{:02X}{:02X}", r, g, b, a)
+    }
+}
+
+// colors are written as hex strings in the themes file, e.g. "800080", or "FF000040"
+// with an alpha
+mod hex_color {
+    use super::*;
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        parse_hex(&String::deserialize(deserializer)?)
+    }
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(color))
+    }
+}
+
+mod hex_colors {
+    use super::*;
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Color>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|hex| parse_hex(hex))
+            .collect()
+    }
+
+    pub fn serialize<S: Serializer>(colors: &[Color], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(colors.iter().map(to_hex))
+    }
+}
+
+// for the themes written before these colors were added
+fn default_invalid_cut() -> Color {
+    Color::RED
+}
+
+fn default_button() -> Color {
+    Color::rgb(0.15, 0.15, 0.15)
+}
+
+fn default_hovered_button() -> Color {
+    Color::rgb(0.25, 0.25, 0.25)
+}
+
+fn default_hovered_pressed_button() -> Color {
+    Color::rgb(0.25, 0.65, 0.25)
+}
+
+fn default_pressed_button() -> Color {
+    Color::rgb(0.35, 0.75, 0.35)
+}
+
+fn default_outline() -> Color {
+    Color::rgb(0.1, 0.1, 0.1)
+}
+
+fn default_selected_outline() -> Color {
+    Color::YELLOW
+}
+
+fn default_intersecting_outline() -> Color {
+    Color::RED
+}
+
+fn default_excess() -> Color {
+    Color::rgba(1.0, 0.65, 0.0, 0.7)
+}
+
+fn default_overlap() -> Color {
+    Color::rgba(1.0, 0.0, 0.0, 0.7)
+}
+
+//
+//
+// Every color of the game, from the background to the highlight of the intersecting
+// polygons
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    #[serde(with = "hex_color")]
+    pub background: Color,
+    #[serde(with = "hex_color")]
+    pub text: Color,
+    // background of the menus and buttons
+    #[serde(with = "hex_color")]
+    pub panel: Color,
+    // the buttons at rest and hovered. The pressed colors are also the selected option
+    #[serde(with = "hex_color", default = "default_button")]
+    pub button: Color,
+    #[serde(with = "hex_color", default = "default_hovered_button")]
+    pub hovered_button: Color,
+    #[serde(with = "hex_color", default = "default_hovered_pressed_button")]
+    pub hovered_pressed_button: Color,
+    #[serde(with = "hex_color", default = "default_pressed_button")]
+    pub pressed_button: Color,
+    #[serde(with = "hex_color")]
+    pub polygon: Color,
+    #[serde(with = "hex_color")]
+    pub cut_polygon: Color,
+    #[serde(with = "hex_color")]
+    pub polygon_segment: Color,
+    #[serde(with = "hex_color")]
+    pub cutting_segment: Color,
+    #[serde(with = "hex_color", default = "default_invalid_cut")]
+    pub invalid_cut: Color,
+    #[serde(with = "hex_color")]
+    pub target: Color,
+    #[serde(with = "hex_color")]
+    pub ghost: Color,
+    #[serde(with = "hex_color")]
+    pub obstacle: Color,
+    #[serde(with = "hex_color")]
+    pub intersecting: Color,
+    #[serde(with = "hex_color")]
+    pub selected: Color,
+    #[serde(with = "hex_color")]
+    pub selection_box: Color,
+    #[serde(with = "hex_color", default = "default_outline")]
+    pub outline: Color,
+    #[serde(with = "hex_color", default = "default_selected_outline")]
+    pub selected_outline: Color,
+    #[serde(with = "hex_color", default = "default_intersecting_outline")]
+    pub intersecting_outline: Color,
+    // hatches over the parts of the pieces outside the target, and over other pieces
+    #[serde(with = "hex_color", default = "default_excess")]
+    pub excess: Color,
+    #[serde(with = "hex_color", default = "default_overlap")]
+    pub overlap: Color,
+    // outlines of the pieces of the cut preview, used in turn
+    #[serde(with = "hex_colors")]
+    pub preview: Vec<Color>,
+    // drawn over the color of the pieces
+    #[serde(default)]
+    pub pattern: PiecePattern,
+    // image in the assets folder, for the "texture" pattern
+    #[serde(default)]
+    pub texture: Option<String>,
+}
+
+impl Theme {
+    // the colors that can be found in the materials of the spawned entities
+    fn material_colors(&self) -> [Color; 10] {
+        [
+            self.polygon,
+            self.cut_polygon,
+            self.polygon_segment,
+            self.cutting_segment,
+            self.target,
+            self.ghost,
+            self.obstacle,
+            self.selection_box,
+            self.excess,
+            self.overlap,
+        ]
+    }
+
+    // the color of `new` that plays the same role as `color` in `self`
+    pub fn remap(&self, color: Color, new: &Theme) -> Option<Color> {
+        let color = color.as_rgba_f32();
+        self.material_colors()
+            .iter()
+            .zip(new.material_colors().iter())
+            .find(|(old, _)| old.as_rgba_f32() == color)
+            .map(|(_, new)| *new)
+    }
+
+    fn remap_vec4(&self, color: Vec4, new: &Theme) -> Vec4 {
+        self.material_colors()
+            .iter()
+            .zip(new.material_colors().iter())
+            .find(|(old, _)| Vec4::from(**old) == color)
+            .map(|(_, new)| Vec4::from(*new))
+            .unwrap_or(color)
+    }
+}
+
+//
+//
+// All the themes, and the one in use. Changing `current` recolors the entities that are
+// already spawned, see apply_theme(..)
+pub struct Themes {
+    pub list: Vec<Theme>,
+    pub current: usize,
+}
+
+impl Themes {
+    pub fn current(&self) -> &Theme {
+        &self.list[self.current]
+    }
+
+    pub fn select_next(&mut self) {
+        self.current = (self.current + 1) % self.list.len();
+        self.save_choice();
+    }
+
+    pub fn load() -> Self {
+        let mut list: Vec<Theme> =
+            serde_json::from_str(DEFAULT_THEMES).expect("invalid shipped themes.json");
+
+        let mut path = std::env::current_dir().unwrap_or_default();
+        path.push(THEMES_FILE);
+
+        let mut contents = String::new();
+        if let Ok(mut file) = std::fs::File::open(&path) {
+            if file.read_to_string(&mut contents).is_ok() {
+                match serde_json::from_str::<Vec<Theme>>(&contents) {
+                    Ok(themes) if !themes.is_empty() => list = themes,
+                    Ok(_) => warn!("no theme in {:?}, using the shipped themes", path),
+                    Err(err) => warn!(
+                        "could not read {:?}, using the shipped themes: {}",
+                        path, err
+                    ),
+                }
+            }
+        }
+
+        let mut path = std::env::current_dir().unwrap_or_default();
+        path.push(THEME_CHOICE_FILE);
+
+        let mut current = 0;
+        let mut contents = String::new();
+        if let Ok(mut file) = std::fs::File::open(&path) {
+            if file.read_to_string(&mut contents).is_ok() {
+                match serde_json::from_str::<String>(&contents) {
+                    Ok(name) => match list.iter().position(|theme| theme.name == name) {
+                        Some(index) => current = index,
+                        None => warn!("no theme named \"{}\", using {}", name, list[0].name),
+                    },
+                    Err(err) => warn!("could not read {:?}: {}", path, err),
+                }
+            }
+        }
+
+        Self { list, current }
+    }
+
+    pub fn save_choice(&self) {
+        let mut path = std::env::current_dir().unwrap_or_default();
+        path.push(THEME_CHOICE_FILE);
+
+        let serialized = serde_json::to_string(&self.current().name).unwrap();
+        let result = std::fs::File::create(&path)
+            .and_then(|mut output| output.write_all(serialized.as_bytes()));
+        if let Err(err) = result {
+            warn!("could not save {:?}: {}", path, err);
+        }
+    }
+}
+
+//
+//
+// Recolors the Globals and every material that uses a color of the previous theme
+pub fn apply_theme(
+    themes: Res<Themes>,
+    mut previous: Local<Option<Theme>>,
+    mut globals: ResMut<Globals>,
+    clear_color: Option<ResMut<ClearColor>>,
+    mut fill_materials: ResMut<Assets<FillMesh2dMaterial>>,
+    mut target_materials: ResMut<Assets<TargetMesh2dMaterial>>,
+    mut obstacle_materials: ResMut<Assets<ObstacleMesh2dMaterial>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    mut cut_materials: ResMut<Assets<CutMesh2dMaterial>>,
+    mut hatch_materials: ResMut<Assets<HatchMesh2dMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    if !themes.is_changed() {
+        return;
+    }
+
+    let theme = themes.current();
+    if let Some(mut clear_color) = clear_color {
+        clear_color.0 = theme.background;
+    }
+    globals.piece_texture = theme
+        .texture
+        .as_ref()
+        .map(|path| asset_server.load(path.as_str()));
+
+    let old = if let Some(old) = previous.replace(theme.clone()) {
+        old
+    } else {
+        // the Globals are themed when they are inserted
+        return;
+    };
+    if old == *theme {
+        return;
+    }
+
+    globals.set_theme(theme);
+
+    let piece_colors = [Vec4::from(old.polygon), Vec4::from(old.cut_polygon)];
+    for (_, material) in fill_materials.iter_mut() {
+        if piece_colors.contains(&material.color) {
+            material.set_pattern(globals.piece_pattern, globals.piece_texture.clone());
+        }
+        material.color = old.remap_vec4(material.color, theme);
+        material.intersecting_color = theme.intersecting.into();
+        material.selected_color = theme.selected.into();
+    }
+    for (_, material) in target_materials.iter_mut() {
+        material.color = old.remap_vec4(material.color, theme);
+    }
+    for (_, material) in cut_materials.iter_mut() {
+        material.color = theme.cutting_segment.into();
+        material.invalid_color = theme.invalid_cut.into();
+    }
+    for (_, material) in hatch_materials.iter_mut() {
+        material.color = old.remap_vec4(material.color, theme);
+    }
+    for (_, material) in obstacle_materials.iter_mut() {
+        material.color = old.remap_vec4(material.color, theme);
+    }
+    for (_, material) in color_materials.iter_mut() {
+        if let Some(color) = old.remap(material.color, theme) {
+            material.color = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(hex: &str) -> Result<Color, serde_json::Error> {
+        parse_hex(hex)
+    }
+
+    #[test]
+    fn hex_colors_with_and_without_alpha() {
+        assert_eq!(parse("800080").unwrap(), Color::rgb_u8(128, 0, 128));
+        assert_eq!(parse("#800080").unwrap(), Color::rgb_u8(128, 0, 128));
+        assert_eq!(parse("FF000040").unwrap(), Color::rgba_u8(255, 0, 0, 64));
+        assert!(parse("80008").is_err());
+        assert!(parse("purple").is_err());
+    }
+
+    #[test]
+    fn hex_colors_round_trip() {
+        for hex in ["800080", "FF000040", "1A1A1A", "99CCFF26"] {
+            assert_eq!(to_hex(&parse(hex).unwrap()), hex);
+        }
+    }
+
+    #[test]
+    fn shipped_themes_are_valid() {
+        let themes: Vec<Theme> = serde_json::from_str(DEFAULT_THEMES).unwrap();
+        assert!(!themes.is_empty());
+        for (k, theme) in themes.iter().enumerate() {
+            assert!(
+                !theme.preview.is_empty(),
+                "{} has no preview color",
+                theme.name
+            );
+            assert!(
+                themes[..k].iter().all(|other| other.name != theme.name),
+                "two themes are named {}",
+                theme.name
+            );
+        }
+    }
+}
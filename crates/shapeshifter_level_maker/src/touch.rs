@@ -0,0 +1,333 @@
+use bevy::prelude::*;
+
+// a finger that moves less than this (in world units) is still a press, not a drag
+pub const TOUCH_SLOP: f32 = 12.0;
+
+// a press held still for this long (in seconds) starts a cut
+pub const LONG_PRESS_TIME: f32 = 0.5;
+
+// an active touch, in world coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchPoint {
+    pub id: u64,
+    pub position: Vec2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TouchGesture {
+    // the single finger moved
+    Move { pos: Vec2 },
+    DragStart { pos: Vec2 },
+    DragEnd,
+    TwistStart { center: Vec2 },
+    // total angle turned since TwistStart, counter-clockwise
+    Twist { angle: f32 },
+    TwistEnd,
+    CutStart { pos: Vec2 },
+    CutEnd { pos: Vec2 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GestureState {
+    Idle,
+    Pressed {
+        id: u64,
+        start: Vec2,
+        since: f32,
+    },
+    Dragging {
+        id: u64,
+    },
+    Cutting {
+        id: u64,
+        last_pos: Vec2,
+    },
+    Twisting {
+        ids: (u64, u64),
+        last_angle: f32,
+        angle: f32,
+    },
+    // the gesture is over but some fingers are still down
+    WaitingForRelease,
+}
+
+impl Default for GestureState {
+    fn default() -> Self {
+        GestureState::Idle
+    }
+}
+
+//
+//
+// Turns the list of active touches of every frame into gestures:
+//     - one finger moved right away drags a piece
+//     - two fingers twist a piece around their middle point
+//     - one finger held still, then swiped, makes a cut
+//
+// It only depends on the touches given to update(..), so it can be driven by synthetic
+// touches without a window.
+#[derive(Default)]
+pub struct TouchGestures {
+    state: GestureState,
+}
+
+fn angle_between_fingers(a: Vec2, b: Vec2) -> f32 {
+    let v = b - a;
+    v.y.atan2(v.x)
+}
+
+impl TouchGestures {
+    pub fn update(&mut self, touches: &[TouchPoint], now: f32) -> Vec<TouchGesture> {
+        let mut gestures = Vec::new();
+        let find = |id: u64| touches.iter().find(|touch| touch.id == id);
+
+        let start_twist = |gestures: &mut Vec<TouchGesture>| {
+            let (a, b) = (touches[0], touches[1]);
+            gestures.push(TouchGesture::TwistStart {
+                center: (a.position + b.position) / 2.0,
+            });
+            GestureState::Twisting {
+                ids: (a.id, b.id),
+                last_angle: angle_between_fingers(a.position, b.position),
+                angle: 0.0,
+            }
+        };
+
+        self.state = match self.state {
+            GestureState::Idle => match touches.len() {
+                0 => GestureState::Idle,
+                1 => {
+                    gestures.push(TouchGesture::Move {
+                        pos: touches[0].position,
+                    });
+                    GestureState::Pressed {
+                        id: touches[0].id,
+                        start: touches[0].position,
+                        since: now,
+                    }
+                }
+                _ => start_twist(&mut gestures),
+            },
+
+            GestureState::Pressed { id, start, since } => {
+                if touches.len() >= 2 {
+                    start_twist(&mut gestures)
+                } else if let Some(touch) = find(id) {
+                    gestures.push(TouchGesture::Move {
+                        pos: touch.position,
+                    });
+                    if (touch.position - start).length() > TOUCH_SLOP {
+                        gestures.push(TouchGesture::DragStart { pos: start });
+                        GestureState::Dragging { id }
+                    } else if now - since > LONG_PRESS_TIME {
+                        gestures.push(TouchGesture::CutStart { pos: start });
+                        GestureState::Cutting {
+                            id,
+                            last_pos: touch.position,
+                        }
+                    } else {
+                        self.state
+                    }
+                } else {
+                    // a tap
+                    GestureState::Idle
+                }
+            }
+
+            GestureState::Dragging { id } => {
+                if touches.len() >= 2 {
+                    gestures.push(TouchGesture::DragEnd);
+                    start_twist(&mut gestures)
+                } else if let Some(touch) = find(id) {
+                    gestures.push(TouchGesture::Move {
+                        pos: touch.position,
+                    });
+                    self.state
+                } else {
+                    gestures.push(TouchGesture::DragEnd);
+                    GestureState::Idle
+                }
+            }
+
+            GestureState::Cutting { id, last_pos } => {
+                if let Some(touch) = find(id) {
+                    gestures.push(TouchGesture::Move {
+                        pos: touch.position,
+                    });
+                    GestureState::Cutting {
+                        id,
+                        last_pos: touch.position,
+                    }
+                } else {
+                    gestures.push(TouchGesture::CutEnd { pos: last_pos });
+                    if touches.is_empty() {
+                        GestureState::Idle
+                    } else {
+                        GestureState::WaitingForRelease
+                    }
+                }
+            }
+
+            GestureState::Twisting {
+                ids,
+                last_angle,
+                angle,
+            } => {
+                if let (Some(a), Some(b)) = (find(ids.0), find(ids.1)) {
+                    //
+                    //
+                    // accumulate the small changes of angle, so that turning past PI
+                    // does not jump back
+                    let new_angle = angle_between_fingers(a.position, b.position);
+                    let mut delta = new_angle - last_angle;
+                    if delta > std::f32::consts::PI {
+                        delta -= 2.0 * std::f32::consts::PI;
+                    } else if delta < -std::f32::consts::PI {
+                        delta += 2.0 * std::f32::consts::PI;
+                    }
+                    gestures.push(TouchGesture::Twist {
+                        angle: angle + delta,
+                    });
+                    GestureState::Twisting {
+                        ids,
+                        last_angle: new_angle,
+                        angle: angle + delta,
+                    }
+                } else {
+                    gestures.push(TouchGesture::TwistEnd);
+                    if touches.is_empty() {
+                        GestureState::Idle
+                    } else {
+                        GestureState::WaitingForRelease
+                    }
+                }
+            }
+
+            GestureState::WaitingForRelease => {
+                if touches.is_empty() {
+                    GestureState::Idle
+                } else {
+                    GestureState::WaitingForRelease
+                }
+            }
+        };
+
+        gestures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn touch(id: u64, x: f32, y: f32) -> TouchPoint {
+        TouchPoint {
+            id,
+            position: Vec2::new(x, y),
+        }
+    }
+
+    #[test]
+    fn one_finger_drag() {
+        let mut gestures = TouchGestures::default();
+
+        assert_eq!(
+            gestures.update(&[touch(1, 0.0, 0.0)], 0.0),
+            vec![TouchGesture::Move { pos: Vec2::ZERO }]
+        );
+        assert_eq!(
+            gestures.update(&[touch(1, 30.0, 0.0)], 0.1),
+            vec![
+                TouchGesture::Move {
+                    pos: Vec2::new(30.0, 0.0)
+                },
+                TouchGesture::DragStart { pos: Vec2::ZERO },
+            ]
+        );
+        assert_eq!(
+            gestures.update(&[touch(1, 60.0, 10.0)], 0.2),
+            vec![TouchGesture::Move {
+                pos: Vec2::new(60.0, 10.0)
+            }]
+        );
+        assert_eq!(gestures.update(&[], 0.3), vec![TouchGesture::DragEnd]);
+    }
+
+    #[test]
+    fn two_finger_twist_across_the_wraparound() {
+        let mut gestures = TouchGestures::default();
+
+        // the angle between the fingers starts just below PI
+        let start = PI - 0.1;
+        assert_eq!(
+            gestures.update(
+                &[touch(1, 0.0, 0.0), touch(2, start.cos(), start.sin())],
+                0.0
+            ),
+            vec![TouchGesture::TwistStart {
+                center: Vec2::new(start.cos(), start.sin()) / 2.0
+            }]
+        );
+
+        // and turns past PI, where atan2 jumps to -PI
+        let end = PI + 0.1;
+        let twist = gestures.update(&[touch(1, 0.0, 0.0), touch(2, end.cos(), end.sin())], 0.1);
+        match twist.as_slice() {
+            [TouchGesture::Twist { angle }] => assert!((angle - 0.2).abs() < 1e-4, "{}", angle),
+            _ => panic!("expected a twist, got {:?}", twist),
+        }
+
+        assert_eq!(
+            gestures.update(&[touch(1, 0.0, 0.0)], 0.2),
+            vec![TouchGesture::TwistEnd]
+        );
+        assert!(gestures.update(&[], 0.3).is_empty());
+    }
+
+    #[test]
+    fn long_press_starts_a_cut() {
+        let mut gestures = TouchGestures::default();
+
+        gestures.update(&[touch(1, 0.0, 0.0)], 0.0);
+        assert_eq!(
+            gestures.update(&[touch(1, 1.0, 0.0)], 0.2),
+            vec![TouchGesture::Move {
+                pos: Vec2::new(1.0, 0.0)
+            }]
+        );
+        assert_eq!(
+            gestures.update(&[touch(1, 1.0, 0.0)], LONG_PRESS_TIME + 0.1),
+            vec![
+                TouchGesture::Move {
+                    pos: Vec2::new(1.0, 0.0)
+                },
+                TouchGesture::CutStart { pos: Vec2::ZERO },
+            ]
+        );
+
+        gestures.update(&[touch(1, 100.0, 0.0)], 0.7);
+        assert_eq!(
+            gestures.update(&[], 0.8),
+            vec![TouchGesture::CutEnd {
+                pos: Vec2::new(100.0, 0.0)
+            }]
+        );
+    }
+
+    #[test]
+    fn tap_within_the_slop_is_not_a_drag() {
+        let mut gestures = TouchGestures::default();
+
+        gestures.update(&[touch(1, 0.0, 0.0)], 0.0);
+        let moved = TOUCH_SLOP - 1.0;
+        assert_eq!(
+            gestures.update(&[touch(1, moved, 0.0)], 0.1),
+            vec![TouchGesture::Move {
+                pos: Vec2::new(moved, 0.0)
+            }]
+        );
+        assert!(gestures.update(&[], 0.2).is_empty());
+        assert_eq!(gestures.state, GestureState::Idle);
+    }
+}
@@ -1,12 +1,18 @@
 // use crate::material::{FeltMaterial, FillMesh2dMaterial};
-use crate::material::FillMesh2dMaterial;
+use crate::material::{FillMesh2dMaterial, ObstacleMesh2dMaterial, PiecePattern};
+use crate::theme::Theme;
 
 use bevy::{
+    math::Affine2,
     prelude::*,
-    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        render_resource::PrimitiveTopology,
+    },
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
 };
 
+use lyon::algorithms::area::approximate_signed_area;
 use lyon::algorithms::hit_test::*;
 use lyon::path::FillRule;
 use lyon::tessellation::math::{point, Point};
@@ -25,11 +31,23 @@ use rand::{thread_rng, Rng};
 
 const TARGET_MULT: f32 = 1.1;
 
+// where the target is spawned, the obstacles are placed relative to it
+pub const TARGET_POSITION: Vec2 = Vec2::new(300.0, 0.0);
+
+// above the target, below the polygons
+const OBSTACLE_Z: f32 = 0.01;
+
+// above the polygons, whose z is between 1 and 2
+pub const SELECTION_BOX_Z: f32 = 100.0;
+
 pub struct Globals {
     pub polygon_segment_color: Color,
     pub polygon_color: Color,
     pub cutting_segment_thickness: f32,
     pub cutting_segment_color: Color,
+    // cut lines that would be rejected, see CutPreview
+    pub invalid_cut_color: Color,
+    pub cut_dash_length: f32,
     pub target_color: Color,
     pub min_turn_angle: f32,
     pub cut_polygon: Color,
@@ -39,6 +57,68 @@ pub struct Globals {
     pub grid_size: f32,
     pub target_size_multiplier: f32,
     pub ghost_color: Color,
+    pub obstacle_color: Color,
+    pub selection_box_color: Color,
+    pub intersecting_color: Color,
+    pub selected_color: Color,
+    pub preview_colors: Vec<Color>,
+    // the outlines of the polygons and targets, see outline.rs
+    pub outline_width: f32,
+    pub outline_color: Color,
+    pub selected_outline_color: Color,
+    pub intersecting_outline_color: Color,
+    // the hatched parts of the pieces outside the target and over other pieces, see overlap.rs
+    pub excess_color: Color,
+    pub overlap_color: Color,
+    pub hatch_spacing: f32,
+    pub piece_pattern: PiecePattern,
+    // loaded from the path in the theme, see apply_theme(..)
+    pub piece_texture: Option<Handle<Image>>,
+    pub magnetic_snapping: bool,
+    // released polygons snap to edges and vertices closer than this
+    pub snap_distance: f32,
+    // edges that overlap by less than this do not collide, when snapping is on
+    pub collision_tolerance: f32,
+    // released polygons keep the velocity of the drag, see physics_step(..).
+    // Toggled with InputAction::ToggleMomentum
+    pub momentum: bool,
+    pub min_throw_speed: f32,
+}
+
+impl Globals {
+    // touching edges are not colliding when the polygons snap to each other
+    pub fn touch_tolerance(&self) -> f32 {
+        if self.magnetic_snapping {
+            self.collision_tolerance
+        } else {
+            0.0
+        }
+    }
+
+    // the colors of the polygons, segments, etc. come from the theme
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.polygon_color = theme.polygon;
+        self.cut_polygon = theme.cut_polygon;
+        self.polygon_segment_color = theme.polygon_segment;
+        self.cutting_segment_color = theme.cutting_segment;
+        self.invalid_cut_color = theme.invalid_cut;
+        self.target_color = theme.target;
+        self.ghost_color = theme.ghost;
+        self.obstacle_color = theme.obstacle;
+        self.intersecting_color = theme.intersecting;
+        self.selected_color = theme.selected;
+        self.selection_box_color = theme.selection_box;
+        self.outline_color = theme.outline;
+        self.selected_outline_color = theme.selected_outline;
+        self.intersecting_outline_color = theme.intersecting_outline;
+        self.excess_color = theme.excess;
+        self.overlap_color = theme.overlap;
+        self.piece_pattern = theme.pattern;
+        // the pieces of the cut preview need at least one color
+        if !theme.preview.is_empty() {
+            self.preview_colors = theme.preview.clone();
+        }
+    }
 }
 
 impl Default for Globals {
@@ -48,12 +128,40 @@ impl Default for Globals {
             polygon_color: Color::PURPLE,
             cutting_segment_thickness: 2.0,
             cutting_segment_color: Color::ORANGE,
+            invalid_cut_color: Color::RED,
+            cut_dash_length: 12.0,
             target_color: Color::DARK_GRAY,
             ghost_color: Color::rgba(0.02, 0.01, 0.21, 1.0),
+            obstacle_color: Color::rgb(0.35, 0.3, 0.25),
+            selection_box_color: Color::rgba(0.6, 0.8, 1.0, 0.15),
+            intersecting_color: Color::rgba(1.0, 0.0, 0.0, 0.25),
+            selected_color: Color::rgba(1.0, 1.0, 0.0, 0.5),
+            preview_colors: vec![
+                Color::YELLOW,
+                Color::CYAN,
+                Color::ORANGE_RED,
+                Color::LIME_GREEN,
+            ],
+            outline_width: 2.0,
+            outline_color: Color::rgb(0.1, 0.1, 0.1),
+            selected_outline_color: Color::YELLOW,
+            intersecting_outline_color: Color::RED,
+            excess_color: Color::rgba(1.0, 0.65, 0.0, 0.7),
+            overlap_color: Color::rgba(1.0, 0.0, 0.0, 0.7),
+            hatch_spacing: 8.0,
+            piece_pattern: PiecePattern::Flat,
+            piece_texture: None,
+            magnetic_snapping: true,
+            snap_distance: 8.0,
+            collision_tolerance: 0.5,
+            momentum: true,
+            min_throw_speed: 200.0,
             min_turn_angle: core::f32::consts::PI / 200.0,
             cut_polygon: Color::TEAL,
             min_velocity: 0.5,
-            friction: 50.0,
+            // a throw at MAX_THROW_SPEED stops after 600 world units, and the pieces of a
+            // cut at CUT_PUSH_SPEED after 6. With the old 50, a throw crossed the screen
+            friction: 1200.0,
             snap_to_grid: false,
             grid_size: 20.0,
             target_size_multiplier: 1.2,
@@ -67,6 +175,8 @@ pub struct ForceMotion {
     pub area: f32,
     pub velocity: Vec2,
     pub position: Vec2,
+    // thrown by the player, as opposed to pushed by a cut
+    pub thrown: bool,
 }
 
 pub struct EntityZ {
@@ -79,6 +189,7 @@ pub struct CurrentLevel {
     pub target: String,
     pub polygon_multiplier: f32,
     pub target_multiplier: f32,
+    pub pieces: Vec<PresetPiece>,
 }
 
 impl Default for CurrentLevel {
@@ -88,6 +199,7 @@ impl Default for CurrentLevel {
             target: "004_simplicity_square_parallel".to_string(),
             polygon_multiplier: 1.0,
             target_multiplier: 1.1,
+            pieces: Vec::new(),
         }
     }
 }
@@ -121,9 +233,30 @@ pub struct Ghost;
 #[derive(Component)]
 pub struct Rotating {
     pub starting_angle: f32,
+    // starting angle of the grabbed polygon, which drives the rotation of its group
+    pub group_angle: f32,
+    // the polygon turns around the center of mass of its group
+    pub center: Vec2,
+    pub starting_pos: Vec2,
     // pub mouse_vec: Vec2,
 }
 
+// Selected polygons that are moved together as one rigid body. Their relative positions do
+// not change, so the collisions between them are not tested again: `overlapping` are the
+// other members this polygon was overlapping when the group was grabbed.
+#[derive(Component)]
+pub struct Grouped {
+    // the polygon that was grabbed, the same for all the members of the group
+    pub group: Entity,
+    pub overlapping: Vec<Entity>,
+}
+
+// rectangle dragged from `start` to the cursor, selects the polygons whose center is inside
+#[derive(Component)]
+pub struct SelectionBox {
+    pub start: Vec2,
+}
+
 // #[derive(Component)]
 // pub struct Rotating {
 //     pub starting_angle: f32,
@@ -138,6 +271,8 @@ pub struct MaybeRotating {
 #[derive(Component)]
 pub struct Translating {
     pub starting_pos: Vec2,
+    // smoothed velocity of the drag, kept by the polygon when it is thrown
+    pub velocity: Vec2,
 }
 
 #[derive(Component)]
@@ -145,6 +280,12 @@ pub struct Target {
     pub path: Path,
 }
 
+// a polygon of the level that does not move, and that the polygons must not overlap
+#[derive(Component)]
+pub struct Obstacle {
+    pub path: Path,
+}
+
 pub struct PolyIsInsideTarget;
 
 pub struct CheckPolyInsideTarget {
@@ -174,12 +315,96 @@ pub struct SpawnTargetKeepTarget {
     pub target_multiplier: f32,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RotationRule {
+    Free,
+    Forbidden,
+    // the angle of a polygon always is a multiple of this angle, in radians
+    Snap(f32),
+}
+
+//
+//
+// Movement rules of a level. They are copied into the LevelRules resource when the level
+// is spawned, and enforced by the input systems.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelRules {
+    pub rotation: RotationRule,
+    pub mirroring: bool,
+}
+
+impl Default for LevelRules {
+    fn default() -> Self {
+        Self {
+            rotation: RotationRule::Free,
+            mirroring: false,
+        }
+    }
+}
+
+impl LevelRules {
+    pub fn can_rotate(&self) -> bool {
+        self.rotation != RotationRule::Forbidden
+    }
+
+    // closest allowed angle
+    pub fn snap_angle(&self, angle: f32) -> f32 {
+        match self.rotation {
+            RotationRule::Snap(step) if step > 0.0 => (angle / step).round() * step,
+            _ => angle,
+        }
+    }
+
+    // smallest rotation of a single step (mouse wheel, keyboard, gamepad)
+    pub fn rotation_step(&self, min_turn_angle: f32) -> f32 {
+        match self.rotation {
+            RotationRule::Free => min_turn_angle,
+            RotationRule::Forbidden => 0.0,
+            RotationRule::Snap(step) => step,
+        }
+    }
+
+    // angle after `dir` rotation steps. With a snapping rule, a step turns by exactly one
+    // snap angle, whatever the magnitude of `dir`
+    pub fn rotate(&self, angle: f32, dir: f32, min_turn_angle: f32) -> f32 {
+        let step = self.rotation_step(min_turn_angle);
+        match self.rotation {
+            RotationRule::Snap(_) => self.snap_angle(angle + dir.signum() * step),
+            _ => angle + dir * step,
+        }
+    }
+}
+
+// a polygon placed by the level itself, at its starting position
+#[derive(Clone, Debug)]
+pub struct PresetPiece {
+    pub polygon: String,
+    pub transform: Transform,
+}
+
+impl PresetPiece {
+    // angle in degrees
+    pub fn new(polygon: &str, x: f32, y: f32, angle: f32) -> Self {
+        let mut rng = thread_rng();
+        Self {
+            polygon: polygon.to_string(),
+            transform: Transform::from_translation(Vec3::new(x, y, rng.gen::<f32>()))
+                .with_rotation(Quat::from_rotation_z(angle.to_radians())),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct SpawnLevel {
     pub polygon: String,
     pub target: String,
     pub target_multiplier: f32,
     pub number_of_cuts: usize,
+    pub rules: LevelRules,
+    // when not empty, these are spawned instead of `polygon`
+    pub pieces: Vec<PresetPiece>,
+    // their transforms are relative to the center of the target
+    pub obstacles: Vec<PresetPiece>,
 }
 
 impl SpawnLevel {
@@ -189,6 +414,9 @@ impl SpawnLevel {
             target: target.to_string(),
             target_multiplier: TARGET_MULT,
             number_of_cuts: 3,
+            rules: LevelRules::default(),
+            pieces: Vec::new(),
+            obstacles: Vec::new(),
         }
     }
 
@@ -198,6 +426,9 @@ impl SpawnLevel {
             target: target.to_string(),
             target_multiplier: TARGET_MULT,
             number_of_cuts,
+            rules: LevelRules::default(),
+            pieces: Vec::new(),
+            obstacles: Vec::new(),
         }
     }
 
@@ -207,22 +438,134 @@ impl SpawnLevel {
             target: target.to_string(),
             target_multiplier: mult,
             number_of_cuts,
+            rules: LevelRules::default(),
+            pieces: Vec::new(),
+            obstacles: Vec::new(),
+        }
+    }
+
+    //
+    //
+    // Tangram level: the pieces are given and cannot be cut, they only have to be
+    // arranged inside the target
+    pub fn tangram(target: &str, mult: f32, pieces: Vec<PresetPiece>) -> Self {
+        Self {
+            polygon: "".to_string(),
+            target: target.to_string(),
+            target_multiplier: mult,
+            number_of_cuts: 0,
+            rules: LevelRules::default(),
+            pieces,
+            obstacles: Vec::new(),
         }
     }
+
+    // position relative to the center of the target, angle in degrees
+    pub fn with_obstacle(mut self, polygon: &str, x: f32, y: f32, angle: f32) -> Self {
+        self.obstacles.push(PresetPiece::new(polygon, x, y, angle));
+        self
+    }
+
+    pub fn is_tangram(&self) -> bool {
+        !self.pieces.is_empty()
+    }
+
+    pub fn no_rotation(mut self) -> Self {
+        self.rules.rotation = RotationRule::Forbidden;
+        self
+    }
+
+    // angle in degrees
+    pub fn snap_rotation(mut self, angle: f32) -> Self {
+        self.rules.rotation = RotationRule::Snap(angle.to_radians());
+        self
+    }
+
+    pub fn with_mirroring(mut self) -> Self {
+        self.rules.mirroring = true;
+        self
+    }
 }
 
 pub struct RemainingCuts {
     pub remaining: usize,
 }
 
-pub struct PerformedCut;
+// number of flips since the level was spawned
+#[derive(Default)]
+pub struct FlipCount {
+    pub flips: usize,
+}
+
+// area of the pieces outside the target, in square pixels, see update_overlays(..)
+#[derive(Default)]
+pub struct ExcessArea {
+    pub area: f32,
+}
+
+// one polygon was split by a cut. `source` is despawned, `pieces` are the new polygons.
+// A cut across several polygons sends one event per polygon, all with the same `cut`
+pub struct CutApplied {
+    pub cut: Entity,
+    pub source: Entity,
+    pub pieces: Vec<Entity>,
+}
+
+// the cuts that split something this frame, one entry per cut
+pub fn applied_cuts(cut_applied_event_reader: &mut EventReader<CutApplied>) -> Vec<Entity> {
+    let mut cuts: Vec<Entity> = cut_applied_event_reader.iter().map(|c| c.cut).collect();
+    cuts.dedup();
+    cuts
+}
+
+// a cut was made but did not split any polygon
+pub struct CutRejected {
+    pub reason: InvalidCut,
+}
+
+#[derive(Debug, Clone)]
+pub struct LevelStats {
+    pub polygon: String,
+    pub target: String,
+    pub pieces: usize,
+    pub remaining_cuts: usize,
+    pub flips: usize,
+}
 
-pub struct HasWonLevelEvent;
+pub struct LevelWon {
+    pub stats: LevelStats,
+}
 
 pub struct TestWinEvent;
 
 pub struct DeleteEvent;
 
+// why a cut segment does not cut anything
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InvalidCut {
+    OddIntersections { count: usize },
+    MissesEveryPiece,
+    CutsNothing,
+}
+
+impl std::fmt::Display for InvalidCut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidCut::OddIntersections { count } => {
+                write!(f, "the cut crosses the edges {} times", count)
+            }
+            InvalidCut::MissesEveryPiece => write!(f, "the cut misses every piece"),
+            InvalidCut::CutsNothing => write!(f, "the cut does not cut anything"),
+        }
+    }
+}
+
+// State of the cut being made, for the UI. `invalid` is None while no cut is being made
+#[derive(Default)]
+pub struct CutPreview {
+    pub invalid: Option<InvalidCut>,
+}
+
 pub struct TestCollisionEvent(pub Entity);
 
 pub type MeshId = u64;
@@ -239,6 +582,10 @@ pub struct MeshMeta {
 }
 
 impl MeshMeta {
+    pub fn area(&self) -> f32 {
+        approximate_signed_area(0.1, &self.path).abs()
+    }
+
     //
     //
     //
@@ -298,6 +645,24 @@ impl MeshMeta {
         self.points = new_points;
     }
 
+    // mirrors the polygon across its local x axis, which goes through the center of mass.
+    // The points are reversed so that the winding stays the one the cut code expects
+    pub fn flip(&mut self) {
+        let mut flipped: Vec<Vec2> = self
+            .path
+            .iter()
+            .filter_map(|seg| match seg {
+                lyon::path::Event::Begin { at } => Some(Vec2::new(at.x, -at.y)),
+                lyon::path::Event::Line { from: _, to } => Some(Vec2::new(to.x, -to.y)),
+                _ => None,
+            })
+            .collect();
+        flipped.reverse();
+
+        self.path = build_path_from_points(&flipped, 1.0);
+        self.points = flipped;
+    }
+
     // Test whether the mouse is inside the polygon
     pub fn hit_test(&self, pos: &Point, transform: &Transform) -> (bool, f32) {
         //
@@ -344,12 +709,47 @@ impl MeshMeta {
     //
     //
     //
-    // Test whether the path is intersecting with another path by checking all intersecting segments
+    // Test whether the path overlaps another path, which is the case when their edges cross or
+    // when one of them is entirely inside the other
+    pub fn precise_overlap_test(
+        &self,
+        other: &Path,
+        transform: &Transform,
+        other_transform: &Transform,
+        tolerance: f32,
+    ) -> bool {
+        if self.precise_intersect_test(other, transform, other_transform, tolerance) {
+            return true;
+        }
+
+        let (transformed_path, _) = transform_path(&self.path, transform);
+        let (transformed_other, _) = transform_path(other, other_transform);
+
+        // a vertex touching the boundary does not make an overlap
+        let is_inside = |p: Point, path: &Path| {
+            hit_test_path(&p, path.iter(), FillRule::EvenOdd, 0.1)
+                && distance_to_path(p, path) > tolerance
+        };
+
+        transformed_other
+            .iter()
+            .any(|seg| is_inside(seg.from(), &transformed_path))
+            || transformed_path
+                .iter()
+                .any(|seg| is_inside(seg.from(), &transformed_other))
+    }
+
+    //
+    //
+    //
+    // Test whether the path is intersecting with another path by checking all intersecting segments.
+    // With a tolerance, segments that only touch or barely cross are not intersecting
     pub fn precise_intersect_test(
         &self,
         other: &Path,
         transform: &Transform,
         other_transform: &Transform,
+        tolerance: f32,
     ) -> bool {
         let (transformed_path, _) = transform_path(&self.path, transform);
         let (transformed_other, _) = transform_path(other, other_transform);
@@ -364,7 +764,11 @@ impl MeshMeta {
                     start: other_seg.from(),
                     end: other_seg.to(),
                 };
-                if segment.intersect(other_segment).is_some() {
+                if tolerance > 0.0 {
+                    if segment.crosses(other_segment, tolerance) {
+                        return true;
+                    }
+                } else if segment.intersect(other_segment).is_some() {
                     return true;
                 }
             }
@@ -464,6 +868,48 @@ impl Segment {
 
         return None;
     }
+
+    // signed distance of a point to the line through the segment
+    fn side(&self, p: Point) -> f32 {
+        let dir = self.end - self.start;
+        let length = dir.length();
+        if length == 0.0 {
+            return 0.0;
+        }
+        dir.cross(p - self.start) / length
+    }
+
+    // true when both segments go more than `tolerance` through each other
+    pub fn crosses(&self, other: Segment, tolerance: f32) -> bool {
+        let opposite = |d1: f32, d2: f32| {
+            (d1 > tolerance && d2 < -tolerance) || (d1 < -tolerance && d2 > tolerance)
+        };
+        opposite(self.side(other.start), self.side(other.end))
+            && opposite(other.side(self.start), other.side(self.end))
+    }
+
+    pub fn distance_to(&self, p: Point) -> f32 {
+        let dir = self.end - self.start;
+        let length_squared = dir.square_length();
+        if length_squared == 0.0 {
+            return (p - self.start).length();
+        }
+        let t = ((p - self.start).dot(dir) / length_squared).clamp(0.0, 1.0);
+        (p - (self.start + dir * t)).length()
+    }
+}
+
+// distance of a point to the closest edge of a path
+pub fn distance_to_path(p: Point, path: &Path) -> f32 {
+    path.iter()
+        .map(|seg| {
+            Segment {
+                start: seg.from(),
+                end: seg.to(),
+            }
+            .distance_to(p)
+        })
+        .fold(f32::MAX, f32::min)
 }
 
 pub struct SegmentMeta {
@@ -494,6 +940,24 @@ pub fn get_segment_meta(segment: Segment) -> SegmentMeta {
     }
 }
 
+pub fn rotate_around(v: Vec2, center: Vec2, angle: f32) -> Vec2 {
+    let (sin, cos) = angle.sin_cos();
+    let r = v - center;
+    center + Vec2::new(r.x * cos - r.y * sin, r.x * sin + r.y * cos)
+}
+
+// combined center of mass of polygons given by (center of mass, area)
+pub fn group_center(members: &[(Vec2, f32)]) -> Vec2 {
+    let total_area: f32 = members.iter().map(|(_, area)| area).sum();
+    if total_area <= 0.0 {
+        return members.first().map_or(Vec2::ZERO, |(pos, _)| *pos);
+    }
+    members
+        .iter()
+        .fold(Vec2::ZERO, |acc, (pos, area)| acc + *pos * *area)
+        / total_area
+}
+
 // The path is by default centered at the origin, so we need to translate it to the
 // position of the entity.
 pub fn transform_path(path: &Path, transform: &Transform) -> (Path, f32) {
@@ -647,6 +1111,42 @@ pub fn make_polygon_mesh(path: &Path, shift_com: bool) -> (Mesh, Vec2) {
     return (mesh, center_of_mass);
 }
 
+//
+//
+// Maps the local coordinates of a piece to the coordinates of the pattern, which are the
+// local coordinates of the piece it was cut from. The pattern of the pieces is then
+// continuous across the cut. Pieces without a PatternFrame use their local coordinates.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PatternFrame(pub Affine2);
+
+impl Default for PatternFrame {
+    fn default() -> Self {
+        Self(Affine2::IDENTITY)
+    }
+}
+
+// the 2d part of a Transform
+pub fn transform_affine2(transform: &Transform) -> Affine2 {
+    let matrix = transform.compute_matrix();
+    Affine2::from_cols(
+        matrix.x_axis.truncate().truncate(),
+        matrix.y_axis.truncate().truncate(),
+        matrix.w_axis.truncate().truncate(),
+    )
+}
+
+// replaces the uvs of a mesh made by make_polygon_mesh(..), which are its positions
+pub fn set_pattern_uvs(mesh: &mut Mesh, frame: &PatternFrame) {
+    let uvs: Vec<[f32; 2]> = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float32x3(positions)) => positions
+            .iter()
+            .map(|p| frame.0.transform_point2(Vec2::new(p[0], p[1])).to_array())
+            .collect(),
+        _ => return,
+    };
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+}
+
 pub fn shift_to_center_of_mass(v: &Vec<Vec2>) -> Vec<Vec2> {
     let mut center_of_mass = Vec2::ZERO;
     for pos in v.iter() {
@@ -713,22 +1213,30 @@ pub fn spawn_poly(
         });
     }
 
+    // the pieces of a tangram level come as SpawnPolyKeepPoly, see spawn_level_pieces(..)
+    let mut is_new_level = false;
     for SpawnLevel {
         polygon,
         target: _,
         target_multiplier: _,
         number_of_cuts: _,
+        rules: _,
+        pieces,
+        obstacles: _,
     } in spawn_level_event_reader.iter()
     {
-        poly_vec.push(SpawnPoly {
-            polygon: polygon.clone(),
-            polygon_multiplier: 1.0,
-        });
+        is_new_level = true;
+        if pieces.is_empty() {
+            poly_vec.push(SpawnPoly {
+                polygon: polygon.clone(),
+                polygon_multiplier: 1.0,
+            });
+        }
     }
 
-    if poly_vec.len() > 0 {
+    if poly_vec.len() > 0 || is_new_level {
         for entity in query.iter() {
-            commands.entity(entity).despawn();
+            commands.entity(entity).despawn_recursive();
         }
     }
 
@@ -786,12 +1294,9 @@ pub fn spawn_poly(
             //     felt: felt_mat,
             // });
 
-            let mat_handle = fill_materials.add(FillMesh2dMaterial {
-                color: globals.polygon_color.into(),
-                show_com: 0.0,
-                selected: 0.0,
-                is_intersecting: 0.0,
-            });
+            let mat_handle = fill_materials.add(
+                FillMesh2dMaterial::new(globals.polygon_color, &globals).with_pattern(&globals),
+            );
 
             let path_translation =
                 lyon::geom::Translation::new(-center_of_mass.x, -center_of_mass.y);
@@ -831,15 +1336,8 @@ pub fn spawn_poly(
                 })
                 .id();
 
-            let ghost_mat_handle = fill_materials.add(FillMesh2dMaterial {
-                // usually globals.ghost_color, but the list of arguments in spawn_poly(..)
-                // is becoming too long
-                color: globals.ghost_color.into(),
-
-                show_com: 0.0,
-                selected: 0.0,
-                is_intersecting: 0.0,
-            });
+            let ghost_mat_handle =
+                fill_materials.add(FillMesh2dMaterial::new(globals.ghost_color, &globals));
 
             let mut ghost_transform = transform;
             ghost_transform.translation.z = -10.0;
@@ -891,6 +1389,9 @@ pub fn spawn_target(
         target,
         target_multiplier,
         number_of_cuts: _,
+        rules: _,
+        pieces: _,
+        obstacles: _,
     } in spawn_level_event_reader.iter()
     {
         target_vec.push(SpawnTarget {
@@ -901,7 +1402,7 @@ pub fn spawn_target(
 
     if target_vec.len() > 0 {
         for entity in query.iter() {
-            commands.entity(entity).despawn();
+            commands.entity(entity).despawn_recursive();
         }
     }
 
@@ -949,12 +1450,8 @@ pub fn spawn_target(
 
             let (mesh, _center_of_mass) = make_polygon_mesh(&built_path, false);
 
-            let mat_handle = fill_materials.add(FillMesh2dMaterial {
-                color: globals.target_color.into(),
-                show_com: 0.0,
-                selected: 0.0,
-                is_intersecting: 0.0,
-            });
+            let mat_handle =
+                fill_materials.add(FillMesh2dMaterial::new(globals.target_color, &globals));
 
             // let path_translation =
             //     lyon::geom::Translation::new(-center_of_mass.x, -center_of_mass.y);
@@ -964,7 +1461,7 @@ pub fn spawn_target(
             // let id = rng.gen::<u64>();
             // let z = rng.gen::<f32>();
 
-            let transform = Transform::from_translation(Vec2::new(300.0, 0.0).extend(0.0));
+            let transform = Transform::from_translation(TARGET_POSITION.extend(0.0));
 
             let mesh_handle = meshes.add(mesh);
 
@@ -995,6 +1492,54 @@ pub fn spawn_target(
     }
 }
 
+//
+//
+//
+// spawns the obstacles of a level, and removes those of the previous level
+pub fn spawn_obstacles(
+    mut commands: Commands,
+    poly_raw_map: Res<LoadedPolygonsRaw>,
+    query: Query<Entity, With<Obstacle>>,
+    mut obstacle_materials: ResMut<Assets<ObstacleMesh2dMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut spawn_level_event_reader: EventReader<SpawnLevel>,
+    globals: Res<Globals>,
+) {
+    if let Some(level) = spawn_level_event_reader.iter().last() {
+        for entity in query.iter() {
+            commands.entity(entity).despawn();
+        }
+
+        for obstacle in level.obstacles.iter() {
+            if let Some(save_format_mesh_meta) = poly_raw_map.polygons.get(&obstacle.polygon) {
+                let points = shift_to_center_of_mass(&save_format_mesh_meta.points);
+                let built_path = build_path_from_points(&points, 1.0);
+
+                let (mesh, _center_of_mass) = make_polygon_mesh(&built_path, false);
+
+                let mat_handle = obstacle_materials.add(ObstacleMesh2dMaterial {
+                    color: globals.obstacle_color.into(),
+                });
+
+                let mut transform = obstacle.transform;
+                transform.translation =
+                    (TARGET_POSITION + transform.translation.truncate()).extend(OBSTACLE_Z);
+
+                commands
+                    .spawn_bundle(MaterialMesh2dBundle {
+                        mesh: Mesh2dHandle(meshes.add(mesh)),
+                        material: mat_handle,
+                        transform,
+                        ..default()
+                    })
+                    .insert(Obstacle { path: built_path });
+            } else {
+                warn!("obstacle {} not found", obstacle.polygon);
+            }
+        }
+    }
+}
+
 // turns a polygon into a target by removing the polygon component, adding the target component
 // and changing its color
 pub fn turn_poly_into_target(
@@ -1026,6 +1571,7 @@ pub fn turn_poly_into_target(
             // change color
             if let Some(material) = fill_materials.get_mut(&fill_mat_handle) {
                 material.color = globals.target_color.into();
+                material.set_pattern(PiecePattern::Flat, None);
             }
             //
             //
@@ -1041,3 +1587,33 @@ pub fn turn_poly_into_target(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn quarter_turn_around_a_center() {
+        let turned = rotate_around(Vec2::new(3.0, 1.0), Vec2::new(1.0, 1.0), FRAC_PI_2);
+        assert!((turned - Vec2::new(1.0, 3.0)).length() < 1e-5, "{}", turned);
+
+        let center = Vec2::new(-2.0, 5.0);
+        assert_eq!(rotate_around(center, center, 1.0), center);
+    }
+
+    #[test]
+    fn group_center_is_weighted_by_area() {
+        let members = [(Vec2::new(0.0, 0.0), 1.0), (Vec2::new(4.0, 0.0), 3.0)];
+        assert_eq!(group_center(&members), Vec2::new(3.0, 0.0));
+    }
+
+    #[test]
+    fn group_center_without_area() {
+        assert_eq!(group_center(&[]), Vec2::ZERO);
+        assert_eq!(
+            group_center(&[(Vec2::new(2.0, 1.0), 0.0), (Vec2::new(5.0, 5.0), 0.0)]),
+            Vec2::new(2.0, 1.0)
+        );
+    }
+}
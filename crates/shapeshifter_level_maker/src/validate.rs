@@ -0,0 +1,416 @@
+use crate::util::*;
+
+use bevy::prelude::*;
+
+use lyon::tessellation::math::Point;
+
+use std::io::{Read, Write};
+use std::path::Path as FilePath;
+
+// edges shorter than this are considered degenerate
+pub const MIN_EDGE_LENGTH: f32 = 0.5;
+
+// a vertex whose two edges make an angle smaller than this (in radians) is a sliver spike
+pub const MIN_SLIVER_ANGLE: f32 = 0.035;
+
+// a vertex whose two edges deviate from a straight line by less than this is near-collinear
+pub const MIN_TURN_ANGLE: f32 = 0.005;
+
+// tolerance for the Douglas-Peucker simplification, in the units of the .pts files
+pub const SIMPLIFY_EPSILON: f32 = 0.25;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShapeIssue {
+    TooFewPoints { count: usize },
+    ZeroLengthEdge { index: usize },
+    SelfIntersection { edge_a: usize, edge_b: usize },
+    WrongWinding,
+    Sliver { index: usize, angle: f32 },
+    NearCollinear { index: usize },
+}
+
+impl ShapeIssue {
+    // issues that cannot be fixed by repair_issues(..)
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            ShapeIssue::TooFewPoints { .. } | ShapeIssue::SelfIntersection { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for ShapeIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShapeIssue::TooFewPoints { count } => write!(f, "only {} points", count),
+            ShapeIssue::ZeroLengthEdge { index } => write!(f, "zero-length edge at {}", index),
+            ShapeIssue::SelfIntersection { edge_a, edge_b } => {
+                write!(f, "edges {} and {} intersect", edge_a, edge_b)
+            }
+            ShapeIssue::WrongWinding => write!(f, "clockwise winding"),
+            ShapeIssue::Sliver { index, angle } => {
+                write!(f, "sliver at {} ({:.2} deg)", index, angle.to_degrees())
+            }
+            ShapeIssue::NearCollinear { index } => write!(f, "near-collinear vertex at {}", index),
+        }
+    }
+}
+
+// shoelace formula. Positive for counter-clockwise polygons
+pub fn signed_area(points: &Vec<Vec2>) -> f32 {
+    let n = points.len();
+    let mut area = 0.0;
+    for k in 0..n {
+        let a = points[k];
+        let b = points[(k + 1) % n];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area / 2.0
+}
+
+// angle between the incoming and outgoing edges at vertex k (PI for a straight line)
+fn vertex_angle(points: &Vec<Vec2>, k: usize) -> Option<f32> {
+    let n = points.len();
+    let prev = points[(k + n - 1) % n];
+    let curr = points[k];
+    let next = points[(k + 1) % n];
+
+    let v1 = prev - curr;
+    let v2 = next - curr;
+    if v1.length() < MIN_EDGE_LENGTH || v2.length() < MIN_EDGE_LENGTH {
+        return None;
+    }
+
+    Some(v1.angle_between(v2).abs())
+}
+
+//
+//
+// Lists everything that may break the tessellation or the cuts. The points are the
+// ones found in the .pts files: a closed polygon without a repeated last point.
+pub fn validate_points(points: &Vec<Vec2>) -> Vec<ShapeIssue> {
+    let mut issues = Vec::new();
+    let n = points.len();
+
+    if n < 3 {
+        issues.push(ShapeIssue::TooFewPoints { count: n });
+        return issues;
+    }
+
+    for k in 0..n {
+        if (points[(k + 1) % n] - points[k]).length() < MIN_EDGE_LENGTH {
+            issues.push(ShapeIssue::ZeroLengthEdge { index: k });
+        }
+    }
+
+    for k in 0..n {
+        if let Some(angle) = vertex_angle(points, k) {
+            if angle < MIN_SLIVER_ANGLE {
+                issues.push(ShapeIssue::Sliver { index: k, angle });
+            } else if std::f32::consts::PI - angle < MIN_TURN_ANGLE {
+                issues.push(ShapeIssue::NearCollinear { index: k });
+            }
+        }
+    }
+
+    //
+    //
+    // test every pair of non-adjacent edges. A repeated vertex would make the edges on
+    // both sides of it touch, so the indices are those of the deduplicated polygon
+    let points = &dedupe_points(points);
+    let n = points.len();
+    for i in 0..n {
+        for j in (i + 2)..n {
+            if i == 0 && j == n - 1 {
+                continue;
+            }
+            let seg_a = Segment {
+                start: Point::new(points[i].x, points[i].y),
+                end: Point::new(points[(i + 1) % n].x, points[(i + 1) % n].y),
+            };
+            let seg_b = Segment {
+                start: Point::new(points[j].x, points[j].y),
+                end: Point::new(points[(j + 1) % n].x, points[(j + 1) % n].y),
+            };
+            if seg_a.intersect(seg_b).is_some() {
+                issues.push(ShapeIssue::SelfIntersection {
+                    edge_a: i,
+                    edge_b: j,
+                });
+            }
+        }
+    }
+
+    if signed_area(points) < 0.0 {
+        issues.push(ShapeIssue::WrongWinding);
+    }
+
+    issues
+}
+
+// removes consecutive points that are closer than MIN_EDGE_LENGTH, including the closing edge
+pub fn dedupe_points(points: &Vec<Vec2>) -> Vec<Vec2> {
+    let mut deduped: Vec<Vec2> = Vec::new();
+    for pt in points.iter() {
+        if let Some(last) = deduped.last() {
+            if (*pt - *last).length() < MIN_EDGE_LENGTH {
+                continue;
+            }
+        }
+        deduped.push(*pt);
+    }
+
+    while deduped.len() > 1 && (*deduped.last().unwrap() - deduped[0]).length() < MIN_EDGE_LENGTH {
+        deduped.pop();
+    }
+
+    deduped
+}
+
+// removes spikes whose two edges fold back onto each other
+pub fn remove_slivers(points: &Vec<Vec2>) -> Vec<Vec2> {
+    let mut pts = points.clone();
+    let mut k = 0;
+    while k < pts.len() && pts.len() > 3 {
+        match vertex_angle(&pts, k) {
+            Some(angle) if angle < MIN_SLIVER_ANGLE => {
+                pts.remove(k);
+                k = 0;
+            }
+            _ => k += 1,
+        }
+    }
+    pts
+}
+
+fn douglas_peucker(points: &[Vec2], epsilon: f32) -> Vec<Vec2> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let first = points[0];
+    let last = points[points.len() - 1];
+
+    let mut max_distance = 0.0;
+    let mut max_index = 0;
+    for (k, pt) in points.iter().enumerate().take(points.len() - 1).skip(1) {
+        let distance = crate::poly::distance_from_point_to_segment(*pt, (first, last));
+        if distance > max_distance {
+            max_distance = distance;
+            max_index = k;
+        }
+    }
+
+    if max_distance > epsilon {
+        let mut left = douglas_peucker(&points[..max_index + 1], epsilon);
+        let right = douglas_peucker(&points[max_index..], epsilon);
+        left.pop();
+        left.extend(right);
+        left
+    } else {
+        vec![first, last]
+    }
+}
+
+//
+//
+// Douglas-Peucker on a closed polygon. The polygon is split at the first point and at the
+// point furthest away from it, and each half is simplified separately.
+pub fn simplify_points(points: &Vec<Vec2>, epsilon: f32) -> Vec<Vec2> {
+    if points.len() <= 3 {
+        return points.clone();
+    }
+
+    let mut far_index = 1;
+    let mut far_distance = 0.0;
+    for (k, pt) in points.iter().enumerate() {
+        let distance = (*pt - points[0]).length();
+        if distance > far_distance {
+            far_distance = distance;
+            far_index = k;
+        }
+    }
+
+    let mut first_half = douglas_peucker(&points[..far_index + 1], epsilon);
+    let mut second_half: Vec<Vec2> = points[far_index..].to_vec();
+    second_half.push(points[0]);
+    let second_half = douglas_peucker(&second_half, epsilon);
+
+    first_half.pop();
+    first_half.extend(second_half.iter().take(second_half.len() - 1));
+
+    if first_half.len() < 3 {
+        return points.clone();
+    }
+    first_half
+}
+
+// removes the vertices that lie on the straight line between their neighbours
+pub fn remove_collinear(points: &Vec<Vec2>) -> Vec<Vec2> {
+    let mut pts = points.clone();
+    let mut k = 0;
+    while k < pts.len() && pts.len() > 3 {
+        match vertex_angle(&pts, k) {
+            Some(angle) if std::f32::consts::PI - angle < MIN_TURN_ANGLE => {
+                pts.remove(k);
+            }
+            _ => k += 1,
+        }
+    }
+    pts
+}
+
+// makes the polygon counter-clockwise
+pub fn fix_winding(points: &Vec<Vec2>) -> Vec<Vec2> {
+    if signed_area(points) < 0.0 {
+        points.iter().rev().cloned().collect()
+    } else {
+        points.clone()
+    }
+}
+
+//
+//
+// Applies only the repairs of the issues that were found, so that a polygon with e.g. a
+// clockwise winding is reversed but not simplified
+pub fn repair_issues(points: &Vec<Vec2>, issues: &[ShapeIssue]) -> Vec<Vec2> {
+    let found = |f: fn(&ShapeIssue) -> bool| issues.iter().any(f);
+
+    let mut pts = points.clone();
+    if found(|issue| matches!(issue, ShapeIssue::ZeroLengthEdge { .. })) {
+        pts = dedupe_points(&pts);
+    }
+    if pts.len() < 3 {
+        return pts;
+    }
+    if found(|issue| matches!(issue, ShapeIssue::Sliver { .. })) {
+        pts = remove_slivers(&pts);
+    }
+    if found(|issue| matches!(issue, ShapeIssue::NearCollinear { .. })) {
+        pts = remove_collinear(&pts);
+    }
+    if found(|issue| matches!(issue, ShapeIssue::WrongWinding)) {
+        pts = fix_winding(&pts);
+    }
+    pts
+}
+
+// applies all automatic repairs, including the simplification, for the --fix option of
+// the validate_pts example. Self-intersections are left as they are
+pub fn repair_points(points: &Vec<Vec2>) -> Vec<Vec2> {
+    let deduped = dedupe_points(points);
+    if deduped.len() < 3 {
+        return deduped;
+    }
+    let without_slivers = remove_slivers(&deduped);
+    let simplified = simplify_points(&without_slivers, SIMPLIFY_EPSILON);
+    fix_winding(&simplified)
+}
+
+pub fn read_pts_file(path: &FilePath) -> Option<SaveMeshMeta2> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+pub fn write_pts_file(path: &FilePath, save_mesh_meta: &SaveMeshMeta2) {
+    let serialized = serde_json::to_string_pretty(save_mesh_meta).unwrap();
+    let mut output = std::fs::File::create(path).unwrap();
+    output.write_all(serialized.as_bytes()).unwrap();
+}
+
+//
+//
+// Runs at load time on every polygon of the LoadedPolygonsRaw map. Repairable polygons
+// get the repairs of their issues, the others are only reported.
+pub fn validate_and_repair_polygon(name: &str, save_mesh_meta: &mut SaveMeshMeta2) {
+    let issues = validate_points(&save_mesh_meta.points);
+    if issues.is_empty() {
+        return;
+    }
+
+    for issue in issues.iter() {
+        if issue.is_fatal() {
+            warn!("polygon {}: {}", name, issue);
+        } else {
+            info!("polygon {}: {}", name, issue);
+        }
+    }
+
+    if issues.iter().any(|issue| issue.is_fatal()) {
+        return;
+    }
+
+    save_mesh_meta.points = repair_issues(&save_mesh_meta.points, &issues);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(contents: &str) -> SaveMeshMeta2 {
+        serde_json::from_str(contents).unwrap()
+    }
+
+    #[test]
+    fn self_intersecting_shape_is_reported_and_left_as_is() {
+        let mut shape = fixture(include_str!("../tests/fixtures/self_intersecting.pts"));
+        let original = shape.points.clone();
+
+        let issues = validate_points(&shape.points);
+        assert!(issues.contains(&ShapeIssue::SelfIntersection {
+            edge_a: 0,
+            edge_b: 2
+        }));
+        assert!(issues.iter().any(|issue| issue.is_fatal()));
+
+        validate_and_repair_polygon("self_intersecting", &mut shape);
+        assert_eq!(shape.points, original);
+    }
+
+    #[test]
+    fn duplicate_vertex_is_removed() {
+        let mut shape = fixture(include_str!("../tests/fixtures/duplicate_vertex.pts"));
+
+        let issues = validate_points(&shape.points);
+        assert_eq!(issues, vec![ShapeIssue::ZeroLengthEdge { index: 1 }]);
+
+        validate_and_repair_polygon("duplicate_vertex", &mut shape);
+        assert_eq!(
+            shape.points,
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(100.0, 0.0),
+                Vec2::new(100.0, 100.0),
+                Vec2::new(0.0, 100.0),
+            ]
+        );
+        assert!(validate_points(&shape.points).is_empty());
+    }
+
+    #[test]
+    fn reversed_winding_is_only_reversed() {
+        let mut shape = fixture(include_str!("../tests/fixtures/reversed_winding.pts"));
+        let original = shape.points.clone();
+
+        let issues = validate_points(&shape.points);
+        assert_eq!(issues, vec![ShapeIssue::WrongWinding]);
+
+        // the vertex that is almost on a line would be removed by the simplification
+        validate_and_repair_polygon("reversed_winding", &mut shape);
+        let reversed: Vec<Vec2> = original.iter().rev().cloned().collect();
+        assert_eq!(shape.points, reversed);
+        assert!(validate_points(&shape.points).is_empty());
+    }
+
+    #[test]
+    fn valid_shape_is_untouched() {
+        let mut shape = fixture(include_str!("../tests/fixtures/duplicate_vertex.pts"));
+        shape.points.remove(2);
+        let original = shape.points.clone();
+
+        validate_and_repair_polygon("square", &mut shape);
+        assert_eq!(shape.points, original);
+    }
+}
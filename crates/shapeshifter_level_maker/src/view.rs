@@ -1,7 +1,11 @@
-use bevy::prelude::*;
+use bevy::{math::Affine2, prelude::*, sprite::Mesh2dHandle};
 
+use crate::bindings::*;
+use crate::gamepad::GamepadInput;
 use crate::input::*;
 use crate::material::*;
+use crate::physics::{DRAG_VELOCITY_SMOOTHING, MAX_THROW_SPEED};
+use crate::snap::SnapPoly;
 use crate::util::*;
 
 use lyon::algorithms::hit_test::*;
@@ -9,7 +13,8 @@ use lyon::path::FillRule;
 use lyon::tessellation::math::Point;
 
 pub enum PossibleMoves {
-    Translation(Vec2),
+    Translation,
+    // angle of the grabbed polygon
     Rotation(f32),
 }
 
@@ -23,9 +28,7 @@ pub enum PossibleMoves {
 // TODO: move inputs to input
 pub fn glow_poly(
     mut commands: Commands,
-    mouse_button_input: Res<Input<MouseButton>>,
     cursor: Res<Cursor>,
-    keyboard_input: Res<Input<KeyCode>>,
     query: Query<
         (
             Entity,
@@ -33,25 +36,31 @@ pub fn glow_poly(
             &Transform,
             &MeshMeta,
             Option<Or<(&MaybeRotating, &Translating)>>,
+            Option<&Selected>,
         ),
         With<Polygon>,
     >,
     mut materials: ResMut<Assets<FillMesh2dMaterial>>,
+    mut action_event_reader: EventReader<Action>,
+    level_rules: Res<LevelRules>,
+    globals: Res<Globals>,
 ) {
-    // TODO: move these to inputs
-    let left_mouse_click = mouse_button_input.just_pressed(MouseButton::Left);
-    let right_mouse_click = mouse_button_input.just_pressed(MouseButton::Right);
+    // the chords that start a translation or a rotation are matched in direct_action(..)
+    let mut left_mouse_click = false;
+    let mut right_mouse_click = false;
+    for action in action_event_reader.iter() {
+        match action {
+            Action::MaybeTranslatePoly => left_mouse_click = true,
+            Action::MaybeRotatePoly => right_mouse_click = level_rules.can_rotate(),
+            _ => {}
+        }
+    }
     // let mut moving_entity = None;
     let mut maybe_highlight_entity = None;
 
     let mut maybe_move_entity: Option<(Entity, PossibleMoves)> = None;
 
-    let ctrl = keyboard_input.pressed(KeyCode::LControl);
-    let shift = keyboard_input.pressed(KeyCode::LShift);
-    let pressed_q = keyboard_input.pressed(KeyCode::Q);
-    let pressed_c = keyboard_input.pressed(KeyCode::C);
-
-    for (entity, material_handle, transform, mesh_meta, maybe_moving) in query.iter() {
+    for (entity, material_handle, transform, mesh_meta, maybe_moving, _) in query.iter() {
         //
         //
         //
@@ -75,14 +84,11 @@ pub fn glow_poly(
         let mut material = materials.get_mut(&material_handle).unwrap();
         material.show_com = 0.0;
 
-        if is_inside_poly && left_mouse_click && !ctrl && !shift && !pressed_q && !pressed_c {
-            maybe_move_entity = Some((
-                entity,
-                PossibleMoves::Translation(transform.translation.truncate()),
-            ));
+        if is_inside_poly && left_mouse_click {
+            maybe_move_entity = Some((entity, PossibleMoves::Translation));
         }
 
-        if is_inside_poly && right_mouse_click && !ctrl && !shift && !pressed_q && !pressed_c {
+        if is_inside_poly && right_mouse_click {
             maybe_move_entity = Some((entity, PossibleMoves::Rotation(angle)));
         }
 
@@ -93,27 +99,92 @@ pub fn glow_poly(
         }
     }
 
-    // add Rotating or Translating component to clicked entity
+    // add Rotating or Translating component to clicked entity, or to all the Selected
+    // polygons if the clicked entity is one of them
     //
 
     // TODO: prioritize higher z pos
-    if !pressed_q {
+    {
         if let Some((entity, moves)) = maybe_move_entity {
-            let (_, material_handle, _, _, _) = query.get(entity).unwrap();
+            let (_, material_handle, _, _, _, selected) = query.get(entity).unwrap();
             let mut material = materials.get_mut(&material_handle).unwrap();
-            match moves {
-                PossibleMoves::Translation(translation) => {
-                    commands.entity(entity).insert(Translating {
-                        starting_pos: translation,
+
+            let group: Vec<Entity> = if selected.is_some() {
+                query
+                    .iter()
+                    .filter(|(_, _, _, _, _, selected)| selected.is_some())
+                    .map(|(entity, _, _, _, _, _)| entity)
+                    .collect()
+            } else {
+                vec![entity]
+            };
+
+            let members: Vec<(Vec2, f32)> = group
+                .iter()
+                .map(|member| {
+                    let (_, _, transform, mesh_meta, _, _) = query.get(*member).unwrap();
+                    (transform.translation.truncate(), mesh_meta.area())
+                })
+                .collect();
+            let center = group_center(&members);
+
+            //
+            //
+            // the pairs of the group are not tested for collisions while it moves rigidly,
+            // see test_collisions(..)
+            if group.len() > 1 {
+                let tolerance = globals.touch_tolerance();
+                for member in group.iter() {
+                    let (_, _, transform, mesh_meta, _, _) = query.get(*member).unwrap();
+                    let overlapping = group
+                        .iter()
+                        .filter(|other| {
+                            let (_, _, other_transform, other_meta, _, _) =
+                                query.get(**other).unwrap();
+                            *other != member
+                                && mesh_meta.bounding_box_collide(
+                                    &other_meta.path,
+                                    transform,
+                                    other_transform,
+                                )
+                                && mesh_meta.precise_intersect_test(
+                                    &other_meta.path,
+                                    transform,
+                                    other_transform,
+                                    tolerance,
+                                )
+                        })
+                        .cloned()
+                        .collect();
+                    commands.entity(*member).insert(Grouped {
+                        group: entity,
+                        overlapping,
                     });
                 }
-                PossibleMoves::Rotation(angle) => {
-                    // commands.entity(entity).insert(MaybeRotating {
-                    //     starting_angle: angle,
-                    // });
-                    commands.entity(entity).insert(Rotating {
-                        starting_angle: angle,
-                    });
+            }
+
+            for member in group.iter() {
+                let (_, _, transform, _, _, _) = query.get(*member).unwrap();
+                let pos = transform.translation.truncate();
+                match moves {
+                    PossibleMoves::Translation => {
+                        commands.entity(*member).insert(Translating {
+                            starting_pos: pos,
+                            velocity: Vec2::ZERO,
+                        });
+                    }
+                    PossibleMoves::Rotation(angle) => {
+                        let (axis, member_angle) = transform.rotation.to_axis_angle();
+                        // commands.entity(entity).insert(MaybeRotating {
+                        //     starting_angle: angle,
+                        // });
+                        commands.entity(*member).insert(Rotating {
+                            starting_angle: axis.z * member_angle,
+                            group_angle: angle,
+                            center,
+                            starting_pos: pos,
+                        });
+                    }
                 }
             }
 
@@ -121,7 +192,7 @@ pub fn glow_poly(
         } else if let Some(highlighted_entity) = maybe_highlight_entity {
             //
             // if no movement is happening, highlight one entity that is hovered over
-            if let Ok((_, material_handle, _, _, _)) = query.get(highlighted_entity) {
+            if let Ok((_, material_handle, _, _, _, _)) = query.get(highlighted_entity) {
                 let mut material = materials.get_mut(&material_handle).unwrap();
                 material.show_com = 1.0;
             }
@@ -245,13 +316,20 @@ pub fn glow_poly(
 pub fn transform_poly(
     mut commands: Commands,
     mouse_button_input: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_input: Res<GamepadInput>,
+    bindings: Res<Bindings>,
     cursor: Res<Cursor>,
+    time: Res<Time>,
+    level_rules: Res<LevelRules>,
     mut queries: ParamSet<(
         Query<(Entity, &mut Transform, &Rotating, &MeshMeta), With<Polygon>>,
-        Query<(Entity, &mut Transform, &Translating), With<Polygon>>,
+        Query<(Entity, &mut Transform, &mut Translating), With<Polygon>>,
     )>,
-    // globals: Res<Globals>,
+    globals: Res<Globals>,
     mut collision_test_writer: EventWriter<TestCollisionEvent>,
+    mut snap_writer: EventWriter<SnapPoly>,
+    mut action_event_reader: EventReader<Action>,
 ) {
     for (_, mut transform, rotating, _) in queries.p0().iter_mut() {
         // println!("rotating");
@@ -259,48 +337,121 @@ pub fn transform_poly(
         //
         //
         // latch the final angle to fixed angles at every pi/25 radians
-        let free_angle = -diag_mouse_dist * 0.0035 + rotating.starting_angle;
-
-        let angle = free_angle;
-        transform.rotation = Quat::from_rotation_z(angle);
+        let free_angle = -diag_mouse_dist * 0.0035 + rotating.group_angle;
+
+        let angle = if let Some(twist) = cursor.touch_twist {
+            twist + rotating.group_angle
+        } else {
+            free_angle
+        };
+
+        // the whole group turns by the rotation of the grabbed polygon
+        let delta_angle = level_rules.snap_angle(angle) - rotating.group_angle;
+        transform.rotation = Quat::from_rotation_z(rotating.starting_angle + delta_angle);
+        transform.translation = rotate_around(rotating.starting_pos, rotating.center, delta_angle)
+            .extend(transform.translation.z);
     }
 
-    for (_, mut transform, translating) in queries.p1().iter_mut() {
+    for (_, mut transform, mut translating) in queries.p1().iter_mut() {
         // println!("rotating");
         let mouse_delta = cursor.position - cursor.last_click_position;
-        transform.translation =
-            (translating.starting_pos + mouse_delta).extend(transform.translation.z);
+        let new_pos = translating.starting_pos + mouse_delta;
+
+        if time.delta_seconds() > 0.0 {
+            let frame_velocity =
+                (new_pos - transform.translation.truncate()) / time.delta_seconds();
+            translating.velocity = translating
+                .velocity
+                .lerp(frame_velocity, DRAG_VELOCITY_SMOOTHING);
+        }
+
+        transform.translation = new_pos.extend(transform.translation.z);
     }
 
+    let state = ButtonState {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad: &gamepad_input,
+        wheel_up: false,
+        wheel_down: false,
+    };
+
+    // the end of a touch gesture releases both
+    let touch_released = action_event_reader
+        .iter()
+        .any(|action| *action == Action::ReleasePoly);
+
     // upon release the mouse button, remove the Translating or Rotating component
     // and check for collisions
-    if mouse_button_input.just_released(MouseButton::Left) {
+    if bindings.trigger_just_released(InputAction::Translate, &state) || touch_released {
+        // a group is neither thrown nor snapped piece by piece, which would break it apart.
+        // snap_poly(..) snaps it as a whole
+        let is_group = queries.p1().iter().count() > 1;
+        let mut group_snapped = false;
+
         // remove Translating
-        for (entity, _, _) in queries.p1().iter_mut() {
+        for (entity, transform, translating) in queries.p1().iter_mut() {
             commands.entity(entity).remove::<Translating>();
-            collision_test_writer.send(TestCollisionEvent(entity));
+
+            if is_group {
+                if globals.magnetic_snapping && !group_snapped {
+                    snap_writer.send(SnapPoly(entity));
+                    group_snapped = true;
+                } else if !globals.magnetic_snapping {
+                    collision_test_writer.send(TestCollisionEvent(entity));
+                }
+            }
+            // thrown, the polygon is snapped and tested when it stops, see physics_step(..)
+            else if globals.momentum && translating.velocity.length() > globals.min_throw_speed {
+                commands.entity(entity).insert(ForceMotion {
+                    force: Vec2::ZERO,
+                    area: 0.0,
+                    velocity: translating.velocity.clamp_length_max(MAX_THROW_SPEED),
+                    position: transform.translation.truncate(),
+                    thrown: true,
+                });
+            } else if globals.magnetic_snapping {
+                snap_writer.send(SnapPoly(entity));
+            } else {
+                collision_test_writer.send(TestCollisionEvent(entity));
+            }
             // info!("sending collision after translating");
         }
     }
 
-    if mouse_button_input.just_released(MouseButton::Right) {
+    if bindings.trigger_just_released(InputAction::Rotate, &state) || touch_released {
         // remove Rotating
+        let mut group_snapped = false;
         for (entity, _, _, _) in queries.p0().iter_mut() {
             commands.entity(entity).remove::<Rotating>();
-            collision_test_writer.send(TestCollisionEvent(entity));
+            if globals.magnetic_snapping {
+                // snap_poly(..) snaps the whole group of the polygon
+                if !group_snapped {
+                    snap_writer.send(SnapPoly(entity));
+                    group_snapped = true;
+                }
+            } else {
+                collision_test_writer.send(TestCollisionEvent(entity));
+            }
         }
     }
 }
 
 // Rotates polygon upon mousewheel event
 pub fn rotate_once(
+    mut commands: Commands,
     mut query: Query<(Entity, &mut Transform, &MeshMeta), With<Polygon>>,
     mut action_event_reader: EventReader<Action>,
     globals: Res<Globals>,
+    level_rules: Res<LevelRules>,
     mut collision_test_writer: EventWriter<TestCollisionEvent>,
 ) {
     // triggered by mousewheel
     if let Some(Action::RotateAt { pos, dir }) = action_event_reader.iter().next() {
+        if !level_rules.can_rotate() {
+            return;
+        }
+
         for (entity, mut transform, mesh_meta) in query.iter_mut() {
             //
             //
@@ -315,7 +466,10 @@ pub fn rotate_once(
                 FillRule::EvenOdd,
                 0.1,
             ) {
-                transform.rotation = Quat::from_rotation_z(angle + dir * globals.min_turn_angle);
+                transform.rotation =
+                    Quat::from_rotation_z(level_rules.rotate(angle, *dir, globals.min_turn_angle));
+                // turned on its own, the polygon no longer moves rigidly with its group
+                commands.entity(entity).remove::<Grouped>();
                 collision_test_writer.send(TestCollisionEvent(entity));
 
                 return;
@@ -323,3 +477,75 @@ pub fn rotate_once(
         }
     }
 }
+
+//
+//
+// Flips the polygon under the cursor, or the Selected polygons when the cursor is not on
+// any polygon. Flipping is only allowed by the levels with LevelRules::mirroring.
+pub fn flip_poly(
+    mut commands: Commands,
+    mut query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut MeshMeta,
+            &mut Mesh2dHandle,
+            Option<&Selected>,
+            Option<&PatternFrame>,
+        ),
+        With<Polygon>,
+    >,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut action_event_reader: EventReader<Action>,
+    level_rules: Res<LevelRules>,
+    mut flip_count: ResMut<FlipCount>,
+    mut collision_test_writer: EventWriter<TestCollisionEvent>,
+) {
+    if let Some(Action::FlipAt { pos }) = action_event_reader.iter().next() {
+        if !level_rules.mirroring {
+            return;
+        }
+
+        let cursor_point = Point::new(pos.x, pos.y);
+        let hovered = query
+            .iter()
+            .find(|(_, transform, mesh_meta, _, _, _)| {
+                mesh_meta.hit_test(&cursor_point, transform).0
+            })
+            .map(|(entity, _, _, _, _, _)| entity);
+
+        for (entity, mut transform, mut mesh_meta, mut mesh_handle, selected, pattern_frame) in
+            query.iter_mut()
+        {
+            let is_flipped = match hovered {
+                Some(hovered_entity) => hovered_entity == entity,
+                None => selected.is_some(),
+            };
+            if !is_flipped {
+                continue;
+            }
+
+            mesh_meta.flip();
+
+            // a mirror image of a rotated polygon is the mirrored polygon rotated the other way
+            let (axis, angle) = transform.rotation.to_axis_angle();
+            transform.rotation = Quat::from_rotation_z(-axis.z * angle);
+
+            // the mesh of the ghost is shared with the polygon, so a new one is made
+            let (mut mesh, _) = make_polygon_mesh(&mesh_meta.path, false);
+
+            // the pattern is mirrored with the polygon
+            let pattern_frame = PatternFrame(
+                pattern_frame.copied().unwrap_or_default().0
+                    * Affine2::from_scale(Vec2::new(1.0, -1.0)),
+            );
+            set_pattern_uvs(&mut mesh, &pattern_frame);
+            *mesh_handle = Mesh2dHandle(meshes.add(mesh));
+            commands.entity(entity).insert(pattern_frame);
+
+            flip_count.flips += 1;
+            commands.entity(entity).remove::<Grouped>();
+            collision_test_writer.send(TestCollisionEvent(entity));
+        }
+    }
+}
@@ -4,6 +4,7 @@ use bevy::prelude::*;
 use shapeshifter_level_maker::{
     input::Action,
     material::FillMesh2dMaterial,
+    stats::{normalize_points, NORMALIZED_AREA},
     util::{Globals, LoadedPolygonsRaw, MeshMeta, SpawnPoly, SpawnTarget},
     ShapeshifterLevelMakerPlugin,
 };
@@ -90,24 +91,8 @@ pub fn build_ptsmap(
         );
 
         for (name, pts) in pts_map.map.iter_mut() {
-            // normalize the area
-
-            let area_sqrt = pts.area.sqrt();
-            // let new_points: Vec<Vec2> = pts.points.iter().map(|pt| *pt / area_sqrt).collect();
-            let mut new_points = vec![];
-
-            let mut center_of_mass = Vec2::ZERO;
-            for pt in pts.points.iter() {
-                center_of_mass += *pt;
-            }
-            center_of_mass /= pts.points.len() as f32;
-
-            // move points to their center of maa
-
-            for point in pts.points.iter() {
-                // normalize such that the new area is 100000
-                new_points.push((point.clone() - center_of_mass) / area_sqrt * 50000.0_f32.sqrt());
-            }
+            // normalize such that the new area is 50000
+            let new_points = normalize_points(&pts.points, NORMALIZED_AREA);
 
             action_event_writer.send(Action::SaveOneSent {
                 name: name.clone(),
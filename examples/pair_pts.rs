@@ -0,0 +1,108 @@
+// Normalizes every polygon in assets/meshes and lists the minimal target_multiplier of
+// every polygon/target pair, to find good level pairings quickly.
+//
+//      cargo run --example pair_pts
+//      cargo run --example pair_pts -- --write              (saves the normalized .pts files)
+//      cargo run --example pair_pts -- --polygon bird1      (only pairs using bird1 as polygon)
+
+use shapeshifter_level_maker::{stats::*, validate::*};
+
+pub fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let do_write = args.iter().any(|arg| arg == "--write");
+    let maybe_polygon = args
+        .iter()
+        .position(|arg| arg == "--polygon")
+        .and_then(|idx| args.get(idx + 1).cloned());
+
+    let mut save_prepath = std::env::current_dir().unwrap();
+    save_prepath.push("assets/meshes/");
+
+    let mut paths: Vec<_> = std::fs::read_dir(save_prepath)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "pts"))
+        .collect();
+    paths.sort();
+
+    //
+    //
+    // normalize. The multipliers depend on the sizes of the shapes in the files, so the
+    // stats of the raw points are kept unless the normalized points are written
+    let mut all_stats = Vec::new();
+    let mut file_stats = Vec::new();
+    for path in paths.iter() {
+        let name_of_file = path.file_stem().unwrap().to_str().unwrap();
+        if let Some(mut save_mesh_meta) = read_pts_file(path) {
+            let raw_stats = compute_shape_stats(name_of_file, &save_mesh_meta.points);
+            save_mesh_meta.points = normalize_points(&save_mesh_meta.points, NORMALIZED_AREA);
+            let stats = compute_shape_stats(name_of_file, &save_mesh_meta.points);
+            if do_write {
+                write_pts_file(path, &save_mesh_meta);
+                file_stats.push(stats.clone());
+            } else {
+                file_stats.push(raw_stats);
+            }
+            all_stats.push(stats);
+        } else {
+            println!("{}: could not be parsed", name_of_file);
+        }
+    }
+
+    println!(
+        "{:<32} {:>10} {:>8} {:>8} {:>10} {:>7} {:>12}",
+        "name", "area", "width", "height", "perimeter", "convex", "compactness"
+    );
+    for stats in all_stats.iter() {
+        println!(
+            "{:<32} {:>10.1} {:>8.1} {:>8.1} {:>10.1} {:>7} {:>12.3}",
+            stats.name,
+            stats.area,
+            stats.width,
+            stats.height,
+            stats.perimeter,
+            stats.is_convex,
+            stats.compactness()
+        );
+    }
+
+    //
+    //
+    // pairs, the most similar silhouettes first. The score adds the difference of the
+    // aspect ratios (log scale) to the difference of compactness
+    println!();
+    println!(
+        "{:<32} {:<32} {:>10} {:>8}",
+        "polygon", "target", "min mult", "score"
+    );
+    for (polygon, polygon_file) in all_stats.iter().zip(file_stats.iter()) {
+        if let Some(ref name) = maybe_polygon {
+            if &polygon.name != name {
+                continue;
+            }
+        }
+
+        let mut pairs: Vec<(&ShapeStats, f32, f32)> = all_stats
+            .iter()
+            .zip(file_stats.iter())
+            .filter(|(target, _)| target.name != polygon.name)
+            .map(|(target, target_file)| {
+                let aspect_diff = (polygon.aspect_ratio().ln() - target.aspect_ratio().ln()).abs();
+                let compactness_diff = (polygon.compactness() - target.compactness()).abs();
+                (
+                    target,
+                    min_target_multiplier(polygon_file, target_file),
+                    aspect_diff + compactness_diff,
+                )
+            })
+            .collect();
+        pairs.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+        for (target, mult, score) in pairs.iter() {
+            println!(
+                "{:<32} {:<32} {:>10.3} {:>8.3}",
+                polygon.name, target.name, mult, score
+            );
+        }
+    }
+}
@@ -0,0 +1,72 @@
+// Validates every polygon in assets/meshes and reports self-intersections, zero-length
+// edges, wrong winding and slivers.
+//
+//      cargo run --example validate_pts
+//      cargo run --example validate_pts -- --fix     (overwrites the repaired .pts files)
+
+use shapeshifter_level_maker::validate::*;
+
+pub fn main() {
+    let do_fix = std::env::args().any(|arg| arg == "--fix");
+
+    let mut save_prepath = std::env::current_dir().unwrap();
+    save_prepath.push("assets/meshes/");
+    println!("save_prepath: {:?}", save_prepath);
+
+    let mut num_with_issues = 0;
+    let mut num_fatal = 0;
+
+    let mut paths: Vec<_> = std::fs::read_dir(save_prepath)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "pts"))
+        .collect();
+    paths.sort();
+
+    for path in paths.iter() {
+        let name_of_file = path.file_stem().unwrap().to_str().unwrap();
+
+        let mut save_mesh_meta = if let Some(save_mesh_meta) = read_pts_file(path) {
+            save_mesh_meta
+        } else {
+            println!("{}: could not be parsed", name_of_file);
+            num_fatal += 1;
+            continue;
+        };
+
+        let issues = validate_points(&save_mesh_meta.points);
+        if issues.is_empty() {
+            continue;
+        }
+
+        num_with_issues += 1;
+        println!("{} ({} points):", name_of_file, save_mesh_meta.points.len());
+        for issue in issues.iter() {
+            println!("    {}", issue);
+        }
+
+        if issues.iter().any(|issue| issue.is_fatal()) {
+            num_fatal += 1;
+            println!("    -> needs to be fixed by hand");
+            continue;
+        }
+
+        if do_fix {
+            let repaired = repair_points(&save_mesh_meta.points);
+            println!(
+                "    -> repaired ({} points -> {} points)",
+                save_mesh_meta.points.len(),
+                repaired.len()
+            );
+            save_mesh_meta.points = repaired;
+            write_pts_file(path, &save_mesh_meta);
+        }
+    }
+
+    println!(
+        "{} polygons checked, {} with issues, {} that cannot be repaired automatically",
+        paths.len(),
+        num_with_issues,
+        num_fatal
+    );
+}
@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use std::io::{Read, Write};
+
+use crate::sound::SoundMap;
+
+// saved in the current directory, like the bindings
+pub const AUDIO_SETTINGS_FILE: &str = "audio_settings.json";
+
+// one click on the - or + button of a volume slider
+pub const VOLUME_STEP: u32 = 10;
+
+pub struct AudioSettingsPlugin;
+
+impl Plugin for AudioSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AudioSettings::load())
+            .add_system(apply_audio_settings);
+    }
+}
+
+// volume in percent, from 0 to 100
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Volume(pub u32);
+
+impl Volume {
+    pub fn fraction(&self) -> f32 {
+        self.0.min(100) as f32 / 100.0
+    }
+
+    pub fn step(&mut self, dir: i32) {
+        self.0 = if dir > 0 {
+            (self.0 + VOLUME_STEP).min(100)
+        } else {
+            self.0.saturating_sub(VOLUME_STEP)
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeChannel {
+    Master,
+    Music,
+    Effects,
+}
+
+impl VolumeChannel {
+    pub const ALL: [VolumeChannel; 3] = [
+        VolumeChannel::Master,
+        VolumeChannel::Music,
+        VolumeChannel::Effects,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            VolumeChannel::Master => "Master",
+            VolumeChannel::Music => "Music",
+            VolumeChannel::Effects => "Effects",
+        }
+    }
+}
+
+//
+//
+// The volumes chosen in the audio menu. The music and the effects are both scaled by the
+// master volume, and muting silences everything without losing the volumes.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AudioSettings {
+    pub master: Volume,
+    pub music: Volume,
+    pub effects: Volume,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: Volume(80),
+            music: Volume(70),
+            effects: Volume(100),
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn volume(&self, channel: VolumeChannel) -> Volume {
+        match channel {
+            VolumeChannel::Master => self.master,
+            VolumeChannel::Music => self.music,
+            VolumeChannel::Effects => self.effects,
+        }
+    }
+
+    pub fn volume_mut(&mut self, channel: VolumeChannel) -> &mut Volume {
+        match channel {
+            VolumeChannel::Master => &mut self.master,
+            VolumeChannel::Music => &mut self.music,
+            VolumeChannel::Effects => &mut self.effects,
+        }
+    }
+
+    // volume given to the music sink
+    pub fn music_volume(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.master.fraction() * self.music.fraction()
+        }
+    }
+
+    // volume given to every sound of the SoundMap
+    pub fn effects_volume(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.master.fraction() * self.effects.fraction()
+        }
+    }
+
+    pub fn load() -> Self {
+        let mut path = std::env::current_dir().unwrap_or_default();
+        path.push(AUDIO_SETTINGS_FILE);
+
+        let mut contents = String::new();
+        if let Ok(mut file) = std::fs::File::open(&path) {
+            if file.read_to_string(&mut contents).is_ok() {
+                match serde_json::from_str::<AudioSettings>(&contents) {
+                    Ok(settings) => return settings,
+                    Err(err) => warn!("could not read {:?}, using defaults: {}", path, err),
+                }
+            }
+        }
+
+        Self::default()
+    }
+
+    pub fn save(&self) {
+        let mut path = std::env::current_dir().unwrap_or_default();
+        path.push(AUDIO_SETTINGS_FILE);
+
+        let serialized = serde_json::to_string_pretty(&self).unwrap();
+        let result = std::fs::File::create(&path)
+            .and_then(|mut output| output.write_all(serialized.as_bytes()));
+        if let Err(err) = result {
+            warn!("could not save {:?}: {}", path, err);
+        }
+    }
+}
+
+// the music follows the settings every frame, see update_music(..)
+fn apply_audio_settings(settings: Res<AudioSettings>, sound_map: Option<ResMut<SoundMap>>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    if let Some(mut sound_map) = sound_map {
+        sound_map.volume = settings.effects_volume();
+    }
+}
@@ -6,10 +6,9 @@ use bevy::{
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
 };
 
-use shapeshifter_level_maker::{input::Cursor, material::*, util::*};
+use shapeshifter_level_maker::{input::Cursor, material::*, theme::Themes, util::*};
 
 use super::GameState;
-use super::TEXT_COLOR;
 
 // This plugin will contain the game. In this case, it's just be a screen that will
 // display the current settings for 5 seconds before returning to the menu
@@ -127,6 +126,7 @@ pub fn spawn_target_scale(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     target_scale: ResMut<TargetScale>,
+    themes: Res<Themes>,
 ) {
     //
     // info!("spawn_shortcuts");
@@ -138,7 +138,7 @@ pub fn spawn_target_scale(
     let text_style = TextStyle {
         font: font.clone(),
         font_size: 22.0,
-        color: TEXT_COLOR,
+        color: themes.current().text,
     };
 
     commands
@@ -172,7 +172,11 @@ pub fn spawn_target_scale(
         });
 }
 
-pub fn spawn_shortcuts(mut commands: Commands, asset_server: Res<AssetServer>) {
+pub fn spawn_shortcuts(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    themes: Res<Themes>,
+) {
     //
     // info!("spawn_shortcuts");
 
@@ -194,7 +198,7 @@ Note: there is no way to save a level on the browser version";
     let text_style = TextStyle {
         font: font.clone(),
         font_size: 22.0,
-        color: TEXT_COLOR,
+        color: themes.current().text,
     };
 
     commands
@@ -242,7 +246,7 @@ pub fn spawn_design_poly(
     mut fill_materials: ResMut<Assets<FillMesh2dMaterial>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut spawn_polykeep_event_reader: EventReader<SpawnDesignPoly>,
-    // globals: Res<Globals>,
+    globals: Res<Globals>,
 ) {
     for SpawnDesignPoly { polygon, position } in spawn_polykeep_event_reader.iter() {
         // let mesh_meta: MeshMeta = save_format_mesh_meta.into();
@@ -263,12 +267,10 @@ pub fn spawn_design_poly(
 
             let (mesh, center_of_mass) = make_polygon_mesh(&mesh_meta.path, true);
 
-            let mat_handle = fill_materials.add(FillMesh2dMaterial {
-                color: Color::rgb(0.22, 0.4, 0.05).into(),
-                show_com: 0.0,
-                selected: 0.0,
-                is_intersecting: 0.0,
-            });
+            let mat_handle = fill_materials.add(
+                FillMesh2dMaterial::new(Color::rgb(0.22, 0.4, 0.05), &globals)
+                    .with_pattern(&globals),
+            );
 
             let path_translation =
                 lyon::geom::Translation::new(-center_of_mass.x, -center_of_mass.y);
@@ -1,12 +1,16 @@
 use crate::game_spawn::*;
 // use crate::levels::send_tutorial_text;
 use crate::levels::*;
+use crate::menu::FontHandles;
 
-use bevy::audio::AudioSink;
 use bevy::{prelude::*, utils::Duration};
 
+use shapeshifter_level_maker::overlap::Overlay;
+use shapeshifter_level_maker::theme::Themes;
 use shapeshifter_level_maker::util::{
-    HasWonLevelEvent, PerformedCut, PolyIsInsideTarget, Polygon, RemainingCuts, SpawnLevel, Target,
+    applied_cuts, CutApplied, CutPreview, CutRejected, ExcessArea, FlipCount, LevelRules, LevelWon,
+    Obstacle, PolyIsInsideTarget, Polygon, RemainingCuts, RotationRule, SelectionBox, SpawnLevel,
+    Target,
 };
 
 use super::GameState;
@@ -42,7 +46,10 @@ impl Plugin for GamePlugin {
                     .with_system(game_setup)
                     .with_system(spawn_options_button)
                     .with_system(spawn_current_level)
-                    .with_system(spawn_remaining_cuts_label),
+                    .with_system(spawn_remaining_cuts_label)
+                    .with_system(spawn_cut_reason_label)
+                    .with_system(spawn_level_rules_label)
+                    .with_system(spawn_excess_area_label),
             )
             .add_system_set(
                 SystemSet::on_update(GameState::Game)
@@ -57,9 +64,13 @@ impl Plugin for GamePlugin {
                     .with_system(spawn_instruction)
                     .with_system(previous_level)
                     .with_system(inscrease_total_cuts)
+                    .with_system(play_rejected_cut_sound)
                     .with_system(game_buttons_action)
                     // .with_system(force_next_level)
                     .with_system(show_cuts_label)
+                    .with_system(show_cut_reason)
+                    .with_system(show_level_rules)
+                    .with_system(show_excess_area)
                     .with_system(show_pause_menu)
                     .with_system(play_inside_target_sound)
                     .with_system(activate_next_level_button),
@@ -139,18 +150,128 @@ fn show_cuts_label(
     }
 }
 
+fn show_excess_area(
+    excess_area: Res<ExcessArea>,
+    mut query: Query<&mut Text, With<ExcessAreaLabel>>,
+    added_query: Query<Entity, Added<ExcessAreaLabel>>,
+) {
+    if excess_area.is_changed() || !added_query.is_empty() {
+        let label = format!("Excess: {:.0}", excess_area.area);
+        for mut text in query.iter_mut() {
+            if let Some(mut section) = text.sections.get_mut(0) {
+                section.value = label.clone();
+            }
+        }
+    }
+}
+
 fn inscrease_total_cuts(
-    mut performed_cut_event_reader: EventReader<PerformedCut>,
+    mut cut_applied_event_reader: EventReader<CutApplied>,
     mut whole_game_cuts: ResMut<WholeGameCuts>,
-    sound_map: Res<crate::menu::SoundMap>,
+    mut sound_map: ResMut<crate::sound::SoundMap>,
     audio: Res<Audio>,
 ) {
-    for _ in performed_cut_event_reader.iter() {
+    for _ in applied_cuts(&mut cut_applied_event_reader) {
         whole_game_cuts.cuts += 1;
         sound_map.play("cut", &audio);
     }
 }
 
+fn play_rejected_cut_sound(
+    mut cut_rejected_event_reader: EventReader<CutRejected>,
+    mut sound_map: ResMut<crate::sound::SoundMap>,
+    audio: Res<Audio>,
+) {
+    for cut_rejected in cut_rejected_event_reader.iter() {
+        info!("cut rejected: {}", cut_rejected.reason);
+        sound_map.play("bip", &audio);
+    }
+}
+
+fn show_cut_reason(
+    cut_preview: Res<CutPreview>,
+    mut query: Query<&mut Text, With<CutReasonLabel>>,
+) {
+    if cut_preview.is_changed() {
+        let label = if let Some(invalid) = cut_preview.invalid {
+            invalid.to_string()
+        } else {
+            "".to_string()
+        };
+        for mut text in query.iter_mut() {
+            if let Some(mut section) = text.sections.get_mut(0) {
+                section.value = label.clone();
+            }
+        }
+    }
+}
+
+// one icon per rule of the level that differs from the defaults, with the snap angle or
+// the number of flips next to it
+fn show_level_rules(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    fonts: Res<FontHandles>,
+    themes: Res<Themes>,
+    level_rules: Res<LevelRules>,
+    flip_count: Res<FlipCount>,
+    query: Query<(Entity, Option<&Children>), With<LevelRulesLabel>>,
+    added_query: Query<Entity, Added<LevelRulesLabel>>,
+) {
+    if level_rules.is_changed() || flip_count.is_changed() || !added_query.is_empty() {
+        let mut icons = Vec::new();
+        match level_rules.rotation {
+            RotationRule::Free => {}
+            RotationRule::Forbidden => icons.push(("rule_no_rotation.png", String::new())),
+            RotationRule::Snap(step) => icons.push((
+                "rule_snap_rotation.png",
+                format!("{}", step.to_degrees().round()),
+            )),
+        }
+        if level_rules.mirroring {
+            icons.push(("rule_mirror.png", flip_count.flips.to_string()));
+        }
+
+        let text_style = TextStyle {
+            font: fonts.font.clone(),
+            font_size: 30.0,
+            color: themes.current().text,
+        };
+
+        for (entity, children) in query.iter() {
+            for child in children.iter().flat_map(|children| children.iter()) {
+                commands.entity(*child).despawn_recursive();
+            }
+
+            commands.entity(entity).with_children(|parent| {
+                for (icon, text) in icons.iter() {
+                    // the icons are white, tinted with the colour of the text
+                    parent.spawn_bundle(ImageBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(40.0), Val::Px(40.0)),
+                            margin: UiRect {
+                                left: Val::Px(12.0),
+                                right: Val::Px(4.0),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        image: UiImage(asset_server.load(&format!("textures/Game Icons/{}", icon))),
+                        color: themes.current().text.into(),
+                        ..default()
+                    });
+                    if !text.is_empty() {
+                        parent.spawn_bundle(TextBundle::from_section(
+                            text.clone(),
+                            text_style.clone(),
+                        ));
+                    }
+                }
+            });
+        }
+    }
+}
+
 fn delete_game_entities(
     mut commands: Commands,
     query: Query<
@@ -159,8 +280,14 @@ fn delete_game_entities(
             With<PauseMenu>,
             With<NextButtonParent>,
             With<Target>,
+            With<Obstacle>,
             With<Polygon>,
+            With<SelectionBox>,
+            With<Overlay>,
             With<RemainingCutsComponent>,
+            With<CutReasonLabel>,
+            With<LevelRulesLabel>,
+            With<ExcessAreaLabel>,
             With<Instruction>,
             With<OptionButton>,
             With<LevelInt>,
@@ -176,7 +303,7 @@ fn delete_game_entities(
 
 fn play_inside_target_sound(
     audio: Res<Audio>,
-    sound_map: Res<crate::menu::SoundMap>,
+    mut sound_map: ResMut<crate::sound::SoundMap>,
     mut poly_inside_target_event_reader: EventReader<PolyIsInsideTarget>,
 ) {
     for _ in poly_inside_target_event_reader.iter() {
@@ -453,8 +580,6 @@ fn game_buttons_action(
     mut spawn_level_event_writer: EventWriter<SpawnLevel>,
     pause_menu_query: Query<Entity, With<PauseMenu>>,
     next_button_query: Query<Entity, With<NextButtonParent>>,
-    music_controller: Res<crate::menu::MusicController>,
-    audio_sinks: Res<Assets<AudioSink>>,
     mut spawn_pause_menu_event_writer: EventWriter<TogglePauseMenu>,
 ) {
     let mut has_despawned_next_button = false;
@@ -475,9 +600,6 @@ fn game_buttons_action(
                 }
                 GameButtonAction::ToMenu => {
                     game_state.set(GameState::Menu).unwrap();
-                    if let Some(sink) = audio_sinks.get(&music_controller.0) {
-                        sink.play();
-                    }
                 }
                 GameButtonAction::Restart => {
                     let spawn_level = game_levels.get(&current_level.level.clone());
@@ -516,7 +638,7 @@ fn game_buttons_action(
 fn win_sound(
     mut win_sound_timer: ResMut<WinSoundTimer>,
     audio: Res<Audio>,
-    sound_map: Res<crate::menu::SoundMap>,
+    mut sound_map: ResMut<crate::sound::SoundMap>,
     time: Res<Time>,
 ) {
     if let Some(ref mut timer) = win_sound_timer.maybe_timer {
@@ -529,14 +651,15 @@ fn win_sound(
 fn activate_next_level_button(
     mut commands: Commands,
     // mut win_sound_timer: ResMut<WinSoundTimer>,
-    mut has_won_event_reader: EventReader<HasWonLevelEvent>,
+    mut level_won_event_reader: EventReader<LevelWon>,
     mut go_next_button_query: Query<(Entity, &mut Visibility), With<Button>>,
     mut spawn_next_level_button_event_writer: EventWriter<SpawnNextLevelButton>,
     mut unlocked_levels: ResMut<UnlockedLevels>,
     current_level: Res<CurrentLevel>,
 ) {
     //
-    if let Some(_) = has_won_event_reader.iter().next() {
+    if let Some(level_won) = level_won_event_reader.iter().next() {
+        info!("level won: {:?}", level_won.stats);
         spawn_next_level_button_event_writer.send(SpawnNextLevelButton);
 
         //
@@ -1,15 +1,13 @@
 use bevy::prelude::*;
 
 use bevy_easings::*;
+use shapeshifter_level_maker::theme::Themes;
 use shapeshifter_level_maker::util::{Polygon, RemainingCuts, SpawnLevel, Target};
 
-use super::TEXT_COLOR;
-
 use crate::game::{GameButtonAction, WholeGameCuts, WonTheGame};
 use crate::levels::*;
 use crate::menu::FontHandles;
 
-const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
 const CITY_TITLE_MILLIS: u64 = 3000;
 
 #[derive(Component)]
@@ -27,6 +25,15 @@ pub struct Instruction;
 #[derive(Component)]
 pub struct RemainingCutsComponent;
 
+#[derive(Component)]
+pub struct CutReasonLabel;
+
+#[derive(Component)]
+pub struct LevelRulesLabel;
+
+#[derive(Component)]
+pub struct ExcessAreaLabel;
+
 pub struct SpawnNextLevelButton;
 pub struct TogglePauseMenu;
 pub struct SpawnInstruction {
@@ -39,7 +46,7 @@ pub fn spawn_won_screen(
     // asset_server: Res<AssetServer>,
     mut won_the_game_event_reader: EventReader<WonTheGame>,
     fonts: Res<FontHandles>,
-    sound_map: Res<crate::menu::SoundMap>,
+    mut sound_map: ResMut<crate::sound::SoundMap>,
     audio: Res<Audio>,
 ) {
     //
@@ -99,6 +106,7 @@ pub fn spawn_instruction(
     // asset_server: Res<AssetServer>,
     mut spawn_instruction_event_reader: EventReader<SpawnInstruction>,
     fonts: Res<FontHandles>,
+    themes: Res<Themes>,
 ) {
     if let Some(instruction) = spawn_instruction_event_reader.iter().next() {
         //
@@ -107,7 +115,7 @@ pub fn spawn_instruction(
         let text_style = TextStyle {
             font: font.clone(),
             font_size: 40.0,
-            color: TEXT_COLOR,
+            color: themes.current().text,
         };
 
         commands
@@ -178,6 +186,7 @@ pub fn spawn_city_title(
     // mut city_title_timer: ResMut<CityTitleTimer>,
     // delete_query: Query<Entity, Or<(With<Polygon>, With<Target>, With<NextButtonParent>)>>,
     // mut spawn_city_title_event_reader: EventReader<SpawnCityTitle>,
+    themes: Res<Themes>,
 ) {
     let font = fonts.font.clone();
 
@@ -204,7 +213,7 @@ pub fn spawn_city_title(
     let text_style = TextStyle {
         font: font.clone(),
         font_size: 100.0,
-        color: super::TEXT_COLOR,
+        color: themes.current().text,
     };
 
     let start_style = Style {
@@ -246,6 +255,7 @@ pub fn spawn_pause_menu(
     pause_menu_query: Query<Entity, With<PauseMenu>>,
     mut toggle_pause_menu_event_reader: EventReader<TogglePauseMenu>,
     fonts: Res<FontHandles>,
+    themes: Res<Themes>,
 ) {
     for _ in toggle_pause_menu_event_reader.iter() {
         if let Some(entity) = pause_menu_query.iter().next() {
@@ -267,7 +277,7 @@ pub fn spawn_pause_menu(
             let button_text_style = TextStyle {
                 font: font.clone(),
                 font_size: 50.0,
-                color: TEXT_COLOR,
+                color: themes.current().text,
             };
             commands
                 .spawn_bundle(NodeBundle {
@@ -284,7 +294,7 @@ pub fn spawn_pause_menu(
                         // align_self: AlignSelf::Center,
                         ..default()
                     },
-                    color: Color::PURPLE.into(),
+                    color: themes.current().panel.into(),
                     // transform: Transform::from_translation(Vec3::new(0.0, 0.0, 111.0)),
                     ..default()
                 })
@@ -297,7 +307,7 @@ pub fn spawn_pause_menu(
                             TextStyle {
                                 font: font.clone(),
                                 font_size: 110.0,
-                                color: TEXT_COLOR,
+                                color: themes.current().text,
                             },
                         )
                         .with_style(Style {
@@ -313,7 +323,7 @@ pub fn spawn_pause_menu(
                     parent
                         .spawn_bundle(ButtonBundle {
                             style: button_style.clone(),
-                            color: NORMAL_BUTTON.into(),
+                            color: themes.current().button.into(),
                             ..default()
                         })
                         .insert(GameButtonAction::GoBack)
@@ -326,7 +336,7 @@ pub fn spawn_pause_menu(
                     parent
                         .spawn_bundle(ButtonBundle {
                             style: button_style.clone(),
-                            color: NORMAL_BUTTON.into(),
+                            color: themes.current().button.into(),
                             ..default()
                         })
                         .insert(GameButtonAction::Restart)
@@ -339,7 +349,7 @@ pub fn spawn_pause_menu(
                     parent
                         .spawn_bundle(ButtonBundle {
                             style: button_style.clone(),
-                            color: NORMAL_BUTTON.into(),
+                            color: themes.current().button.into(),
                             ..default()
                         })
                         .insert(GameButtonAction::ToMenu)
@@ -363,6 +373,7 @@ pub fn spawn_current_level(
     game_levels: Res<GameLevels>,
     current_level: Res<crate::levels::CurrentLevel>,
     fonts: Res<FontHandles>,
+    themes: Res<Themes>,
 ) {
     // let font = asset_server.load("fonts/FiraSans-Bold.ttf");
     let font = fonts.font.clone();
@@ -400,7 +411,7 @@ pub fn spawn_current_level(
                     TextStyle {
                         font: font.clone(),
                         font_size: 60.0,
-                        color: TEXT_COLOR,
+                        color: themes.current().text,
                     },
                 ))
                 .insert(LevelInt);
@@ -414,6 +425,7 @@ pub fn spawn_remaining_cuts_label(
     // asset_server: Res<AssetServer>,
     remaining_cuts: ResMut<RemainingCuts>,
     fonts: Res<FontHandles>,
+    themes: Res<Themes>,
 ) {
     //
     //
@@ -449,18 +461,98 @@ pub fn spawn_remaining_cuts_label(
                     TextStyle {
                         font: font.clone(),
                         font_size: 60.0,
-                        color: TEXT_COLOR,
+                        color: themes.current().text,
                     },
                 ))
                 .insert(RemainingCutsComponent);
         });
 }
 
+// empty until a cut being made is invalid, see show_cut_reason(..)
+pub fn spawn_cut_reason_label(
+    mut commands: Commands,
+    fonts: Res<FontHandles>,
+    themes: Res<Themes>,
+) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: fonts.font.clone(),
+                    font_size: 30.0,
+                    color: themes.current().invalid_cut,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(50.0),
+                    top: Val::Px(210.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(CutReasonLabel);
+}
+
+// a row of rule icons, filled by show_level_rules(..)
+pub fn spawn_level_rules_label(mut commands: Commands) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(50.0),
+                    top: Val::Px(250.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(LevelRulesLabel);
+}
+
+// area of the pieces outside the target, see show_excess_area(..)
+pub fn spawn_excess_area_label(
+    mut commands: Commands,
+    fonts: Res<FontHandles>,
+    themes: Res<Themes>,
+) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: fonts.font.clone(),
+                    font_size: 30.0,
+                    color: themes.current().text,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(50.0),
+                    top: Val::Px(290.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(ExcessAreaLabel);
+}
+
 pub fn spawn_options_button(
     mut commands: Commands,
     // asset_server: Res<AssetServer>,
     fonts: Res<FontHandles>,
     // mut spawn_next_level_button_event_reader: EventReader<SpawnNextLevelButton>,
+    themes: Res<Themes>,
 ) {
     // let font = asset_server.load("fonts/FiraSans-Bold.ttf");
     let font = fonts.font.clone();
@@ -476,7 +568,7 @@ pub fn spawn_options_button(
     let button_text_style = TextStyle {
         font: font.clone(),
         font_size: 50.0,
-        color: TEXT_COLOR,
+        color: themes.current().text,
     };
 
     commands
@@ -494,7 +586,7 @@ pub fn spawn_options_button(
                 },
                 ..default()
             },
-            color: Color::PURPLE.into(),
+            color: themes.current().panel.into(),
             // visibility: Visibility { is_visible: false },
             // computed_visibility: ComputedVisibility::not_visible(),
             ..default()
@@ -504,7 +596,7 @@ pub fn spawn_options_button(
             parent
                 .spawn_bundle(ButtonBundle {
                     style: button_style.clone(),
-                    color: NORMAL_BUTTON.into(),
+                    color: themes.current().button.into(),
                     visibility: Visibility { is_visible: true },
                     ..default()
                 })
@@ -522,6 +614,7 @@ pub fn spawn_next_level_button(
     mut commands: Commands,
     mut spawn_next_level_button_event_reader: EventReader<SpawnNextLevelButton>,
     fonts: Res<FontHandles>,
+    themes: Res<Themes>,
 ) {
     //
 
@@ -545,7 +638,7 @@ pub fn spawn_next_level_button(
         let button_text_style = TextStyle {
             font: font.clone(),
             font_size: 45.0,
-            color: TEXT_COLOR,
+            color: themes.current().text,
         };
 
         commands
@@ -563,7 +656,7 @@ pub fn spawn_next_level_button(
                     },
                     ..default()
                 },
-                color: Color::PURPLE.into(),
+                color: themes.current().panel.into(),
                 // transform: Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)),
                 // visibility: Visibility { is_visible: false },
                 // computed_visibility: ComputedVisibility::not_visible(),
@@ -574,7 +667,7 @@ pub fn spawn_next_level_button(
                 parent
                     .spawn_bundle(ButtonBundle {
                         style: button_style.clone(),
-                        color: NORMAL_BUTTON.into(),
+                        color: themes.current().button.into(),
                         visibility: Visibility { is_visible: true },
                         ..default()
                     })
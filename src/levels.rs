@@ -1,6 +1,6 @@
 use super::game_spawn::SpawnInstruction;
 use bevy::prelude::*;
-use shapeshifter_level_maker::util::SpawnLevel;
+use shapeshifter_level_maker::util::{PresetPiece, SpawnLevel};
 
 pub struct UnlockedLevels {
     pub levels: Vec<Level>,
@@ -33,6 +33,15 @@ pub enum Level {
 }
 
 impl Level {
+    pub fn city(&self) -> City {
+        match self {
+            Level::Tutorial(_) => City::Tutorial,
+            Level::Simplicity(_) => City::Simplicity,
+            Level::Perplexity(_) => City::Perplexity,
+            Level::Complexity(_) => City::Complexity,
+        }
+    }
+
     pub fn tutorial(&mut self, x: usize) {
         *self = Level::Tutorial(x);
     }
@@ -112,6 +121,23 @@ pub fn send_tutorial_text(
     }
 }
 
+// the seven classic pieces, to be arranged into `target`
+fn tangram(target: &str, mult: f32) -> SpawnLevel {
+    SpawnLevel::tangram(
+        target,
+        mult,
+        vec![
+            PresetPiece::new("tangram_large_triangle", -480.0, 150.0, 0.0),
+            PresetPiece::new("tangram_large_triangle", -220.0, 150.0, 180.0),
+            PresetPiece::new("tangram_medium_triangle", -500.0, -120.0, 0.0),
+            PresetPiece::new("tangram_square", -360.0, -120.0, 0.0),
+            PresetPiece::new("tangram_small_triangle", -240.0, -80.0, 0.0),
+            PresetPiece::new("tangram_small_triangle", -120.0, -80.0, 90.0),
+            PresetPiece::new("tangram_parallelogram", -300.0, -240.0, 0.0),
+        ],
+    )
+}
+
 // 004_simplicity_square_cut
 
 // SpawnLevel::new4("002_simplicity_square", "tree1", 1, 1.25),
@@ -156,14 +182,18 @@ impl Default for GameLevels {
             // SpawnLevel::new4("a", "glass", 3, 1.1),
             // SpawnLevel::new4("t", "turtle1", 2, 1.3),
             SpawnLevel::new4("crab1", "whale1", 3, 1.3),
-            SpawnLevel::new4("f", "fish_charles", 1, 1.12),
+            SpawnLevel::new4("f", "fish_charles", 1, 1.12).with_mirroring(),
             SpawnLevel::new4("squirrel1", "bird1", 3, 1.18), // convexity
+            // the classic pieces only ever need multiples of 45 degrees
+            tangram("tangram_full_square", 1.05).snap_rotation(45.0),
         ];
         let perplexity = vec![
             // SpawnLevel::new4("giraffe1", "cat2", 3, 1.35),
             SpawnLevel::new4("spade", "p", 2, 1.15), // perplexity
-            SpawnLevel::new4("gege_weird", "beaver1", 2, 1.4), // perplexity
-            SpawnLevel::new4("squirrel1", "glass", 2, 1.15),
+            SpawnLevel::new4("gege_weird", "beaver1", 2, 1.4).no_rotation(), // perplexity
+            // the pieces go around the diamond, which stands between them and the target
+            SpawnLevel::new4("squirrel1", "glass", 2, 1.15)
+                .with_obstacle("diamond", -300.0, 0.0, 0.0),
         ];
         let complexity = vec![
             SpawnLevel::new4("cat3", "otter1", 3, 1.2),
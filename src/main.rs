@@ -1,18 +1,21 @@
+mod audio;
 mod design;
 mod game;
 mod game_spawn;
 mod levels;
 mod menu;
+mod music;
+mod sound;
 mod splash;
+mod window;
 
 use bevy::prelude::*;
+use shapeshifter_level_maker::theme::{Theme, Themes};
 use shapeshifter_level_maker::ShapeshifterLevelMakerPlugin;
+use window::{design_camera, WindowSettings};
 
 // use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 
-const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
-const BG_COLOR: Color = Color::rgb(0.1, 0.1, 0.1);
-
 // Enum that will be used as a global state for the game
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub enum GameState {
@@ -23,35 +26,33 @@ pub enum GameState {
     Design,
 }
 
-// One of the two settings that can be set through the menu. It will be a resource in the app
-#[derive(Debug, Component, PartialEq, Eq, Clone, Copy)]
-struct Volume(u32);
-// 1280x720
 fn main() {
+    // the window is described in window_settings.json, see window.rs
+    let window_settings = WindowSettings::load();
+
     App::new()
         //
         //
         //
-        // .insert_resource(WindowDescriptor {
-        //     title: "pen".to_string(),
-        //     width: 1280.,
-        //     height: 720.,
-        //     position: WindowPosition::At(Vec2::new(500., 200.)),
-        //     present_mode: bevy::window::PresentMode::Immediate,
-        //     ..Default::default()
-        // })
+        .insert_resource(window_settings.descriptor())
+        .insert_resource(window_settings)
         // .add_plugin(LogDiagnosticsPlugin::default())
         // .add_plugin(FrameTimeDiagnosticsPlugin::default())
         //
         //
         //
-        .insert_resource(ClearColor(BG_COLOR))
+        // the ClearColor comes from the theme, see apply_theme(..)
         .add_plugins(DefaultPlugins)
         .add_plugin(ShapeshifterLevelMakerPlugin)
+        .add_plugin(window::WindowSettingsPlugin)
+        .add_plugin(audio::AudioSettingsPlugin)
+        .add_plugin(sound::SoundPlugin)
+        .add_plugin(music::MusicPlugin)
         // .add_plugin(CamPlugin)
         // Insert as resource the initial value for the settings resources
         // .add_startup_system(camera_setup)
         .add_startup_system(setup)
+        .add_system(apply_ui_theme)
         // Declare the game state, and set its startup value
         .add_state(GameState::Splash)
         // Adds the plugins for each state
@@ -63,7 +64,39 @@ fn main() {
 }
 
 fn setup(mut commands: Commands) {
-    commands.spawn_bundle(Camera2dBundle::default());
+    commands.spawn_bundle(design_camera());
+}
+
+// the texts and the panels of the menus follow the theme, like the polygons
+fn apply_ui_theme(
+    themes: Res<Themes>,
+    mut previous: Local<Option<Theme>>,
+    mut text_query: Query<&mut Text>,
+    mut ui_color_query: Query<&mut UiColor>,
+) {
+    if !themes.is_changed() {
+        return;
+    }
+
+    let theme = themes.current();
+    let old = if let Some(old) = previous.replace(theme.clone()) {
+        old
+    } else {
+        return;
+    };
+
+    for mut text in text_query.iter_mut() {
+        for section in text.sections.iter_mut() {
+            if section.style.color == old.text {
+                section.style.color = theme.text;
+            }
+        }
+    }
+    for mut ui_color in ui_color_query.iter_mut() {
+        if ui_color.0 == old.panel {
+            ui_color.0 = theme.panel;
+        }
+    }
 }
 
 // mod game {
@@ -1,11 +1,17 @@
 // use crate::game;
 use crate::levels;
 
-use bevy::audio::AudioSink;
+use crate::audio::{AudioSettings, VolumeChannel};
+
 use bevy::prelude::*;
-use shapeshifter_level_maker::util::{PerformedCut, Polygon, SpawnLevel, Target};
+use shapeshifter_level_maker::bindings::{Bindings, ButtonState, InputAction, InputButton};
+use shapeshifter_level_maker::gamepad::GamepadInput;
+use shapeshifter_level_maker::theme::{Theme, Themes};
+use shapeshifter_level_maker::util::{
+    applied_cuts, CutApplied, LevelRules, Polygon, SpawnLevel, Target,
+};
 
-use super::{despawn_screen, GameState, TEXT_COLOR};
+use super::{despawn_screen, GameState};
 
 pub struct MenuPlugin;
 
@@ -20,7 +26,14 @@ impl Plugin for MenuPlugin {
             .add_system_set(
                 SystemSet::on_exit(MenuState::Main).with_system(despawn_screen::<OnMainMenuScreen>),
             )
-            // Systems to handle the settings menu screen
+            // Systems to handle the city menu screen
+            .add_system_set(SystemSet::on_enter(MenuState::Cities).with_system(cities_menu_setup))
+            .add_system_set(
+                SystemSet::on_exit(MenuState::Cities)
+                    .with_system(despawn_screen::<OnCitiesMenuScreen>),
+            )
+            // Systems to handle the settings menu screen, which leads to audio, display and
+            // controls
             .add_system_set(
                 SystemSet::on_enter(MenuState::Settings).with_system(settings_menu_setup),
             )
@@ -28,12 +41,48 @@ impl Plugin for MenuPlugin {
                 SystemSet::on_exit(MenuState::Settings)
                     .with_system(despawn_screen::<OnSettingsMenuScreen>),
             )
+            // Systems to handle the audio menu screen
+            .add_system_set(SystemSet::on_enter(MenuState::Audio).with_system(audio_menu_setup))
+            .add_system_set(
+                SystemSet::on_exit(MenuState::Audio)
+                    .with_system(despawn_screen::<OnAudioMenuScreen>),
+            )
+            .add_system_set(
+                SystemSet::on_update(MenuState::Audio)
+                    .with_system(volume_button)
+                    .with_system(update_volume_widgets),
+            )
+            // Systems to handle the display menu screen, where the theme is chosen
+            .add_system_set(SystemSet::on_enter(MenuState::Display).with_system(display_menu_setup))
+            .add_system_set(
+                SystemSet::on_exit(MenuState::Display)
+                    .with_system(despawn_screen::<OnDisplayMenuScreen>),
+            )
+            .add_system_set(
+                SystemSet::on_update(MenuState::Display).with_system(update_theme_label),
+            )
+            // Systems to handle the controls menu screen
+            .insert_resource(WaitingForBinding::default())
+            .add_system_set(
+                SystemSet::on_enter(MenuState::Controls).with_system(controls_menu_setup),
+            )
+            .add_system_set(
+                SystemSet::on_exit(MenuState::Controls)
+                    .with_system(despawn_screen::<OnControlsMenuScreen>),
+            )
+            .add_system_set(
+                SystemSet::on_update(MenuState::Controls)
+                    .with_system(binding_button)
+                    .with_system(capture_binding)
+                    .with_system(update_binding_labels),
+            )
             // Common systems to all screens that handles buttons behaviour
             .add_system_set(
                 SystemSet::on_update(GameState::Menu)
                     .with_system(menu_action)
                     .with_system(play_cut_sound)
-                    .with_system(button_system),
+                    .with_system(button_system)
+                    .with_system(recolor_buttons),
             )
             .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(remove_poly_target));
     }
@@ -57,7 +106,11 @@ pub struct FontHandles {
 enum MenuState {
     Main,
     // Design,
+    Cities,
     Settings,
+    Audio,
+    Display,
+    Controls,
     Disabled,
 }
 // Tag component used to tag buttons that cannot be interacted with
@@ -68,14 +121,63 @@ pub struct Inactive;
 #[derive(Component)]
 struct OnMainMenuScreen;
 
+// Tag component used to tag entities added on the city menu screen
+#[derive(Component)]
+struct OnCitiesMenuScreen;
+
 // Tag component used to tag entities added on the settings menu screen
 #[derive(Component)]
 struct OnSettingsMenuScreen;
 
-const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
-const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
-const HOVERED_PRESSED_BUTTON: Color = Color::rgb(0.25, 0.65, 0.25);
-const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
+// Tag component used to tag entities added on the audio menu screen
+#[derive(Component)]
+struct OnAudioMenuScreen;
+
+// The - (dir = -1) and + (dir = 1) buttons of a volume slider
+#[derive(Component)]
+struct VolumeButton {
+    channel: VolumeChannel,
+    dir: i32,
+}
+
+// the filled part of a volume slider
+#[derive(Component)]
+struct VolumeBar(VolumeChannel);
+
+#[derive(Component)]
+struct VolumeLabel(VolumeChannel);
+
+#[derive(Component)]
+struct MuteLabel;
+
+// Tag component used to tag entities added on the display menu screen
+#[derive(Component)]
+struct OnDisplayMenuScreen;
+
+#[derive(Component)]
+struct ThemeLabel;
+
+// Tag component used to tag entities added on the controls menu screen
+#[derive(Component)]
+struct OnControlsMenuScreen;
+
+// The button of an action in the controls menu, and the text showing its chords
+#[derive(Component)]
+struct BindingButton(InputAction);
+
+#[derive(Component)]
+struct BindingLabel(InputAction);
+
+//
+//
+// Set when a binding button is clicked: the next key or button pressed becomes the
+// new chord of the action. The click on the binding button itself is ignored by
+// waiting for a frame without any new press before listening (armed).
+#[derive(Default)]
+struct WaitingForBinding {
+    action: Option<InputAction>,
+    armed: bool,
+}
 
 // Tag component used to mark wich setting is currently selected
 #[derive(Component)]
@@ -92,7 +194,24 @@ enum MenuButtonAction {
     Perplexity,
     Complexity,
     // Design,
+    Settings,
+    Audio,
+    ToggleMute,
+    Display,
+    NextTheme,
+    Controls,
+    ResetBindings,
     BackToMainMenu,
+    BackToSettings,
+}
+
+fn button_color(theme: &Theme, interaction: Interaction, selected: bool) -> Color {
+    match (interaction, selected) {
+        (Interaction::Clicked, _) | (Interaction::None, true) => theme.pressed_button,
+        (Interaction::Hovered, true) => theme.hovered_pressed_button,
+        (Interaction::Hovered, false) => theme.hovered_button,
+        (Interaction::None, false) => theme.button,
+    }
 }
 
 // This system handles changing all buttons color based on mouse interaction
@@ -101,25 +220,33 @@ pub fn button_system(
         (&Interaction, &mut UiColor, Option<&SelectedOption>),
         (Changed<Interaction>, With<Button>, Without<Inactive>),
     >,
-    sound_map: Res<crate::menu::SoundMap>,
+    themes: Res<Themes>,
+    mut sound_map: ResMut<crate::sound::SoundMap>,
     audio: Res<Audio>,
 ) {
     for (interaction, mut color, selected) in &mut interaction_query {
-        *color = match (*interaction, selected) {
-            (Interaction::Clicked, _) | (Interaction::None, Some(_)) => {
-                sound_map.play("bip", &audio);
-                PRESSED_BUTTON.into()
-            }
-            (Interaction::Hovered, Some(_)) => {
-                sound_map.play("click", &audio);
-                HOVERED_PRESSED_BUTTON.into()
-            }
-            (Interaction::Hovered, None) => {
-                sound_map.play("click", &audio);
-                HOVERED_BUTTON.into()
-            }
-            (Interaction::None, None) => NORMAL_BUTTON.into(),
+        match (*interaction, selected.is_some()) {
+            (Interaction::Clicked, _) | (Interaction::None, true) => sound_map.play("bip", &audio),
+            (Interaction::Hovered, _) => sound_map.play("click", &audio),
+            (Interaction::None, false) => {}
         }
+        *color = button_color(themes.current(), *interaction, selected.is_some()).into();
+    }
+}
+
+// the buttons of the screen take the colors of a newly chosen theme
+fn recolor_buttons(
+    themes: Res<Themes>,
+    mut button_query: Query<
+        (&Interaction, &mut UiColor, Option<&SelectedOption>),
+        (With<Button>, Without<Inactive>),
+    >,
+) {
+    if !themes.is_changed() {
+        return;
+    }
+    for (interaction, mut color, selected) in &mut button_query {
+        *color = button_color(themes.current(), *interaction, selected.is_some()).into();
     }
 }
 
@@ -134,7 +261,7 @@ pub fn button_system(
 //     for (interaction, button_setting, entity) in &interaction_query {
 //         if *interaction == Interaction::Clicked && *setting != *button_setting {
 //             let (previous_button, mut previous_color) = selected_query.single_mut();
-//             *previous_color = NORMAL_BUTTON.into();
+//             *previous_color = themes.current().button.into();
 //             commands.entity(previous_button).remove::<SelectedOption>();
 //             commands.entity(entity).insert(SelectedOption);
 //             *setting = *button_setting;
@@ -142,127 +269,21 @@ pub fn button_system(
 //     }
 // }
 
-pub struct MusicController(pub Handle<AudioSink>);
-
-use std::collections::HashMap;
-pub struct SoundMap {
-    pub map: HashMap<String, Vec<Handle<AudioSource>>>,
-}
-
-use rand::Rng;
-// use rand::Thread;
-
-impl SoundMap {
-    //
-    //
-    // plays a random sound in the sound group
-    pub fn play(&self, sound_group: &str, audio: &Audio) {
-        let mut rng = rand::thread_rng();
-        let sounds = self.map.get(sound_group).unwrap();
-        // let random_index = rng.gen_range(0..sounds.len());
-        let random_index = rng.gen_range(0..sounds.len()).clone();
-        let sound = &sounds[random_index];
-        audio.play(sound.clone());
-    }
-}
-
-fn menu_setup(
-    mut commands: Commands,
-    mut menu_state: ResMut<State<MenuState>>,
-    asset_server: Res<AssetServer>,
-    // mut menu_music: ResMut<MenuMusic>,
-    audio: Res<Audio>,
-    audio_sinks: Res<Assets<AudioSink>>,
-) {
-    //
-    //
-    // music settings
-    let menu_music_handle = asset_server.load("music/charles_menu.ogg");
-    let handle = audio_sinks.get_handle(
-        audio.play_with_settings(menu_music_handle, bevy::audio::PlaybackSettings::LOOP),
-    );
-    commands.insert_resource(MusicController(handle));
-
+// the menu music is started by update_music(..), see music.rs
+fn menu_setup(mut menu_state: ResMut<State<MenuState>>) {
     //
     //
     // initialize menu state
     let _ = menu_state.set(MenuState::Main);
-
-    //
-    //
-    // sounds
-    let mut sound_map = HashMap::new();
-
-    // let click1 = asset_server.load("sounds/Menu/Menu Click 1.ogg"); // too different
-    let click2 = asset_server.load("sounds/Menu/Menu Click 2.ogg");
-    let click3 = asset_server.load("sounds/Menu/Menu Click 3.ogg");
-    let click4 = asset_server.load("sounds/Menu/Menu Click 4.ogg");
-    let click5 = asset_server.load("sounds/Menu/Menu Click 5.ogg");
-    let click7 = asset_server.load("sounds/Menu/Menu Click 7.ogg");
-    let bip = asset_server.load("sounds/Menu/Menu Bip.ogg");
-
-    // let scissor1 = asset_server.load("sounds/Scissor Cut/Scissor paper cut 1.ogg"); // too long
-    let scissor2 = asset_server.load("sounds/Scissor Cut/Scissor paper cut 2.ogg");
-    let scissor3 = asset_server.load("sounds/Scissor Cut/Scissor paper cut 3.ogg");
-    // let scissor4 = asset_server.load("sounds/Scissor Cut/Scissor paper cut 4.ogg"); // too long
-    let scissor5 = asset_server.load("sounds/Scissor Cut/Scissor paper cut 5.ogg");
-    let scissor6 = asset_server.load("sounds/Scissor Cut/Scissor paper cut 6.ogg");
-
-    let place1 = asset_server.load("sounds/Placement Ding/Placement Ding 1.ogg");
-    let place2 = asset_server.load("sounds/Placement Ding/Placement Ding 2.ogg");
-    let place3 = asset_server.load("sounds/Placement Ding/Placement Ding 3.ogg");
-    let place4 = asset_server.load("sounds/Placement Ding/Placement Ding 4.ogg");
-
-    // let victoy1 = asset_server.load("sounds/Victory Sounds/Victory Cartoon 1.ogg");
-    // let victoy2 = asset_server.load("sounds/Victory Sounds/Victory Cartoon 2.ogg");
-    let victoy3 = asset_server.load("sounds/Victory Sounds/Victory Cartoon 3.ogg");
-    // let victory_chord1 = asset_server.load("sounds/Victory Sounds/Victory Chord 1.ogg");
-    // let victory_chord2 = asset_server.load("sounds/Victory Sounds/Victory Chord 2.ogg");
-    // let victory_chord3 = asset_server.load("sounds/Victory Sounds/Victory Chord 3.ogg");
-    // let victory_chord4 = asset_server.load("sounds/Victory Sounds/Victory Chord 4.ogg");
-    // let victory_ukulele = asset_server.load("sounds/Victory Sounds/Victory Ukulele.ogg");
-
-    let final_victory = asset_server.load("sounds/Victory Finale.ogg");
-
-    sound_map.insert(
-        "click".to_string(),
-        vec![click2, click3, click4, click5, click7],
-    );
-    sound_map.insert("bip".to_string(), vec![bip]);
-
-    sound_map.insert(
-        "cut".to_string(),
-        vec![scissor2, scissor3, scissor5, scissor6],
-    );
-
-    sound_map.insert("target".to_string(), vec![place1, place2, place3, place4]);
-
-    sound_map.insert(
-        "victory".to_string(),
-        vec![
-            // victoy1,
-            // victoy2,
-            victoy3,
-            // victory_chord1,
-            // victory_chord2,
-            // victory_chord3,
-            // victory_chord4,
-            // victory_ukulele,
-        ],
-    );
-
-    sound_map.insert("final_victory".to_string(), vec![final_victory]);
-
-    commands.insert_resource(SoundMap { map: sound_map });
 }
 
 fn play_cut_sound(
-    mut performed_cut_event_reader: EventReader<PerformedCut>,
+    mut cut_applied_event_reader: EventReader<CutApplied>,
 
-    sound_map: Res<crate::menu::SoundMap>,
+    mut sound_map: ResMut<crate::sound::SoundMap>,
     audio: Res<Audio>,
 ) {
-    for _ in performed_cut_event_reader.iter() {
+    for _ in applied_cuts(&mut cut_applied_event_reader) {
         sound_map.play("cut", &audio);
     }
 }
@@ -272,6 +293,7 @@ fn main_menu_setup(
     asset_server: Res<AssetServer>,
     mut spawn_level_event_writer: EventWriter<SpawnLevel>,
     current_level: Res<crate::levels::CurrentLevel>,
+    themes: Res<Themes>,
 ) {
     let new_game_label = if current_level.level == crate::levels::Level::Tutorial(0) {
         "New Game"
@@ -284,6 +306,9 @@ fn main_menu_setup(
         target: "shark1".to_string(),
         target_multiplier: 1.1,
         number_of_cuts: 1000,
+        rules: LevelRules::default(),
+        pieces: Vec::new(),
+        obstacles: Vec::new(),
     });
 
     // let font = asset_server.load("fonts/FiraSans-Bold.ttf");
@@ -316,7 +341,7 @@ fn main_menu_setup(
     let button_text_style = TextStyle {
         font: font.clone(),
         font_size: 60.0,
-        color: TEXT_COLOR,
+        color: themes.current().text,
     };
 
     commands
@@ -327,7 +352,7 @@ fn main_menu_setup(
                 align_items: AlignItems::Center,
                 ..default()
             },
-            color: Color::PURPLE.into(),
+            color: themes.current().panel.into(),
             ..default()
         })
         .insert(OnMainMenuScreen)
@@ -339,7 +364,7 @@ fn main_menu_setup(
                     TextStyle {
                         font: font.clone(),
                         font_size: 110.0,
-                        color: TEXT_COLOR,
+                        color: themes.current().text,
                     },
                 )
                 .with_style(Style {
@@ -355,7 +380,7 @@ fn main_menu_setup(
             parent
                 .spawn_bundle(ButtonBundle {
                     style: button_style.clone(),
-                    color: NORMAL_BUTTON.into(),
+                    color: themes.current().button.into(),
                     ..default()
                 })
                 .insert(MenuButtonAction::Play)
@@ -374,7 +399,7 @@ fn main_menu_setup(
             parent
                 .spawn_bundle(ButtonBundle {
                     style: button_style.clone(),
-                    color: NORMAL_BUTTON.into(),
+                    color: themes.current().button.into(),
                     ..default()
                 })
                 .insert(MenuButtonAction::GoToCity)
@@ -390,16 +415,585 @@ fn main_menu_setup(
                         button_text_style.clone(),
                     ));
                 });
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: themes.current().button.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::Settings)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle::from_section(
+                        "Settings",
+                        button_text_style.clone(),
+                    ));
+                });
+        });
+}
+
+//
+//
+// The audio and the controls pages, each with a Back button to this page
+fn settings_menu_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    themes: Res<Themes>,
+) {
+    let button_style = Style {
+        size: Size::new(Val::Px(300.0), Val::Px(65.0)),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    let button_text_style = TextStyle {
+        font: asset_server.load("fonts/poly.ttf"),
+        font_size: 60.0,
+        color: themes.current().text,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                margin: UiRect::all(Val::Auto),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: themes.current().panel.into(),
+            ..default()
+        })
+        .insert(OnSettingsMenuScreen)
+        .with_children(|parent| {
+            for (action, text) in [
+                (MenuButtonAction::Audio, "Audio"),
+                (MenuButtonAction::Display, "Display"),
+                (MenuButtonAction::Controls, "Controls"),
+                (MenuButtonAction::BackToMainMenu, "Back"),
+            ] {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: button_style.clone(),
+                        color: themes.current().button.into(),
+                        ..default()
+                    })
+                    .insert(action)
+                    .with_children(|parent2| {
+                        parent2.spawn_bundle(TextBundle::from_section(
+                            text,
+                            button_text_style.clone(),
+                        ));
+                    });
+            }
+        });
+}
+
+fn binding_text(action: InputAction, bindings: &Bindings, waiting: &WaitingForBinding) -> String {
+    if waiting.action == Some(action) {
+        return format!("{}: press a key...", action.label());
+    }
+    format!(
+        "{}: {}",
+        action.label(),
+        bindings.chord_labels(action).join(" / ")
+    )
+}
+
+fn controls_menu_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    bindings: Res<Bindings>,
+    mut waiting: ResMut<WaitingForBinding>,
+    themes: Res<Themes>,
+) {
+    *waiting = WaitingForBinding::default();
+
+    let button_style = Style {
+        size: Size::new(Val::Px(500.0), Val::Px(40.0)),
+        margin: UiRect::all(Val::Px(4.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    // the rows of the actions are thinner than the buttons below, so that the two
+    // columns and the buttons fit in the 720px of the design height
+    let binding_button_style = Style {
+        size: Size::new(Val::Px(500.0), Val::Px(34.0)),
+        margin: UiRect::all(Val::Px(2.0)),
+        ..button_style.clone()
+    };
+
+    let column_style = Style {
+        flex_direction: FlexDirection::ColumnReverse,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    let binding_text_style = TextStyle {
+        font: asset_server.load("fonts/poly.ttf"),
+        font_size: 24.0,
+        color: themes.current().text,
+    };
+
+    let button_text_style = TextStyle {
+        font: asset_server.load("fonts/poly.ttf"),
+        font_size: 40.0,
+        color: themes.current().text,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                margin: UiRect::all(Val::Auto),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: themes.current().panel.into(),
+            ..default()
+        })
+        .insert(OnControlsMenuScreen)
+        .with_children(|parent| {
+            //
+            //
+            // one button per action, showing all its chords. Click to rebind.
+            // The actions are split in two columns to fit the screen
+            let half = (InputAction::PLAYER_ACTIONS.len() + 1) / 2;
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::FlexStart,
+                        ..default()
+                    },
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent2| {
+                    for actions in InputAction::PLAYER_ACTIONS.chunks(half) {
+                        parent2
+                            .spawn_bundle(NodeBundle {
+                                style: column_style.clone(),
+                                color: Color::NONE.into(),
+                                ..default()
+                            })
+                            .with_children(|parent3| {
+                                for action in actions {
+                                    parent3
+                                        .spawn_bundle(ButtonBundle {
+                                            style: binding_button_style.clone(),
+                                            color: themes.current().button.into(),
+                                            ..default()
+                                        })
+                                        .insert(BindingButton(*action))
+                                        .with_children(|parent4| {
+                                            parent4
+                                                .spawn_bundle(TextBundle::from_section(
+                                                    binding_text(
+                                                        *action,
+                                                        &bindings,
+                                                        &WaitingForBinding::default(),
+                                                    ),
+                                                    binding_text_style.clone(),
+                                                ))
+                                                .insert(BindingLabel(*action));
+                                        });
+                                }
+                            });
+                    }
+                });
+
+            for (action, text) in [
+                (MenuButtonAction::ResetBindings, "Reset defaults"),
+                (MenuButtonAction::BackToSettings, "Back"),
+            ] {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: button_style.clone(),
+                        color: themes.current().button.into(),
+                        ..default()
+                    })
+                    .insert(action)
+                    .with_children(|parent2| {
+                        parent2.spawn_bundle(TextBundle::from_section(
+                            text,
+                            button_text_style.clone(),
+                        ));
+                    });
+            }
+        });
+}
+
+// clicking on the button of an action starts listening for its new chord
+fn binding_button(
+    interaction_query: Query<(&Interaction, &BindingButton), (Changed<Interaction>, With<Button>)>,
+    mut waiting: ResMut<WaitingForBinding>,
+) {
+    for (interaction, binding_button) in &interaction_query {
+        if *interaction == Interaction::Clicked && waiting.action.is_none() {
+            waiting.action = Some(binding_button.0);
+            waiting.armed = false;
+        }
+    }
+}
+
+//
+//
+// The first key or button pressed becomes the trigger of the new chord, and the keys
+// held at that moment become the held keys (e.g. hold LControl, then click)
+fn capture_binding(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<GamepadInput>,
+    mut bindings: ResMut<Bindings>,
+    mut waiting: ResMut<WaitingForBinding>,
+) {
+    let action = if let Some(action) = waiting.action {
+        action
+    } else {
+        return;
+    };
+
+    let state = ButtonState {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad: &gamepad_input,
+        wheel_up: false,
+        wheel_down: false,
+    };
+
+    let maybe_trigger = state.first_just_pressed();
+
+    if !waiting.armed {
+        if maybe_trigger.is_none() {
+            waiting.armed = true;
+        }
+        return;
+    }
+
+    if let Some(trigger) = maybe_trigger {
+        let held: Vec<InputButton> = keyboard_input
+            .get_pressed()
+            .map(|key| InputButton::Key(*key))
+            .chain(
+                mouse_button_input
+                    .get_pressed()
+                    .map(|button| InputButton::Mouse(*button)),
+            )
+            .chain(
+                gamepad_input
+                    .get_pressed()
+                    .map(|button| InputButton::Gamepad(*button)),
+            )
+            .filter(|button| *button != trigger)
+            .collect();
+
+        bindings.rebind(action, held, trigger);
+        bindings.save();
+        *waiting = WaitingForBinding::default();
+    }
+}
+
+fn update_binding_labels(
+    bindings: Res<Bindings>,
+    waiting: Res<WaitingForBinding>,
+    mut text_query: Query<(&mut Text, &BindingLabel)>,
+) {
+    if !bindings.is_changed() && !waiting.is_changed() {
+        return;
+    }
+    for (mut text, binding_label) in text_query.iter_mut() {
+        text.sections[0].value = binding_text(binding_label.0, &bindings, &waiting);
+    }
+}
+
+fn volume_text(channel: VolumeChannel, settings: &AudioSettings) -> String {
+    format!("{}: {}%", channel.label(), settings.volume(channel).0)
+}
+
+fn mute_text(settings: &AudioSettings) -> String {
+    if settings.muted {
+        "Sound: off".to_string()
+    } else {
+        "Sound: on".to_string()
+    }
+}
+
+fn theme_text(themes: &Themes) -> String {
+    format!("Theme: {}", themes.current().name)
+}
+
+fn update_theme_label(themes: Res<Themes>, mut label_query: Query<&mut Text, With<ThemeLabel>>) {
+    if !themes.is_changed() {
+        return;
+    }
+    for mut text in label_query.iter_mut() {
+        text.sections[0].value = theme_text(&themes);
+    }
+}
+
+// the colour theme, which recolors the game as soon as it is chosen, see apply_theme(..)
+fn display_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>, themes: Res<Themes>) {
+    let button_style = Style {
+        size: Size::new(Val::Px(300.0), Val::Px(65.0)),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    let button_text_style = TextStyle {
+        font: asset_server.load("fonts/poly.ttf"),
+        font_size: 60.0,
+        color: themes.current().text,
+    };
+
+    let label_text_style = TextStyle {
+        font: asset_server.load("fonts/poly.ttf"),
+        font_size: 40.0,
+        color: themes.current().text,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                margin: UiRect::all(Val::Auto),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: themes.current().panel.into(),
+            ..default()
+        })
+        .insert(OnDisplayMenuScreen)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(500.0), Val::Px(65.0)),
+                        ..button_style.clone()
+                    },
+                    color: themes.current().button.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::NextTheme)
+                .with_children(|parent2| {
+                    parent2
+                        .spawn_bundle(TextBundle::from_section(
+                            theme_text(&themes),
+                            label_text_style.clone(),
+                        ))
+                        .insert(ThemeLabel);
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: themes.current().button.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::BackToSettings)
+                .with_children(|parent2| {
+                    parent2
+                        .spawn_bundle(TextBundle::from_section("Back", button_text_style.clone()));
+                });
         });
 }
 
+//
+//
+// One slider per VolumeChannel: a - button, a bar filled up to the volume and a + button
+fn audio_menu_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    audio_settings: Res<AudioSettings>,
+    themes: Res<Themes>,
+) {
+    let button_style = Style {
+        size: Size::new(Val::Px(300.0), Val::Px(65.0)),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    let small_button_style = Style {
+        size: Size::new(Val::Px(50.0), Val::Px(50.0)),
+        margin: UiRect::all(Val::Px(10.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    let bar_style = Style {
+        size: Size::new(Val::Px(300.0), Val::Px(20.0)),
+        ..default()
+    };
+
+    let button_text_style = TextStyle {
+        font: asset_server.load("fonts/poly.ttf"),
+        font_size: 60.0,
+        color: themes.current().text,
+    };
+
+    let label_text_style = TextStyle {
+        font: asset_server.load("fonts/poly.ttf"),
+        font_size: 40.0,
+        color: themes.current().text,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                margin: UiRect::all(Val::Auto),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: themes.current().panel.into(),
+            ..default()
+        })
+        .insert(OnAudioMenuScreen)
+        .with_children(|parent| {
+            for channel in VolumeChannel::ALL {
+                parent
+                    .spawn_bundle(TextBundle::from_section(
+                        volume_text(channel, &audio_settings),
+                        label_text_style.clone(),
+                    ))
+                    .insert(VolumeLabel(channel));
+
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        color: Color::NONE.into(),
+                        ..default()
+                    })
+                    .with_children(|parent2| {
+                        for (dir, text) in [(-1, "-"), (1, "+")] {
+                            if dir > 0 {
+                                // the bar sits between the two buttons
+                                parent2
+                                    .spawn_bundle(NodeBundle {
+                                        style: bar_style.clone(),
+                                        color: themes.current().button.into(),
+                                        ..default()
+                                    })
+                                    .with_children(|parent3| {
+                                        parent3
+                                            .spawn_bundle(NodeBundle {
+                                                style: Style {
+                                                    size: Size::new(
+                                                        Val::Percent(
+                                                            audio_settings.volume(channel).0 as f32,
+                                                        ),
+                                                        Val::Percent(100.0),
+                                                    ),
+                                                    ..default()
+                                                },
+                                                color: themes.current().pressed_button.into(),
+                                                ..default()
+                                            })
+                                            .insert(VolumeBar(channel));
+                                    });
+                            }
+
+                            parent2
+                                .spawn_bundle(ButtonBundle {
+                                    style: small_button_style.clone(),
+                                    color: themes.current().button.into(),
+                                    ..default()
+                                })
+                                .insert(VolumeButton { channel, dir })
+                                .with_children(|parent3| {
+                                    parent3.spawn_bundle(TextBundle::from_section(
+                                        text,
+                                        label_text_style.clone(),
+                                    ));
+                                });
+                        }
+                    });
+            }
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: themes.current().button.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::ToggleMute)
+                .with_children(|parent2| {
+                    parent2
+                        .spawn_bundle(TextBundle::from_section(
+                            mute_text(&audio_settings),
+                            button_text_style.clone(),
+                        ))
+                        .insert(MuteLabel);
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: themes.current().button.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::BackToSettings)
+                .with_children(|parent2| {
+                    parent2
+                        .spawn_bundle(TextBundle::from_section("Back", button_text_style.clone()));
+                });
+        });
+}
+
+// the volumes are saved on every click, like the bindings
+fn volume_button(
+    interaction_query: Query<(&Interaction, &VolumeButton), (Changed<Interaction>, With<Button>)>,
+    mut audio_settings: ResMut<AudioSettings>,
+) {
+    for (interaction, volume_button) in &interaction_query {
+        if *interaction == Interaction::Clicked {
+            audio_settings
+                .volume_mut(volume_button.channel)
+                .step(volume_button.dir);
+            audio_settings.save();
+        }
+    }
+}
+
+fn update_volume_widgets(
+    audio_settings: Res<AudioSettings>,
+    mut bar_query: Query<(&mut Style, &VolumeBar)>,
+    mut label_query: Query<(&mut Text, &VolumeLabel), Without<MuteLabel>>,
+    mut mute_query: Query<&mut Text, With<MuteLabel>>,
+) {
+    if !audio_settings.is_changed() {
+        return;
+    }
+    for (mut style, volume_bar) in bar_query.iter_mut() {
+        style.size.width = Val::Percent(audio_settings.volume(volume_bar.0).0 as f32);
+    }
+    for (mut text, volume_label) in label_query.iter_mut() {
+        text.sections[0].value = volume_text(volume_label.0, &audio_settings);
+    }
+    for mut text in mute_query.iter_mut() {
+        text.sections[0].value = mute_text(&audio_settings);
+    }
+}
+
 // #[derive(Component)]
 // pub struct LockIcon;
 
-fn settings_menu_setup(
+fn cities_menu_setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     unlocked_cities: Res<levels::UnlockedCities>,
+    themes: Res<Themes>,
 ) {
     let button_style = Style {
         size: Size::new(Val::Px(300.0), Val::Px(65.0)),
@@ -413,7 +1007,7 @@ fn settings_menu_setup(
         // font: asset_server.load("fonts/FiraSans-Bold.ttf"),
         font: asset_server.load("fonts/poly.ttf"),
         font_size: 60.0,
-        color: TEXT_COLOR,
+        color: themes.current().text,
     };
 
     let button_icon_style = Style {
@@ -440,10 +1034,10 @@ fn settings_menu_setup(
                 align_items: AlignItems::Center,
                 ..default()
             },
-            color: Color::PURPLE.into(),
+            color: themes.current().panel.into(),
             ..default()
         })
-        .insert(OnSettingsMenuScreen)
+        .insert(OnCitiesMenuScreen)
         .with_children(|parent| {
             for (action, text) in [
                 (MenuButtonAction::Tutorial, "Tutorial"),
@@ -453,7 +1047,7 @@ fn settings_menu_setup(
             ] {
                 let button_bundle = ButtonBundle {
                     style: button_style.clone(),
-                    color: NORMAL_BUTTON.into(),
+                    color: themes.current().button.into(),
                     ..default()
                 };
 
@@ -505,7 +1099,7 @@ fn settings_menu_setup(
             // parent
             //     .spawn_bundle(ButtonBundle {
             //         style: button_style.clone(),
-            //         color: NORMAL_BUTTON.into(),
+            //         color: themes.current().button.into(),
             //         ..default()
             //     })
             //     .insert(MenuButtonAction::Design)
@@ -519,7 +1113,7 @@ fn settings_menu_setup(
             parent
                 .spawn_bundle(ButtonBundle {
                     style: button_style.clone(),
-                    color: NORMAL_BUTTON.into(),
+                    color: themes.current().button.into(),
                     ..default()
                 })
                 .insert(MenuButtonAction::BackToMainMenu)
@@ -538,9 +1132,10 @@ fn menu_action(
     // mut app_exit_events: EventWriter<AppExit>,
     mut menu_state: ResMut<State<MenuState>>,
     mut game_state: ResMut<State<GameState>>,
-    music_controller: Res<MusicController>,
-    audio_sinks: Res<Assets<AudioSink>>,
     mut current_level: ResMut<crate::levels::CurrentLevel>,
+    mut bindings: ResMut<Bindings>,
+    mut audio_settings: ResMut<AudioSettings>,
+    mut themes: ResMut<Themes>,
 ) {
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Clicked {
@@ -550,20 +1145,27 @@ fn menu_action(
                     // game_state.set(GameState::Game).unwrap();
                     game_state.set(GameState::CityTitle).unwrap();
                     menu_state.set(MenuState::Disabled).unwrap();
-                    if let Some(sink) = audio_sinks.get(&music_controller.0) {
-                        sink.stop();
-                    }
                 }
-                MenuButtonAction::GoToCity => menu_state.set(MenuState::Settings).unwrap(),
+                MenuButtonAction::GoToCity => menu_state.set(MenuState::Cities).unwrap(),
+                MenuButtonAction::Settings => menu_state.set(MenuState::Settings).unwrap(),
+                MenuButtonAction::Controls => menu_state.set(MenuState::Controls).unwrap(),
+                MenuButtonAction::Audio => menu_state.set(MenuState::Audio).unwrap(),
+                MenuButtonAction::Display => menu_state.set(MenuState::Display).unwrap(),
+                MenuButtonAction::ToggleMute => {
+                    audio_settings.muted = !audio_settings.muted;
+                    audio_settings.save();
+                }
+                MenuButtonAction::NextTheme => themes.select_next(),
+                MenuButtonAction::ResetBindings => {
+                    *bindings = Bindings::default();
+                    bindings.save();
+                }
 
                 MenuButtonAction::Tutorial => {
                     current_level.level = crate::levels::Level::Tutorial(0);
                     // game_state.set(GameState::Game).unwrap();
                     game_state.set(GameState::CityTitle).unwrap();
                     menu_state.set(MenuState::Disabled).unwrap();
-                    if let Some(sink) = audio_sinks.get(&music_controller.0) {
-                        sink.stop();
-                    }
                 }
 
                 MenuButtonAction::Simplicity => {
@@ -571,9 +1173,6 @@ fn menu_action(
                     // game_state.set(GameState::Game).unwrap();
                     game_state.set(GameState::CityTitle).unwrap();
                     menu_state.set(MenuState::Disabled).unwrap();
-                    if let Some(sink) = audio_sinks.get(&music_controller.0) {
-                        sink.stop();
-                    }
                 }
 
                 // MenuButtonAction::Convexity => {
@@ -589,18 +1188,12 @@ fn menu_action(
                     current_level.level = crate::levels::Level::Perplexity(0);
                     game_state.set(GameState::CityTitle).unwrap();
                     menu_state.set(MenuState::Disabled).unwrap();
-                    if let Some(sink) = audio_sinks.get(&music_controller.0) {
-                        sink.stop();
-                    }
                 }
 
                 MenuButtonAction::Complexity => {
                     current_level.level = crate::levels::Level::Complexity(0);
                     game_state.set(GameState::CityTitle).unwrap();
                     menu_state.set(MenuState::Disabled).unwrap();
-                    if let Some(sink) = audio_sinks.get(&music_controller.0) {
-                        sink.stop();
-                    }
                 }
 
                 // MenuButtonAction::Design => {
@@ -609,11 +1202,11 @@ fn menu_action(
                 //     // info!("menu state: {:?}", menu_state);
                 // }
                 MenuButtonAction::BackToMainMenu => {
-                    if let Some(sink) = audio_sinks.get(&music_controller.0) {
-                        sink.play();
-                    }
                     menu_state.set(MenuState::Main).unwrap();
                 }
+                MenuButtonAction::BackToSettings => {
+                    menu_state.set(MenuState::Settings).unwrap();
+                }
             }
         }
     }
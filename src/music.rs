@@ -0,0 +1,219 @@
+use bevy::audio::AudioSink;
+use bevy::prelude::*;
+
+use serde::Deserialize;
+
+use shapeshifter_level_maker::util::Polygon;
+
+use super::GameState;
+use crate::audio::AudioSettings;
+use crate::levels::{City, CurrentLevel};
+use crate::sound::{SoundManifest, SoundMap};
+
+// time to fade a track in or out when the GameState or the city changes
+pub const CROSSFADE_TIME: f32 = 2.0;
+
+// time to bring an intensity layer in or out
+pub const LAYER_FADE_TIME: f32 = 1.5;
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MusicController::default())
+            .add_system(update_music);
+    }
+}
+
+fn full_volume() -> f32 {
+    1.0
+}
+
+// a stem played on top of the base of the track, once `threshold` of the pieces are in
+// the target (0.5 is half of the pieces). A copy of the base with some volume works as
+// a louder base until the track has real stems
+#[derive(Deserialize, Debug, Clone)]
+pub struct MusicLayerDesc {
+    pub path: String,
+    #[serde(default)]
+    pub threshold: f32,
+    #[serde(default = "full_volume")]
+    pub volume: f32,
+}
+
+// the "music" section of the sound manifest: one track for the menu and one per city.
+// A track without a base is silent, for the cities that have no music yet
+#[derive(Deserialize, Debug, Clone)]
+pub struct MusicTrackDesc {
+    #[serde(default)]
+    pub base: Option<String>,
+    #[serde(default)]
+    pub layers: Vec<MusicLayerDesc>,
+}
+
+struct MusicLayer {
+    sink: Handle<AudioSink>,
+    threshold: f32,
+    volume: f32,
+    // from 0 (silent) to 1, follows the puzzle progress
+    level: f32,
+}
+
+struct PlayingTrack {
+    name: String,
+    layers: Vec<MusicLayer>,
+    // from 0 (silent) to 1, for the crossfades
+    fade: f32,
+    fading_out: bool,
+}
+
+//
+//
+// The tracks that are playing: the current one, and the ones fading out. All the layers
+// of a track loop together from the start, most of them silent. The layers are brought
+// in by raising their volume, which keeps them in sync.
+#[derive(Default)]
+pub struct MusicController {
+    tracks: Vec<PlayingTrack>,
+}
+
+fn track_name(game_state: &GameState, current_level: &CurrentLevel) -> Option<&'static str> {
+    match game_state {
+        GameState::Splash => None,
+        GameState::Menu | GameState::Design => Some("menu"),
+        GameState::CityTitle | GameState::Game => Some(match current_level.level.city() {
+            City::Tutorial => "tutorial",
+            City::Simplicity => "simplicity",
+            City::Perplexity => "perplexity",
+            City::Complexity => "complexity",
+        }),
+    }
+}
+
+fn update_music(
+    game_state: Res<State<GameState>>,
+    current_level: Res<CurrentLevel>,
+    sound_map: Res<SoundMap>,
+    manifests: Res<Assets<SoundManifest>>,
+    polygon_query: Query<&Polygon>,
+    mut music_controller: ResMut<MusicController>,
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    audio_settings: Res<AudioSettings>,
+    time: Res<Time>,
+) {
+    // the manifest is loaded asynchronously
+    let manifest = if let Some(manifest) = manifests.get(&sound_map.manifest) {
+        manifest
+    } else {
+        return;
+    };
+
+    // crossfade to the track of the GameState. A city without a track is silent
+    let wanted = track_name(game_state.current(), &current_level);
+
+    let playing = music_controller
+        .tracks
+        .iter()
+        .find(|track| !track.fading_out)
+        .map(|track| track.name.clone());
+
+    if playing.as_deref() != wanted {
+        for track in music_controller.tracks.iter_mut() {
+            track.fading_out = true;
+        }
+
+        let desc = wanted.and_then(|name| manifest.music.get(name));
+        if let (Some(name), Some(desc)) = (wanted, desc) {
+            // the layers of a track without a base are not played either
+            let layers = match &desc.base {
+                Some(base) => std::iter::once((base.as_str(), 0.0, 1.0))
+                    .chain(
+                        desc.layers
+                            .iter()
+                            .map(|layer| (layer.path.as_str(), layer.threshold, layer.volume)),
+                    )
+                    .map(|(path, threshold, volume)| MusicLayer {
+                        sink: audio_sinks.get_handle(audio.play_with_settings(
+                            asset_server.load(path),
+                            PlaybackSettings::LOOP.with_volume(0.0),
+                        )),
+                        threshold,
+                        volume,
+                        level: 0.0,
+                    })
+                    .collect(),
+                None => Vec::new(),
+            };
+
+            music_controller.tracks.push(PlayingTrack {
+                name: name.to_string(),
+                layers,
+                fade: 0.0,
+                fading_out: false,
+            });
+        }
+    }
+
+    //
+    //
+    // fraction of the pieces that are in the target, also for the puzzle of the menu
+    let total = polygon_query.iter().count();
+    let progress = if total > 0 {
+        polygon_query
+            .iter()
+            .filter(|polygon| polygon.in_target)
+            .count() as f32
+            / total as f32
+    } else {
+        0.0
+    };
+
+    let dt = time.delta_seconds();
+    for track in music_controller.tracks.iter_mut() {
+        let fade_step = dt / CROSSFADE_TIME;
+        track.fade = if track.fading_out {
+            (track.fade - fade_step).max(0.0)
+        } else {
+            (track.fade + fade_step).min(1.0)
+        };
+
+        for layer in track.layers.iter_mut() {
+            let target_level = if progress >= layer.threshold {
+                1.0
+            } else {
+                0.0
+            };
+            let layer_step = dt / LAYER_FADE_TIME;
+            layer.level = if layer.level < target_level {
+                (layer.level + layer_step).min(target_level)
+            } else {
+                (layer.level - layer_step).max(target_level)
+            };
+
+            // the sink exists once the audio source is loaded
+            if let Some(sink) = audio_sinks.get(&layer.sink) {
+                sink.set_volume(
+                    audio_settings.music_volume() * track.fade * layer.level * layer.volume,
+                );
+                if track.fading_out && track.fade <= 0.0 {
+                    sink.stop();
+                }
+            }
+        }
+    }
+
+    //
+    //
+    // a track that faded out before its sinks were loaded is kept until the sinks can be
+    // stopped, or they would keep looping silently
+    music_controller.tracks.retain(|track| {
+        !(track.fading_out
+            && track.fade <= 0.0
+            && track
+                .layers
+                .iter()
+                .all(|layer| audio_sinks.get(&layer.sink).is_some()))
+    });
+}
@@ -0,0 +1,225 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, Instant},
+};
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+
+use serde::Deserialize;
+
+use std::collections::HashMap;
+
+use crate::audio::AudioSettings;
+use crate::music::MusicTrackDesc;
+
+// the sound groups, edited by the sound designers. The groups are rebuilt when the file
+// is modified, if the AssetServerSettings watch for changes
+pub const SOUND_MANIFEST: &str = "sounds/manifest.sounds.json";
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<SoundManifest>()
+            .init_asset_loader::<SoundManifestLoader>()
+            .add_startup_system(load_sound_manifest)
+            .add_system(build_sound_map);
+    }
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SoundEntry {
+    pub path: String,
+    // chance of being picked, relative to the other sounds of the group
+    #[serde(default = "default_one")]
+    pub weight: f32,
+    #[serde(default = "default_one")]
+    pub volume: f32,
+}
+
+//
+//
+// A group of interchangeable sounds, e.g. all the scissor cuts. Every time the group is
+// played, the pitch and the volume are changed by a random amount up to their variation
+// (0.1 is 10%). The group is not played again before `cooldown` seconds.
+#[derive(Deserialize, Debug, Clone)]
+pub struct SoundGroupDesc {
+    pub sounds: Vec<SoundEntry>,
+    #[serde(default)]
+    pub pitch_variation: f32,
+    #[serde(default)]
+    pub volume_variation: f32,
+    #[serde(default)]
+    pub cooldown: f32,
+}
+
+#[derive(Deserialize, Debug, Clone, TypeUuid)]
+#[uuid = "3c1b6a2e-8f4d-4c59-9e0b-7a5d2f61c8b4"]
+pub struct SoundManifest {
+    pub groups: HashMap<String, SoundGroupDesc>,
+    // see music.rs
+    #[serde(default)]
+    pub music: HashMap<String, MusicTrackDesc>,
+}
+
+#[derive(Default)]
+pub struct SoundManifestLoader;
+
+impl AssetLoader for SoundManifestLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let manifest: SoundManifest = serde_json::from_slice(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(manifest));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sounds.json"]
+    }
+}
+
+pub struct SoundGroup {
+    pub sounds: Vec<Handle<AudioSource>>,
+    pub weights: Option<WeightedIndex<f32>>,
+    pub volumes: Vec<f32>,
+    pub pitch_variation: f32,
+    pub volume_variation: f32,
+    pub cooldown: f32,
+    pub last_played: Option<Instant>,
+}
+
+pub struct SoundMap {
+    pub manifest: Handle<SoundManifest>,
+    pub groups: HashMap<String, SoundGroup>,
+    // effects volume of the AudioSettings, see apply_audio_settings(..)
+    pub volume: f32,
+}
+
+impl SoundMap {
+    //
+    //
+    // plays a random sound in the sound group
+    pub fn play(&mut self, sound_group: &str, audio: &Audio) {
+        let group = if let Some(group) = self.groups.get_mut(sound_group) {
+            group
+        } else {
+            // the manifest is loaded asynchronously, the first sounds can be missed
+            if !self.groups.is_empty() {
+                warn!("no sound group \"{}\" in {}", sound_group, SOUND_MANIFEST);
+            }
+            return;
+        };
+
+        if group.sounds.is_empty() {
+            return;
+        }
+
+        let now = Instant::now();
+        if let Some(last_played) = group.last_played {
+            if now.duration_since(last_played).as_secs_f32() < group.cooldown {
+                return;
+            }
+        }
+        group.last_played = Some(now);
+
+        let mut rng = rand::thread_rng();
+        let index = match &group.weights {
+            Some(weights) => weights.sample(&mut rng),
+            None => rng.gen_range(0..group.sounds.len()),
+        };
+
+        let pitch = 1.0 + rng.gen_range(-1.0f32..=1.0) * group.pitch_variation;
+        let volume = group.volumes[index]
+            * (1.0 + rng.gen_range(-1.0f32..=1.0) * group.volume_variation)
+            * self.volume;
+
+        audio.play_with_settings(
+            group.sounds[index].clone(),
+            PlaybackSettings::ONCE
+                .with_volume(volume.max(0.0))
+                .with_speed(pitch.max(0.01)),
+        );
+    }
+}
+
+fn load_sound_manifest(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    audio_settings: Res<AudioSettings>,
+) {
+    commands.insert_resource(SoundMap {
+        manifest: asset_server.load(SOUND_MANIFEST),
+        groups: HashMap::new(),
+        volume: audio_settings.effects_volume(),
+    });
+}
+
+// (re)builds the sound groups when the manifest is loaded or modified
+fn build_sound_map(
+    mut sound_map: ResMut<SoundMap>,
+    mut manifest_events: EventReader<AssetEvent<SoundManifest>>,
+    manifests: Res<Assets<SoundManifest>>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in manifest_events.iter() {
+        let handle = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { .. } => continue,
+        };
+        if *handle != sound_map.manifest {
+            continue;
+        }
+        let manifest = if let Some(manifest) = manifests.get(handle) {
+            manifest
+        } else {
+            continue;
+        };
+
+        let mut groups = HashMap::new();
+        for (name, desc) in manifest.groups.iter() {
+            let weights = WeightedIndex::new(desc.sounds.iter().map(|sound| sound.weight));
+            if weights.is_err() && !desc.sounds.is_empty() {
+                warn!(
+                    "invalid weights in sound group \"{}\", using equal weights",
+                    name
+                );
+            }
+
+            groups.insert(
+                name.clone(),
+                SoundGroup {
+                    sounds: desc
+                        .sounds
+                        .iter()
+                        .map(|sound| asset_server.load(sound.path.as_str()))
+                        .collect(),
+                    weights: weights.ok(),
+                    volumes: desc.sounds.iter().map(|sound| sound.volume).collect(),
+                    pitch_variation: desc.pitch_variation,
+                    volume_variation: desc.volume_variation,
+                    cooldown: desc.cooldown,
+                    last_played: None,
+                },
+            );
+        }
+
+        info!(
+            "loaded {} sound groups from {}",
+            groups.len(),
+            SOUND_MANIFEST
+        );
+        sound_map.groups = groups;
+    }
+}
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 
 use super::{despawn_screen, GameState};
+use crate::audio::AudioSettings;
 
 use shapeshifter_level_maker::{input::Action, util::SpawnPolyKeepPoly};
 
@@ -41,13 +42,17 @@ fn splash_setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     audio: Res<Audio>,
+    audio_settings: Res<AudioSettings>,
     mut spawn_polykeep_event_writer: EventWriter<SpawnPolyKeepPoly>,
 ) {
     // let icon = asset_server.load("branding/icon.png");
     let scissor2 = asset_server.load("sounds/Scissor Cut/Scissor paper cut 2.ogg");
     let logo_sound = asset_server.load("sounds/Shapeshift Logo v2.ogg");
 
-    audio.play(logo_sound.clone());
+    audio.play_with_settings(
+        logo_sound.clone(),
+        PlaybackSettings::ONCE.with_volume(audio_settings.effects_volume()),
+    );
 
     commands.insert_resource(LogoSoundsHandle {
         cut: scissor2,
@@ -101,6 +106,7 @@ fn countdown(
     mut cut_timer2: ResMut<CutTimer2>,
     logo_sounds: Res<LogoSoundsHandle>,
     audio: Res<Audio>,
+    audio_settings: Res<AudioSettings>,
     mut action_event_writer: EventWriter<shapeshifter_level_maker::input::Action>,
 ) {
     if timer.tick(time.delta()).finished() {
@@ -132,6 +138,9 @@ fn countdown(
 
         action_event_writer.send(shapeshifter_level_maker::input::Action::EndCutSegment { end });
 
-        audio.play(logo_sounds.cut.clone());
+        audio.play_with_settings(
+            logo_sounds.cut.clone(),
+            PlaybackSettings::ONCE.with_volume(audio_settings.effects_volume()),
+        );
     }
 }
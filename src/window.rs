@@ -0,0 +1,277 @@
+use bevy::{
+    prelude::*,
+    render::camera::ScalingMode,
+    utils::Duration,
+    window::{PresentMode, WindowMode, WindowMoved, WindowPosition, WindowResized},
+};
+
+use serde::{Deserialize, Serialize};
+
+use shapeshifter_level_maker::bindings::{Bindings, ButtonState, InputAction};
+use shapeshifter_level_maker::gamepad::GamepadInput;
+
+use std::io::{Read, Write};
+
+// saved in the current directory, like the bindings
+pub const WINDOW_SETTINGS_FILE: &str = "window_settings.json";
+
+// the levels and the menus are laid out for this size, and scaled to the window
+pub const DESIGN_WIDTH: f32 = 1280.0;
+pub const DESIGN_HEIGHT: f32 = 720.0;
+
+// a resize or a move is saved once the window has stopped changing for this long
+const SAVE_DELAY_MILLIS: u64 = 500;
+
+pub struct WindowSettingsPlugin;
+
+impl Plugin for WindowSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(scale_ui_to_window)
+            .add_system(toggle_fullscreen)
+            .add_system(record_window_changes);
+    }
+}
+
+//
+//
+// The size, position and mode of the window. The size and the position are those of the
+// windowed mode, which is restored when leaving fullscreen. Without a position, the
+// window is placed by the system, which is what the wasm build needs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WindowSettings {
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub position: Option<[i32; 2]>,
+    pub vsync: bool,
+    pub fullscreen: bool,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            width: DESIGN_WIDTH,
+            height: DESIGN_HEIGHT,
+            position: None,
+            vsync: true,
+            fullscreen: false,
+        }
+    }
+}
+
+impl WindowSettings {
+    fn mode(&self) -> WindowMode {
+        if self.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        }
+    }
+
+    fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::Fifo
+        } else {
+            PresentMode::Immediate
+        }
+    }
+
+    // inserted before the DefaultPlugins, which create the window from it
+    pub fn descriptor(&self) -> WindowDescriptor {
+        WindowDescriptor {
+            title: "Shapeshifter".to_string(),
+            width: self.width,
+            height: self.height,
+            position: match self.position {
+                Some([x, y]) => WindowPosition::At(Vec2::new(x as f32, y as f32)),
+                None => WindowPosition::Automatic,
+            },
+            present_mode: self.present_mode(),
+            mode: self.mode(),
+            // the canvas follows the size of the page
+            fit_canvas_to_parent: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn load() -> Self {
+        let mut path = std::env::current_dir().unwrap_or_default();
+        path.push(WINDOW_SETTINGS_FILE);
+        Self::load_from(&path)
+    }
+
+    fn load_from(path: &std::path::Path) -> Self {
+        let mut contents = String::new();
+        if let Ok(mut file) = std::fs::File::open(path) {
+            if file.read_to_string(&mut contents).is_ok() {
+                match serde_json::from_str::<WindowSettings>(&contents) {
+                    Ok(settings) => return settings,
+                    Err(err) => warn!("could not read {:?}, using defaults: {}", path, err),
+                }
+            }
+        }
+
+        Self::default()
+    }
+
+    pub fn save(&self) {
+        let mut path = std::env::current_dir().unwrap_or_default();
+        path.push(WINDOW_SETTINGS_FILE);
+        self.save_to(&path);
+    }
+
+    fn save_to(&self, path: &std::path::Path) {
+        let serialized = serde_json::to_string_pretty(&self).unwrap();
+        let result = std::fs::File::create(path)
+            .and_then(|mut output| output.write_all(serialized.as_bytes()));
+        if let Err(err) = result {
+            warn!("could not save {:?}: {}", path, err);
+        }
+    }
+}
+
+// the play area shows at least the design resolution, whatever the shape of the window
+pub fn design_camera() -> Camera2dBundle {
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scaling_mode = ScalingMode::Auto {
+        min_width: DESIGN_WIDTH,
+        min_height: DESIGN_HEIGHT,
+    };
+    camera
+}
+
+//
+//
+// The UI is laid out in pixels of the design resolution, and scaled like the play area
+fn scale_ui_to_window(
+    mut resized_events: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    mut ui_scale: ResMut<UiScale>,
+    mut scaled: Local<bool>,
+) {
+    if resized_events.iter().last().is_none() && *scaled {
+        return;
+    }
+
+    if let Some(window) = windows.get_primary() {
+        let scale = (window.width() / DESIGN_WIDTH).min(window.height() / DESIGN_HEIGHT);
+        if scale > 0.0 {
+            ui_scale.scale = scale as f64;
+            *scaled = true;
+        }
+    }
+}
+
+fn toggle_fullscreen(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<GamepadInput>,
+    bindings: Res<Bindings>,
+    mut settings: ResMut<WindowSettings>,
+    mut windows: ResMut<Windows>,
+) {
+    let state = ButtonState {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad: &gamepad_input,
+        wheel_up: false,
+        wheel_down: false,
+    };
+    if !bindings.just_pressed(InputAction::ToggleFullscreen, &state) {
+        return;
+    }
+
+    settings.fullscreen = !settings.fullscreen;
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_mode(settings.mode());
+    }
+    settings.save();
+}
+
+//
+//
+// Keeps the size and the position of the window in the settings. They are saved once the
+// window has stopped changing, not on every event of a drag. The events that do not change
+// the settings, like the resize of the window creation, are not saved.
+fn record_window_changes(
+    mut resized_events: EventReader<WindowResized>,
+    mut moved_events: EventReader<WindowMoved>,
+    windows: Res<Windows>,
+    time: Res<Time>,
+    mut settings: ResMut<WindowSettings>,
+    mut save_timer: Local<Option<Timer>>,
+) {
+    let primary_id = windows.get_primary().map(|window| window.id());
+    let windowed = !settings.fullscreen;
+
+    for event in resized_events.iter() {
+        let size_changed = (settings.width, settings.height) != (event.width, event.height);
+        if Some(event.id) == primary_id && windowed && size_changed {
+            settings.width = event.width;
+            settings.height = event.height;
+            *save_timer = Some(Timer::new(Duration::from_millis(SAVE_DELAY_MILLIS), false));
+        }
+    }
+    for event in moved_events.iter() {
+        let position = Some([event.position.x, event.position.y]);
+        if Some(event.id) == primary_id && windowed && settings.position != position {
+            settings.position = position;
+            *save_timer = Some(Timer::new(Duration::from_millis(SAVE_DELAY_MILLIS), false));
+        }
+    }
+
+    if let Some(timer) = save_timer.as_mut() {
+        if timer.tick(time.delta()).just_finished() {
+            settings.save();
+            *save_timer = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip_through_the_file() {
+        let path =
+            std::env::temp_dir().join(format!("window_settings_{}.json", std::process::id()));
+        let settings = WindowSettings {
+            width: 1600.0,
+            height: 900.0,
+            position: Some([-20, 40]),
+            vsync: false,
+            fullscreen: true,
+        };
+        settings.save_to(&path);
+        let loaded = WindowSettings::load_from(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded, settings);
+    }
+
+    #[test]
+    fn missing_or_invalid_file_gives_the_defaults() {
+        let path =
+            std::env::temp_dir().join(format!("no_window_settings_{}.json", std::process::id()));
+        assert_eq!(WindowSettings::load_from(&path), WindowSettings::default());
+
+        std::fs::write(&path, "{ \"width\": ").unwrap();
+        let loaded = WindowSettings::load_from(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded, WindowSettings::default());
+    }
+
+    #[test]
+    fn without_a_position_the_window_is_placed_by_the_system() {
+        let settings: WindowSettings = serde_json::from_str(
+            r#"{ "width": 1280.0, "height": 720.0, "vsync": true, "fullscreen": false }"#,
+        )
+        .unwrap();
+        assert_eq!(settings.position, None);
+        assert!(matches!(
+            settings.descriptor().position,
+            WindowPosition::Automatic
+        ));
+    }
+}
@@ -1,15 +1,25 @@
 #import bevy_pbr::mesh_view_bindings
 
-// This uniform is not needed here, as color attributes are being sent.
-// But in the future, the uniform will be used to highlight selected 
-// fill meshes
-struct PolygonMaterial {
-    color: vec4<f32>, 
-
+// A dashed cut line. The mesh is a unit quad stretched to the segment: uv.x goes from
+// the start to the end of the segment, uv.y across it.
+struct CutMaterial {
+    color: vec4<f32>,
+    invalid_color: vec4<f32>,
+    length: f32,
+    thickness: f32,
+    dash_length: f32,
+    time: f32,
+    invalid: f32,
 };
 
 @group(1) @binding(0)
-var<uniform> uni: PolygonMaterial;
+var<uniform> uni: CutMaterial;
+
+// pixels per second
+let DASH_SPEED: f32 = 30.0;
+
+// opacity of the gaps between the dashes
+let GAP_ALPHA: f32 = 0.3;
 
 // // Converts a color from sRGB gamma to linear light gamma
 fn toLinear(sRGB: vec4<f32>) -> vec4<f32>
@@ -26,9 +36,22 @@ fn fragment(
     #import bevy_sprite::mesh2d_vertex_output
 ) -> @location(0) vec4<f32> {
 
-        // let selector_color = vec4<f32>(0.0, 0.0, 0.0, 0.5);
-        // let mixed_color = mix(uni.color, selector_color, 0.2);
-        // return mixed_color;
-    
-    return toLinear(uni.color);
-}
\ No newline at end of file
+    var color = uni.color;
+    if uni.invalid > 0.5 {
+        color = uni.invalid_color;
+    }
+
+    // the dashes run towards the end of the segment, with a one pixel ramp at their ends
+    let period = max(uni.dash_length * 2.0, 2.0);
+    let phase = fract((uv.x * uni.length - uni.time * DASH_SPEED) / period);
+    let ramp = 1.0 / period;
+    let dash = smoothstep(0.0, ramp, phase) * (1.0 - smoothstep(0.5, 0.5 + ramp, phase));
+
+    // smooth sides, also one pixel wide
+    let half_thickness = uni.thickness * 0.5;
+    let across = abs(uv.y - 0.5) * uni.thickness;
+    let side = 1.0 - smoothstep(max(half_thickness - 1.0, 0.0), half_thickness, across);
+
+    color.a = color.a * mix(GAP_ALPHA, 1.0, dash) * side;
+    return toLinear(color);
+}
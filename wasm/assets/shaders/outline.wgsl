@@ -0,0 +1,51 @@
+#import bevy_pbr::mesh_view_bindings
+
+// The outline of a polygon or a target. uv.x is the distance along the outline and uv.y
+// goes from -1 to 1 across the stroke, which is tessellated `geometry_width` wide.
+struct OutlineMaterial {
+    color: vec4<f32>,
+    width: f32,
+    geometry_width: f32,
+    dashed: f32,
+    dash_length: f32,
+    time: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> uni: OutlineMaterial;
+
+// pixels per second
+let DASH_SPEED: f32 = 20.0;
+
+// // Converts a color from sRGB gamma to linear light gamma
+fn toLinear(sRGB: vec4<f32>) -> vec4<f32>
+{
+    let cutoff = vec4<f32>(sRGB < vec4<f32>(0.04045));
+    let higher = pow((sRGB + vec4<f32>(0.055))/vec4<f32>(1.055), vec4<f32>(2.4));
+    let lower = sRGB/vec4<f32>(12.92);
+    return mix(higher, lower, cutoff);
+}
+
+@fragment
+fn fragment(
+    @builtin(position) position: vec4<f32>,
+    #import bevy_sprite::mesh2d_vertex_output
+) -> @location(0) vec4<f32> {
+
+    var color = uni.color;
+
+    // anti-aliased sides: the alpha falls off over one pixel
+    let distance = abs(uv.y) * uni.geometry_width * 0.5;
+    let half_width = uni.width * 0.5;
+    var alpha = 1.0 - smoothstep(half_width - 0.5, half_width + 0.5, distance);
+
+    if uni.dashed > 0.5 {
+        let period = max(uni.dash_length * 2.0, 2.0);
+        let phase = fract((uv.x - uni.time * DASH_SPEED) / period);
+        let ramp = 1.0 / period;
+        alpha = alpha * smoothstep(0.0, ramp, phase) * (1.0 - smoothstep(0.5, 0.5 + ramp, phase));
+    }
+
+    color.a = color.a * alpha;
+    return toLinear(color);
+}